@@ -0,0 +1,353 @@
+use std::ops::Range;
+
+use solana_client::rpc_filter::RpcFilterType;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::decoders::{
+  DecoderRegistry,
+  metadata_address,
+};
+use crate::solana_utils::anchor_idl_address;
+use crate::traits::AccountSource;
+
+/// The labels of raw data fields, covered by the byte-range diff instead.
+const RAW_FIELDS: [&str; 2] = ["Data", "Data Length"];
+
+/// A decoded field that differs between the cached and the live account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+  pub label: String,
+  /// The cached value, `None` if the field is new.
+  pub cached: Option<String>,
+  /// The live value, `None` if the field is gone.
+  pub live: Option<String>,
+}
+
+/// AccountDiff holds the differences between a cached and a live account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+  pub address: Pubkey,
+  /// The cached and live lamports, if they differ.
+  pub lamports: Option<(u64, u64)>,
+  /// The cached and live owners, if they differ.
+  pub owner: Option<(Pubkey, Pubkey)>,
+  /// The cached and live data lengths, if they differ.
+  pub data_len: Option<(usize, usize)>,
+  /// The decoded fields that differ, if the account could be decoded.
+  pub fields: Vec<FieldChange>,
+  /// The byte ranges that differ, if no decoded field shows the change.
+  pub byte_ranges: Vec<Range<usize>>,
+}
+
+/// Implementation of AccountDiff.
+impl AccountDiff {
+  /// Check if the cached account is up to date.
+  pub fn is_empty(&self) -> bool {
+    self.lamports.is_none()
+      && self.owner.is_none()
+      && self.data_len.is_none()
+      && self.fields.is_empty()
+      && self.byte_ranges.is_empty()
+  }
+}
+
+/// Compare a cached account with the live one.
+/// Decoded fields are compared when both accounts can be decoded, and the data
+/// byte by byte when they can't or no decoded field differs.
+/// # Arguments
+/// * `registry` - The decoders.
+/// * `address` - The account address.
+/// * `cached` - The cached account and the source of related cached accounts.
+/// * `live` - The live account and the source of related live accounts.
+pub fn diff_accounts(
+  registry: &DecoderRegistry,
+  address: &Pubkey,
+  cached: (&Account, &dyn AccountSource),
+  live: (&Account, &dyn AccountSource),
+) -> AccountDiff {
+  let ((cached, cached_source), (live, live_source)) = (cached, live);
+
+  // Anchor IDLs aren't cloned, and Metaplex metadata only with `clone_metadata`,
+  // so the cached side reads them from the cluster.
+  let cached_source = LiveRelatedSource {
+    source: cached_source,
+    live_source,
+    idl_address: anchor_idl_address(&cached.owner).ok(),
+    metadata_address: metadata_address(address),
+  };
+  let decoded_cached = registry.decode(address, cached, &cached_source);
+  let decoded_live = registry.decode(address, live, live_source);
+  let fields = match (decoded_cached, decoded_live) {
+    (Ok(decoded_cached), Ok(decoded_live)) => diff_fields(&decoded_cached.fields, &decoded_live.fields),
+    _ => vec![],
+  };
+
+  let byte_ranges = match fields.is_empty() {
+    true => byte_ranges(&cached.data, &live.data),
+    false => vec![],
+  };
+
+  AccountDiff {
+    address: *address,
+    lamports: changed(cached.lamports, live.lamports),
+    owner: changed(cached.owner, live.owner),
+    data_len: changed(cached.data.len(), live.data.len()),
+    fields,
+    byte_ranges,
+  }
+}
+
+/// LiveRelatedSource reads the accounts related to a cached account that
+/// aren't cloned with it from the live source, and every other account from
+/// the cached one.
+struct LiveRelatedSource<'a> {
+  source: &'a dyn AccountSource,
+  live_source: &'a dyn AccountSource,
+  /// The IDL account of the owner of the cached account, always read live.
+  idl_address: Option<Pubkey>,
+  /// The Metaplex metadata account of the cached account, read live unless
+  /// it was cloned.
+  metadata_address: Pubkey,
+}
+
+/// Implementation of AccountSource for LiveRelatedSource.
+impl AccountSource for LiveRelatedSource<'_> {
+  fn get_account(&self, address: &Pubkey) -> anyhow::Result<Option<Account>> {
+    if self.idl_address == Some(*address) {
+      return self.live_source.get_account(address);
+    }
+
+    match self.source.get_account(address)? {
+      None if *address == self.metadata_address => self.live_source.get_account(address),
+      account => Ok(account),
+    }
+  }
+
+  fn get_program_accounts(
+    &self,
+    program_id: &Pubkey,
+    filters: Vec<RpcFilterType>,
+  ) -> anyhow::Result<Vec<(Pubkey, Account)>> {
+    self.source.get_program_accounts(program_id, filters)
+  }
+}
+
+/// Get both values if they differ.
+fn changed<T: PartialEq>(cached: T, live: T) -> Option<(T, T)> {
+  (cached != live).then_some((cached, live))
+}
+
+/// Compare decoded fields by label, in the order of the live fields.
+fn diff_fields(cached: &[(String, String)], live: &[(String, String)]) -> Vec<FieldChange> {
+  let find = |fields: &[(String, String)], label: &str| {
+    fields
+      .iter()
+      .find(|(field, _)| field == label)
+      .map(|(_, value)| value.clone())
+  };
+
+  let mut labels: Vec<&str> = live.iter().map(|(label, _)| label.as_str()).collect();
+  for (label, _) in cached.iter() {
+    if !labels.contains(&label.as_str()) {
+      labels.push(label);
+    }
+  }
+
+  labels
+    .into_iter()
+    .filter(|label| !RAW_FIELDS.contains(label))
+    .filter_map(|label| {
+      let (cached, live) = (find(cached, label), find(live, label));
+      (cached != live).then(|| FieldChange {
+        label: label.to_string(),
+        cached,
+        live,
+      })
+    })
+    .collect()
+}
+
+/// Get the byte ranges that differ between two buffers. Bytes past the end of
+/// the shorter buffer differ.
+pub fn byte_ranges(cached: &[u8], live: &[u8]) -> Vec<Range<usize>> {
+  let mut ranges: Vec<Range<usize>> = vec![];
+  for offset in 0..cached.len().max(live.len()) {
+    if cached.get(offset) == live.get(offset) {
+      continue;
+    }
+
+    match ranges.last_mut() {
+      Some(range) if range.end == offset => range.end += 1,
+      _ => ranges.push(offset..offset + 1),
+    }
+  }
+
+  ranges
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::decoders::{
+    MetaplexCollection,
+    MetaplexMetadata,
+    TOKEN_METADATA_PROGRAM_ID,
+  };
+  use crate::mock_rpc::{
+    MockRpcFixtures,
+    serve_mock_rpc,
+  };
+  use crate::test_support::{
+    account,
+    encode_idl,
+    encode_metadata,
+    mint_account,
+  };
+  use solana_client::rpc_client::RpcClient;
+
+  #[test]
+  fn it_should_diff_byte_ranges() -> anyhow::Result<()> {
+    assert_eq!(byte_ranges(&[1, 2, 3, 4], &[1, 9, 9, 4, 5, 6]), vec![1..3, 4..6]);
+    assert!(byte_ranges(&[1, 2], &[1, 2]).is_empty());
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_diff_decoded_fields() -> anyhow::Result<()> {
+    let mint = |supply| mint_account(supply, 6);
+
+    let server = serve_mock_rpc(MockRpcFixtures::new(), 0)?;
+    let client = RpcClient::new(server.url());
+    let registry = DecoderRegistry::default();
+    let address = Pubkey::new_unique();
+
+    let diff = diff_accounts(&registry, &address, (&mint(100), &client), (&mint(100), &client));
+    assert!(diff.is_empty());
+
+    let diff = diff_accounts(&registry, &address, (&mint(100), &client), (&mint(250), &client));
+    assert_eq!(diff.data_len, None);
+    assert_eq!(
+      diff.fields,
+      vec![FieldChange {
+        label: "Supply".to_string(),
+        cached: Some("100".to_string()),
+        live: Some("250".to_string()),
+      }]
+    );
+    assert!(diff.byte_ranges.is_empty());
+
+    let owner = Pubkey::new_unique();
+    let unknown = |data: Vec<u8>| account(owner, data);
+    let diff = diff_accounts(
+      &registry,
+      &address,
+      (&unknown(vec![0; 4]), &client),
+      (&unknown(vec![0, 1, 0, 0, 0]), &client),
+    );
+    assert_eq!(diff.data_len, Some((4, 5)));
+    assert!(diff.fields.is_empty());
+    assert_eq!(diff.byte_ranges, vec![1..2, 4..5]);
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_diff_anchor_accounts_with_the_live_idl() -> anyhow::Result<()> {
+    let program_id = Pubkey::new_unique();
+    let idl_data = encode_idl(
+      r#"{
+        "metadata": { "name": "counter" },
+        "accounts": [{ "name": "Counter", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] }],
+        "types": [{
+          "name": "Counter",
+          "type": { "kind": "struct", "fields": [{ "name": "count", "type": "u64" }] }
+        }]
+      }"#,
+    )?;
+
+    // Only the cluster has the IDL account, like a cache without it.
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_account(
+      crate::solana_utils::anchor_idl_address(&program_id)?,
+      account(program_id, idl_data),
+    );
+    let live_server = serve_mock_rpc(fixtures, 0)?;
+    let cached_server = serve_mock_rpc(MockRpcFixtures::new(), 0)?;
+    let (live_client, cached_client) = (
+      RpcClient::new(live_server.url()),
+      RpcClient::new(cached_server.url()),
+    );
+
+    let counter = |count: u64| {
+      let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+      data.extend(count.to_le_bytes());
+      account(program_id, data)
+    };
+
+    let registry = DecoderRegistry::default();
+    let diff = diff_accounts(
+      &registry,
+      &Pubkey::new_unique(),
+      (&counter(1), &cached_client),
+      (&counter(2), &live_client),
+    );
+    assert_eq!(
+      diff.fields,
+      vec![FieldChange {
+        label: "Count".to_string(),
+        cached: Some("1".to_string()),
+        live: Some("2".to_string()),
+      }]
+    );
+    assert!(diff.byte_ranges.is_empty());
+
+    // The registry keeps no IDL, a source without it decodes the raw data.
+    let decoded = registry.decode(&Pubkey::new_unique(), &counter(1), &cached_client)?;
+    assert_eq!(decoded.kind, "Unknown");
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_diff_mints_with_the_live_metadata() -> anyhow::Result<()> {
+    let mint = Pubkey::new_unique();
+    let metadata = MetaplexMetadata {
+      update_authority: Pubkey::new_unique(),
+      mint,
+      name: "USD Coin".to_string(),
+      symbol: "USDC".to_string(),
+      uri: String::new(),
+      seller_fee_basis_points: 0,
+      creators: vec![],
+      primary_sale_happened: false,
+      is_mutable: true,
+      token_standard: None,
+      collection: Some(MetaplexCollection {
+        key: Pubkey::new_unique(),
+        verified: false,
+      }),
+    };
+
+    // Only the cluster has the metadata account, like a cache without `clone_metadata`.
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_account(
+      metadata_address(&mint),
+      account(TOKEN_METADATA_PROGRAM_ID, encode_metadata(&metadata)),
+    );
+    let live_server = serve_mock_rpc(fixtures, 0)?;
+    let cached_server = serve_mock_rpc(MockRpcFixtures::new(), 0)?;
+    let (live_client, cached_client) = (
+      RpcClient::new(live_server.url()),
+      RpcClient::new(cached_server.url()),
+    );
+
+    let registry = DecoderRegistry::default();
+    let diff = diff_accounts(
+      &registry,
+      &mint,
+      (&mint_account(100, 6), &cached_client),
+      (&mint_account(100, 6), &live_client),
+    );
+    assert!(diff.is_empty(), "{diff:?}");
+    Ok(())
+  }
+}
@@ -0,0 +1,97 @@
+use anyhow::Context;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Pack a directory into a gzip compressed tar archive.
+/// Regular files, directories and symlinks are archived with paths relative
+/// to the directory. Other entries, like the admin socket of the validator,
+/// are skipped.
+/// # Arguments
+/// * `dir` - The directory to pack.
+/// * `archive` - The path of the `.tar.gz` file to write.
+pub fn pack_tar_gz(dir: &Path, archive: &Path) -> anyhow::Result<()> {
+  if let Some(parent) = archive.parent() {
+    fs::create_dir_all(parent)?;
+  }
+
+  let file = fs::File::create(archive).with_context(|| format!("Failed to create archive: {archive:?}"))?;
+  let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+  builder.follow_symlinks(false);
+  append_dir(&mut builder, dir, Path::new(""))?;
+  builder.into_inner()?.finish()?.sync_all()?;
+  Ok(())
+}
+
+/// Unpack a gzip compressed tar archive into a directory.
+/// Entries escaping the directory are skipped.
+/// # Arguments
+/// * `archive` - The path of the `.tar.gz` file.
+/// * `dir` - The directory to unpack into.
+pub fn unpack_tar_gz(archive: &Path, dir: &Path) -> anyhow::Result<()> {
+  let file = fs::File::open(archive).with_context(|| format!("Failed to open archive: {archive:?}"))?;
+  fs::create_dir_all(dir)?;
+  tar::Archive::new(GzDecoder::new(file))
+    .unpack(dir)
+    .with_context(|| format!("Failed to unpack archive: {archive:?}"))
+}
+
+/// Append the entries of a directory to a tar archive, recursively.
+fn append_dir<W: Write>(builder: &mut tar::Builder<W>, dir: &Path, prefix: &Path) -> anyhow::Result<()> {
+  let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+  entries.sort_by_key(|entry| entry.file_name());
+
+  for entry in entries {
+    let file_type = entry.file_type()?;
+    let name = prefix.join(entry.file_name());
+    if file_type.is_dir() {
+      builder.append_dir(&name, entry.path())?;
+      append_dir(builder, &entry.path(), &name)?;
+    } else if file_type.is_file() || file_type.is_symlink() {
+      builder.append_path_with_name(entry.path(), &name)?;
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use assert_fs::TempDir;
+  use std::os::unix::net::UnixListener;
+
+  #[test]
+  fn it_should_pack_and_unpack_a_directory() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let source = temp_dir.path().join("ledger");
+    let nested = source.join("rocksdb").join("a".repeat(80)).join("b".repeat(60));
+    fs::create_dir_all(&nested)?;
+    fs::write(source.join("genesis.bin"), vec![7; 1_000])?;
+    fs::write(nested.join("000001.sst"), b"rocks")?;
+    fs::create_dir_all(source.join("empty"))?;
+    std::os::unix::fs::symlink("genesis.bin", source.join("genesis.link"))?;
+    let _socket = UnixListener::bind(source.join("admin.rpc"))?;
+
+    let archive = temp_dir.path().join("snapshot").join("ledger.tar.gz");
+    pack_tar_gz(&source, &archive)?;
+
+    let target = temp_dir.path().join("restored");
+    unpack_tar_gz(&archive, &target)?;
+    assert_eq!(fs::read(target.join("genesis.bin"))?, vec![7; 1_000]);
+    assert_eq!(
+      fs::read(target.join(nested.strip_prefix(&source)?).join("000001.sst"))?,
+      b"rocks"
+    );
+    assert!(target.join("empty").is_dir());
+    assert_eq!(
+      fs::read_link(target.join("genesis.link"))?,
+      Path::new("genesis.bin")
+    );
+    assert!(!target.join("admin.rpc").exists());
+    Ok(())
+  }
+}
@@ -0,0 +1,268 @@
+use std::fs;
+use std::path::{
+  Path,
+  PathBuf,
+};
+use std::str::FromStr as _;
+
+use hashbrown::HashMap;
+use solana_loader_v3_interface::get_program_data_address;
+use solana_loader_v3_interface::state::UpgradeableLoaderState;
+use solana_sdk::account::Account;
+use solana_sdk::bpf_loader_upgradeable;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+
+use crate::lumos_context::LumosContext;
+use crate::traits::AccountSource;
+use crate::utils::read_account_json;
+
+/// A program binary loaded into the validator.
+#[derive(Debug, Clone)]
+struct CachedProgram {
+  /// The path of the binary.
+  path: PathBuf,
+  /// The upgrade authority, `None` if the program is loaded immutable.
+  authority: Option<Pubkey>,
+}
+
+/// CacheSource reads the accounts and programs of the cache, the state the
+/// validator is started with, instead of the cluster.
+///
+/// Accounts are read from the `{address}.json` files of the account cache, or
+/// of the accounts restored from a snapshot, which take precedence.
+/// Programs are read from the `{address}.so` files of the program cache, or
+/// the local binary of the config, and served as upgradeable loader program
+/// and ProgramData accounts.
+#[derive(Debug)]
+pub struct CacheSource {
+  /// The account directories, in the order the validator loads them.
+  account_dirs: Vec<PathBuf>,
+  programs: HashMap<Pubkey, CachedProgram>,
+}
+
+/// Implementation of CacheSource.
+impl CacheSource {
+  /// Create a source reading the cache of a context.
+  /// # Arguments
+  /// * `context` - The Lumos context.
+  pub fn new(context: &LumosContext) -> anyhow::Result<Self> {
+    let account_dirs = vec![
+      PathBuf::from(context.account_cache_dir()?),
+      PathBuf::from(context.restored_account_dir()?),
+    ];
+    let program_dir: &str = &context.program_cache_dir()?;
+
+    // Cached binaries without a config entry are loaded without authority.
+    let mut programs = HashMap::new();
+    for (address, path) in cached_files(Path::new(program_dir), "so")? {
+      programs.insert(
+        address,
+        CachedProgram {
+          path,
+          authority: None,
+        },
+      );
+    }
+
+    for program in context.config.program.values() {
+      let Ok(address) = Pubkey::from_str(&program.address) else {
+        continue;
+      };
+      let authority = program
+        .authority
+        .as_deref()
+        .and_then(|authority| Pubkey::from_str(authority).ok());
+      let path = program.program_file(context)?;
+      programs.insert(address, CachedProgram { path, authority });
+    }
+
+    Ok(Self {
+      account_dirs,
+      programs,
+    })
+  }
+
+  /// Get the addresses of all cached accounts and programs.
+  pub fn addresses(&self) -> anyhow::Result<Vec<Pubkey>> {
+    let mut addresses: Vec<Pubkey> = self.programs.keys().copied().collect();
+    for account_dir in self.account_dirs.iter() {
+      addresses.extend(
+        cached_files(account_dir, "json")?
+          .into_iter()
+          .map(|(address, _)| address),
+      );
+    }
+
+    addresses.sort();
+    addresses.dedup();
+    Ok(addresses)
+  }
+
+  /// Check if an address is a cached program or its ProgramData account,
+  /// whose accounts are created from the binary rather than cloned.
+  pub fn is_program(&self, address: &Pubkey) -> bool {
+    self
+      .programs
+      .keys()
+      .any(|program_id| program_id == address || get_program_data_address(program_id) == *address)
+  }
+
+  /// Get the program or ProgramData account of a cached program binary.
+  fn program_account(&self, address: &Pubkey) -> anyhow::Result<Option<Account>> {
+    if let Some(program) = self.programs.get(address) {
+      if !program.path.exists() {
+        return Ok(None);
+      }
+
+      return Ok(Some(upgradeable_program_account(address)?));
+    }
+
+    let program = self
+      .programs
+      .iter()
+      .find(|(program_id, _)| get_program_data_address(program_id) == *address);
+    let Some((_, program)) = program else {
+      return Ok(None);
+    };
+    if !program.path.exists() {
+      return Ok(None);
+    }
+
+    let elf = fs::read(&program.path)?;
+    Ok(Some(upgradeable_program_data_account(&elf, program.authority)?))
+  }
+}
+
+/// Create the upgradeable loader account of a program, pointing at its
+/// ProgramData account.
+/// # Arguments
+/// * `program_id` - The program id.
+pub fn upgradeable_program_account(program_id: &Pubkey) -> anyhow::Result<Account> {
+  let data = bincode::serialize(&UpgradeableLoaderState::Program {
+    programdata_address: get_program_data_address(program_id),
+  })?;
+  Ok(loader_account(data, true))
+}
+
+/// Create the ProgramData account of a program binary deployed at slot 0.
+/// # Arguments
+/// * `elf` - The program binary.
+/// * `authority` - The upgrade authority, `None` if the program is immutable.
+pub fn upgradeable_program_data_account(elf: &[u8], authority: Option<Pubkey>) -> anyhow::Result<Account> {
+  let mut data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+    slot: 0,
+    upgrade_authority_address: authority,
+  })?;
+  data.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+  data.extend_from_slice(elf);
+  Ok(loader_account(data, false))
+}
+
+/// Implementation of AccountSource for CacheSource.
+impl AccountSource for CacheSource {
+  fn get_account(&self, address: &Pubkey) -> anyhow::Result<Option<Account>> {
+    for account_dir in self.account_dirs.iter().rev() {
+      let path = account_dir.join(format!("{address}.json"));
+      if path.exists() {
+        return Ok(Some(read_account_json(&path)?.1));
+      }
+    }
+
+    self.program_account(address)
+  }
+}
+
+/// Create a rent exempt account of the upgradeable loader.
+fn loader_account(data: Vec<u8>, executable: bool) -> Account {
+  Account {
+    lamports: Rent::default().minimum_balance(data.len()),
+    data,
+    owner: bpf_loader_upgradeable::ID,
+    executable,
+    rent_epoch: 0,
+  }
+}
+
+/// List the `{address}.{extension}` files of a directory.
+fn cached_files(dir: &Path, extension: &str) -> anyhow::Result<Vec<(Pubkey, PathBuf)>> {
+  if !dir.exists() {
+    return Ok(vec![]);
+  }
+
+  let mut files = vec![];
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if !path.extension().is_some_and(|ext| ext == extension) {
+      continue;
+    }
+
+    if let Some(address) = path
+      .file_stem()
+      .and_then(|stem| Pubkey::from_str(&stem.to_string_lossy()).ok())
+    {
+      files.push((address, path));
+    }
+  }
+
+  Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::decoders::DecoderRegistry;
+  use crate::schema::ConfigRoot;
+  use crate::test_support::mint_account;
+  use crate::utils::write_account_json;
+  use assert_fs::TempDir;
+  use std::sync::Arc;
+
+  #[test]
+  fn it_should_read_cached_accounts_and_programs() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (mint, program_id, authority) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let config: ConfigRoot = toml::from_str(&format!(
+      r#"
+      [general]
+      rpc_endpoint = "http://127.0.0.1:8899"
+
+      [account]
+
+      [program.counter]
+      address = "{program_id}"
+      authority = "{authority}"
+      "#
+    ))?;
+    let cache_dir = temp_dir.path().to_str().unwrap().to_string();
+    let context = LumosContext::new(Arc::new(config), "http://127.0.0.1:8899", Some(cache_dir), false);
+
+    let account = mint_account(0, 6);
+    write_account_json(Path::new(&context.account_cache_dir()?), &mint, &account)?;
+
+    let program_dir = context.program_cache_dir()?;
+    fs::create_dir_all(&program_dir)?;
+    fs::write(
+      Path::new(&program_dir).join(format!("{program_id}.so")),
+      vec![1; 512],
+    )?;
+
+    let source = CacheSource::new(&context)?;
+    assert_eq!(source.get_account(&mint)?, Some(account));
+    assert_eq!(source.get_account(&Pubkey::new_unique())?, None);
+
+    let mut addresses = vec![mint, program_id];
+    addresses.sort();
+    assert_eq!(source.addresses()?, addresses);
+
+    let program = source.get_account(&program_id)?.unwrap();
+    let decoded = DecoderRegistry::default().decode(&program_id, &program, &source)?;
+    assert_eq!(decoded.kind, "Program");
+    assert_eq!(
+      decoded.get("Upgrade Authority"),
+      Some(authority.to_string().as_str())
+    );
+    assert_eq!(decoded.get("ELF Size"), Some("512 bytes"));
+    Ok(())
+  }
+}
@@ -1,53 +1,439 @@
-use clap::Args;
+use std::collections::HashMap;
+use std::fs;
+use std::str::FromStr as _;
+use std::sync::Arc;
+
+use anyhow::Context;
+use clap::{
+  Args,
+  ValueEnum,
+};
+use lumos_svm_lib::cache_source::CacheSource;
+use lumos_svm_lib::decoders::{
+  DecodedAccount,
+  DecoderRegistry,
+  humanize,
+};
+use lumos_svm_lib::lumos_context::LumosContext;
 use lumos_svm_lib::schema::ConfigRoot;
-use lumos_svm_lib::solana_utils::get_token_details;
-use tokio::runtime::Runtime;
+use lumos_svm_lib::solana_utils::{
+  TokenHolder,
+  get_largest_holders,
+};
+use lumos_svm_lib::traits::AccountSource;
+use serde::Serialize;
+use serde_json::{
+  Value,
+  json,
+};
+use solana_account_decoder::parse_token::is_known_spl_token_id;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::generic_token_account::GenericTokenAccount as _;
+
+/// The output format of `analyze`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+  /// Human readable labels and values.
+  #[default]
+  Table,
+  /// JSON, see `AccountReport` for the schema.
+  Json,
+  /// YAML, with the same schema as JSON.
+  Yaml,
+}
 
 #[derive(Debug, Args)]
 pub struct Analyze {
+  #[arg(help = "Addresses or config names to analyze")]
+  addresses: Vec<String>,
+
+  #[arg(short, long, help = "File with one address or config name per line")]
+  file: Option<String>,
+
+  #[arg(long, help = "Analyze every account and program of the config")]
+  config_entries: bool,
+
+  #[arg(
+    long,
+    help = "Analyze the cached accounts and programs instead of the cluster, all of them if no address is given"
+  )]
+  cache: bool,
+
+  #[arg(short, long, value_enum, default_value_t, help = "Output format")]
+  output: OutputFormat,
+
+  #[arg(
+    long,
+    value_name = "N",
+    value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=20),
+    help = "Show the N largest holders of a mint, at most 20"
+  )]
+  holders: Option<usize>,
+
+  #[arg(
+    long,
+    requires = "holders",
+    conflicts_with = "output",
+    help = "Print the largest holders as [account.*] entries for lumos.toml"
+  )]
+  holders_toml: bool,
+}
+
+/// An analyzed account.
+struct Analysis {
+  /// The config name, if the account is a config entry.
+  name: Option<String>,
+  address: Pubkey,
+  /// The account, `None` if it doesn't exist.
+  account: Option<Account>,
+  decoded: DecodedAccount,
+  /// The largest holders, if requested for a mint.
+  holders: Option<Vec<TokenHolder>>,
+}
+
+/// The machine-readable analysis of an account.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountReport<'a> {
+  /// The config name, `null` if the account isn't a config entry.
+  name: Option<&'a str>,
+  /// The account address.
   address: String,
+  /// The detected account type, e.g. "Token Mint", or "Not Found".
+  #[serde(rename = "type")]
+  kind: &'a str,
+  /// The program owning the account, `null` if it doesn't exist.
+  owner: Option<String>,
+  /// The balance in lamports.
+  lamports: u64,
+  /// Whether the account is a program.
+  executable: bool,
+  /// The decoded account, depending on the type.
+  data: &'a Value,
+  /// The largest holders of a mint, only with `--holders`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  holders: Option<&'a [TokenHolder]>,
 }
 
 impl Analyze {
   pub fn execute(&self, config_file: &str) -> anyhow::Result<()> {
-    log::trace!("Analyzing address: {}", self.address);
+    let config = Arc::new(ConfigRoot::from_file(config_file)?);
+    let mut targets = self.targets(&config)?;
 
-    let config = ConfigRoot::from_file(config_file)?;
-    let rpc_endpoint: &str = &config.general.rpc_endpoint;
-
-    let rt = Runtime::new()?;
-    let details = rt.block_on(async { get_token_details(rpc_endpoint, &self.address).await })?;
+    if self.holders.is_some() && self.cache {
+      anyhow::bail!("`--holders` queries the cluster and can't be used with `--cache`");
+    }
 
-    println!("\nAnalysis:");
-    println!("------------------------");
-    println!("Owner: {}", details.owner);
-    println!(
-      "Mint Authority: {}",
-      details.mint_authority.as_deref().unwrap_or("None")
-    );
-    println!(
-      "Freeze Authority: {}",
-      details.freeze_authority.as_deref().unwrap_or("None")
-    );
+    let source: Box<dyn AccountSource> = match self.cache {
+      true => {
+        let rpc_endpoint: &str = &config.general.rpc_endpoint;
+        let cache_dir = config.general.cache_dir.clone();
+        let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, false);
+        let source = CacheSource::new(&context)?;
+        if targets.is_empty() && self.file.is_none() {
+          targets = self.named(&config, source.addresses()?);
+        }
 
-    match (details.extensions, &details.metadata) {
-      (true, Some(metadata)) => {
-        println!(
-          "Update Authority: {}",
-          metadata.update_authority.as_deref().unwrap_or("None")
-        );
-        println!("Token Name: {}", metadata.name.as_deref().unwrap_or("None"));
-        println!("Token Symbol: {}", metadata.symbol.as_deref().unwrap_or("None"));
-        println!("Token URI: {}", metadata.uri.as_deref().unwrap_or("None"));
+        Box::new(source)
       },
-      _ => println!("No metadata available"),
+      false => Box::new(RpcClient::new(config.general.rpc_endpoint.clone())),
+    };
+
+    if targets.is_empty() {
+      anyhow::bail!("Nothing to analyze. Pass addresses, `--file`, `--config-entries` or `--cache`.");
     }
 
-    println!("Decimals: {}", details.decimals);
-    println!("Supply: {}", details.supply);
-    println!("Is Initialized: {}", details.is_initialized);
-    println!("Extensions: {}", details.extensions);
+    log::trace!("Analyzing {} addresses", targets.len());
+
+    let registry = DecoderRegistry::for_config(&config);
+    let addresses: Vec<Pubkey> = targets.iter().map(|(_, address)| *address).collect();
+    let accounts = source.get_multiple_accounts(&addresses)?;
+
+    // A single address is shown in detail, and fails if it can't be analyzed.
+    let batch = targets.len() > 1 || self.addresses.len() != 1;
+    let mut analyses = Vec::with_capacity(targets.len());
+    for ((name, address), account) in targets.into_iter().zip(accounts) {
+      let decoded = match &account {
+        Some(account) => match registry.decode(&address, account, source.as_ref()) {
+          Ok(decoded) => decoded,
+          Err(err) if batch => DecodedAccount::new("Decode Error")
+            .field("Error", &err)
+            .with_data(json!({ "error": err.to_string() })),
+          Err(err) => return Err(err),
+        },
+        None if batch => DecodedAccount::new("Not Found").with_data(Value::Null),
+        None => anyhow::bail!("Account not found"),
+      };
+
+      analyses.push(Analysis {
+        name,
+        address,
+        account,
+        decoded,
+        holders: None,
+      });
+    }
+
+    if let Some(limit) = self.holders {
+      let [analysis] = analyses.as_mut_slice() else {
+        anyhow::bail!("`--holders` analyzes a single mint");
+      };
+      let mint = analysis
+        .account
+        .as_ref()
+        .filter(|account| {
+          is_known_spl_token_id(&account.owner)
+            && !spl_token_2022::state::Account::valid_account_data(&account.data)
+        })
+        .with_context(|| format!("`--holders` needs a token mint, not a {}", analysis.decoded.kind))?;
+
+      let client = RpcClient::new(config.general.rpc_endpoint.clone());
+      let holders = get_largest_holders(&client, &analysis.address, mint, limit)?;
+      if self.holders_toml {
+        print_holder_entries(analysis, &holders);
+        return Ok(());
+      }
+
+      analysis.holders = Some(holders);
+    }
+
+    match (self.output, batch) {
+      (OutputFormat::Table, false) => {
+        print_details(&analyses[0]);
+        if let Some(holders) = &analyses[0].holders {
+          print_holders(holders);
+        }
+      },
+      (OutputFormat::Table, true) => print_summary(&analyses),
+      (OutputFormat::Json, false) => println!("{}", serde_json::to_string_pretty(&report(&analyses[0]))?),
+      (OutputFormat::Json, true) => {
+        let reports: Vec<AccountReport> = analyses.iter().map(report).collect();
+        println!("{}", serde_json::to_string_pretty(&reports)?)
+      },
+      (OutputFormat::Yaml, false) => print!("{}", serde_yaml::to_string(&report(&analyses[0]))?),
+      (OutputFormat::Yaml, true) => {
+        let reports: Vec<AccountReport> = analyses.iter().map(report).collect();
+        print!("{}", serde_yaml::to_string(&reports)?)
+      },
+    }
 
     Ok(())
   }
+
+  /// Get the addresses to analyze, with their config name if they have one.
+  fn targets(&self, config: &ConfigRoot) -> anyhow::Result<Vec<(Option<String>, Pubkey)>> {
+    let mut values = self.addresses.clone();
+    if let Some(file) = &self.file {
+      let content = fs::read_to_string(file).with_context(|| format!("Failed to read {file}"))?;
+      values.extend(
+        content
+          .lines()
+          .map(|line| line.trim())
+          .filter(|line| !line.is_empty() && !line.starts_with('#'))
+          .map(|line| line.to_string()),
+      );
+    }
+
+    let mut targets = vec![];
+    // Accounts and programs are listed separately, as they may share a name.
+    if self.config_entries {
+      let mut accounts: Vec<(&String, &String)> = config
+        .account
+        .iter()
+        .map(|(name, account)| (name, &account.address))
+        .collect();
+      accounts.sort();
+      let mut programs: Vec<(&String, &String)> = config
+        .program
+        .iter()
+        .map(|(name, program)| (name, &program.address))
+        .collect();
+      programs.sort();
+
+      for (name, address) in accounts.into_iter().chain(programs) {
+        let pubkey =
+          Pubkey::from_str(address).with_context(|| format!("Invalid address of config entry: {name}"))?;
+        targets.push((Some(name.clone()), pubkey));
+      }
+    }
+
+    for value in values {
+      let address = config.resolve_address(&value);
+      let pubkey = Pubkey::from_str(address).with_context(|| format!("Invalid address: {value}"))?;
+      let name = match address != value {
+        true => Some(value),
+        false => None,
+      };
+      targets.push((name, pubkey));
+    }
+
+    Ok(targets)
+  }
+
+  /// Name the addresses that are config entries.
+  fn named(&self, config: &ConfigRoot, addresses: Vec<Pubkey>) -> Vec<(Option<String>, Pubkey)> {
+    let names: HashMap<Pubkey, String> = config
+      .addresses()
+      .into_iter()
+      .map(|(name, address)| (address, name))
+      .collect();
+
+    addresses
+      .into_iter()
+      .map(|address| (names.get(&address).cloned(), address))
+      .collect()
+  }
+}
+
+/// Get the machine-readable report of an analysis.
+fn report(analysis: &Analysis) -> AccountReport<'_> {
+  AccountReport {
+    name: analysis.name.as_deref(),
+    address: analysis.address.to_string(),
+    kind: &analysis.decoded.kind,
+    owner: analysis.account.as_ref().map(|account| account.owner.to_string()),
+    lamports: analysis.account.as_ref().map_or(0, |account| account.lamports),
+    executable: analysis
+      .account
+      .as_ref()
+      .is_some_and(|account| account.executable),
+    data: &analysis.decoded.data,
+    holders: analysis.holders.as_deref(),
+  }
+}
+
+/// Get the authorities of a decoded account, e.g. the mint authority of a
+/// mint or the upgrade authority of a program.
+fn authorities(decoded: &DecodedAccount) -> Vec<String> {
+  let Some(data) = decoded.data.as_object() else {
+    return vec![];
+  };
+
+  data
+    .iter()
+    .filter(|(key, _)| key.ends_with("Authority") || *key == "authority")
+    .filter_map(|(key, value)| Some(format!("{}: {}", humanize(key), value.as_str()?)))
+    .collect()
+}
+
+/// Print a summary table of several analyzed accounts.
+fn print_summary(analyses: &[Analysis]) {
+  let rows: Vec<[String; 5]> = analyses
+    .iter()
+    .map(|analysis| {
+      let authorities = authorities(&analysis.decoded);
+      [
+        analysis.name.clone().unwrap_or("-".to_string()),
+        analysis.address.to_string(),
+        analysis.decoded.kind.clone(),
+        analysis
+          .account
+          .as_ref()
+          .map(|account| account.owner.to_string())
+          .unwrap_or("-".to_string()),
+        match authorities.is_empty() {
+          true => "-".to_string(),
+          false => authorities.join(", "),
+        },
+      ]
+    })
+    .collect();
+
+  print_table(["Name", "Address", "Type", "Owner", "Authorities"], &rows);
+}
+
+/// Print rows in aligned columns below a header.
+fn print_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) {
+  let header = header.map(String::from);
+  let mut widths = header.clone().map(|column| column.len());
+  for row in rows.iter() {
+    for (width, column) in widths.iter_mut().zip(row.iter()) {
+      *width = (*width).max(column.len());
+    }
+  }
+
+  for row in std::iter::once(&header).chain(rows.iter()) {
+    let columns: Vec<String> = row
+      .iter()
+      .zip(widths.iter())
+      .map(|(column, width)| format!("{column:<width$}"))
+      .collect();
+    println!("{}", columns.join("  ").trim_end());
+  }
+}
+
+/// Print the largest holders of a mint.
+fn print_holders(holders: &[TokenHolder]) {
+  println!("\nLargest Holders:");
+  let rows: Vec<[String; 5]> = holders
+    .iter()
+    .enumerate()
+    .map(|(index, holder)| {
+      [
+        (index + 1).to_string(),
+        holder.address.clone(),
+        holder.owner.clone().unwrap_or("-".to_string()),
+        holder.ui_amount.clone(),
+        format!("{:.2}%", holder.share),
+      ]
+    })
+    .collect();
+
+  print_table(["#", "Token Account", "Owner", "Amount", "Share"], &rows);
+}
+
+/// Print the largest holders of a mint as `[account.*]` entries, named after
+/// the config name of the mint or the start of its address.
+fn print_holder_entries(analysis: &Analysis, holders: &[TokenHolder]) {
+  let address = analysis.address.to_string();
+  let prefix = analysis.name.as_deref().unwrap_or(&address[..8]);
+  for (index, holder) in holders.iter().enumerate() {
+    if index > 0 {
+      println!();
+    }
+    match &holder.owner {
+      Some(owner) => println!("# Owner {owner}, {:.2}% of the supply", holder.share),
+      None => println!("# {:.2}% of the supply", holder.share),
+    }
+    println!("[account.{prefix}_holder_{}]", index + 1);
+    println!("address = \"{}\"", holder.address);
+  }
+}
+
+/// Print the analysis of an account as human readable labels and values.
+fn print_details(analysis: &Analysis) {
+  let decoded = &analysis.decoded;
+
+  println!("\nAnalysis:");
+  println!("------------------------");
+  if let Some(name) = &analysis.name {
+    println!("Name: {name}");
+  }
+  println!("Address: {}", analysis.address);
+  println!("Type: {}", decoded.kind);
+  if let Some(account) = &analysis.account {
+    println!("Owner: {}", account.owner);
+    println!(
+      "Balance: {} SOL",
+      account.lamports as f64 / LAMPORTS_PER_SOL as f64
+    );
+    if account.executable {
+      println!("Executable: true");
+    }
+  }
+
+  for (label, value) in decoded.fields.iter() {
+    match value.contains('\n') {
+      true => {
+        println!("{label}:");
+        for line in value.lines() {
+          println!("  {line}");
+        }
+      },
+      false => println!("{label}: {value}"),
+    }
+  }
 }
@@ -6,6 +6,7 @@ use std::time::{
 };
 
 use clap::{
+  Args,
   Parser,
   Subcommand,
   crate_authors,
@@ -15,14 +16,29 @@ use indicatif::{
   HumanDuration,
   ProgressBar,
 };
+#[cfg(feature = "inprocess")]
+use lumos_svm_lib::inprocess_rpc::run_inprocess;
 use lumos_svm_lib::lumos_context::LumosContext;
-use lumos_svm_lib::schema::ConfigRoot;
+use lumos_svm_lib::program_logs::resolve_program_filter;
+use lumos_svm_lib::schema::{
+  Backend,
+  ConfigRoot,
+};
 use lumos_svm_lib::traits::Pull;
 use lumos_svm_lib::utils::validator;
 use lumos_svm_lib::version::get_version_digits;
+use lumos_svm_lib::watch::watch_validator;
 use once_cell::sync::Lazy;
 
 use crate::analyze::Analyze;
+use crate::diff::Diff;
+use crate::dump::Dump;
+use crate::env::Env;
+use crate::logs::Logs;
+use crate::mock_rpc::MockRpc;
+use crate::replay::Replay;
+use crate::simulate::Simulate;
+use crate::snapshot::Snapshot;
 
 /// Get the version digits
 static VERSION: Lazy<String> = Lazy::new(get_version_digits);
@@ -59,6 +75,15 @@ struct CliArgs {
 /// # Variants
 /// * `Clone` - Clone the programs, accounts, and data specified in the config
 /// * `Run` - Run the a test solana validator
+/// * `Analyze` - Analyze given addresses
+/// * `Snapshot` - Save and restore named local states
+/// * `Dump` - Dump the accounts of the local validator as fixtures
+/// * `Logs` - Show the validator logs
+/// * `Env` - Print the connection details of the validator as shell exports
+/// * `MockRpc` - Serve fixture accounts and transactions as a mock upstream RPC
+/// * `Replay` - Replay a transaction against its cloned state
+/// * `Simulate` - Simulate a transaction against the configured state
+/// * `Diff` - Compare cached accounts with the cluster
 #[derive(Debug, Subcommand)]
 enum Command {
   /// Clone the programs, accounts, and data specified in the config
@@ -73,14 +98,82 @@ enum Command {
 
   /// Run the a test solana validator
   #[command(visible_aliases = ["r"], arg_required_else_help = false, about = "Run the a test solana validator")]
-  Run {
-    #[arg(short, long, help = "Verbose output")]
-    verbose: bool,
-  },
+  Run(RunArgs),
 
-  /// Analyze given address
-  #[command(visible_aliases = ["a"], arg_required_else_help = false, about = "Analyze given address")]
+  /// Analyze given addresses
+  #[command(visible_aliases = ["a"], arg_required_else_help = true, about = "Analyze given addresses")]
   Analyze(Analyze),
+
+  /// Save and restore named local states
+  #[command(visible_aliases = ["s"], arg_required_else_help = true, about = "Save and restore named local states")]
+  Snapshot(Snapshot),
+
+  /// Dump the accounts of the local validator as fixtures
+  #[command(visible_aliases = ["d"], arg_required_else_help = true, about = "Dump the accounts of the local validator as fixtures")]
+  Dump(Dump),
+
+  /// Show the validator logs
+  #[command(visible_aliases = ["l"], arg_required_else_help = false, about = "Show the validator logs")]
+  Logs(Logs),
+
+  /// Print the connection details of the validator as shell exports
+  #[command(visible_aliases = ["e"], arg_required_else_help = false, about = "Print the connection details of the validator as shell exports")]
+  Env(Env),
+
+  /// Serve fixture accounts and transactions as a mock upstream RPC
+  #[command(
+    arg_required_else_help = true,
+    about = "Serve fixture accounts and transactions as a mock upstream RPC"
+  )]
+  MockRpc(MockRpc),
+
+  /// Replay a transaction against its cloned state
+  #[command(
+    arg_required_else_help = true,
+    about = "Replay a transaction against its cloned state"
+  )]
+  Replay(Replay),
+
+  /// Simulate a transaction against the configured state
+  #[command(
+    arg_required_else_help = true,
+    about = "Simulate a transaction against the configured state"
+  )]
+  Simulate(Simulate),
+
+  /// Compare cached accounts with the cluster
+  #[command(
+    arg_required_else_help = false,
+    about = "Compare cached accounts with the cluster"
+  )]
+  Diff(Diff),
+}
+
+/// Arguments of the run subcommand
+#[derive(Debug, Args)]
+struct RunArgs {
+  #[arg(short, long, help = "Verbose output")]
+  verbose: bool,
+
+  #[arg(short, long, help = "Keep the existing ledger instead of resetting it")]
+  keep_ledger: bool,
+
+  #[arg(
+    short,
+    long,
+    help = "Restart the validator when the config or local programs change"
+  )]
+  watch: bool,
+
+  #[arg(long, help = "Pretty-print the program logs of the validator")]
+  logs: bool,
+
+  #[arg(
+    long,
+    value_name = "PROGRAM",
+    help = "Only print the program logs of transactions invoking this program (config name or address)"
+  )]
+  logs_for: Vec<String>,
 }
 
 /// CliEntry is the main entry point for the CLI
@@ -98,8 +191,9 @@ impl CliEntry {
 
     assert!(!args.config.is_empty());
 
+    // The mock RPC serves fixtures and doesn't read the config.
     let config = Path::new(&args.config);
-    if !config.exists() {
+    if !matches!(args.command, Some(Command::MockRpc(_))) && !config.exists() {
       anyhow::bail!("Config file not found: {}", args.config);
     }
 
@@ -114,8 +208,16 @@ impl CliEntry {
     // as not all command requires the context.
     match &self.args.command {
       Some(Command::Clone { clean, verbose }) => self.clone(*clean, *verbose),
-      Some(Command::Run { verbose }) => self.run_validator(*verbose),
+      Some(Command::Run(args)) => self.run_validator(args),
       Some(Command::Analyze(analyze)) => analyze.execute(&self.args.config),
+      Some(Command::Snapshot(snapshot)) => snapshot.execute(&self.args.config),
+      Some(Command::Dump(dump)) => dump.execute(&self.args.config),
+      Some(Command::Logs(logs)) => logs.execute(&self.args.config),
+      Some(Command::Env(env)) => env.execute(&self.args.config),
+      Some(Command::MockRpc(mock_rpc)) => mock_rpc.execute(),
+      Some(Command::Replay(replay)) => replay.execute(&self.args.config),
+      Some(Command::Simulate(simulate)) => simulate.execute(&self.args.config),
+      Some(Command::Diff(diff)) => diff.execute(&self.args.config),
       None => {
         anyhow::bail!("No subcommand provided. Use `--help` flag for more information.");
       },
@@ -188,7 +290,10 @@ impl CliEntry {
   }
 
   /// Run the a test solana validator
-  fn run_validator(&self, verbose: bool) -> anyhow::Result<()> {
+  /// With `general.backend = "inprocess"`, the config is served from an in-process SVM instead.
+  /// The ledger is kept if `keep_ledger` is set or `general.reset` is disabled.
+  /// With `watch`, the validator is restarted when the config or local programs change.
+  fn run_validator(&self, args: &RunArgs) -> anyhow::Result<()> {
     log::trace!("Running validator...");
 
     // Start the timer
@@ -200,8 +305,14 @@ impl CliEntry {
     let rpc_endpoint: &str = &config.general.rpc_endpoint;
     let cache_dir = config.general.cache_dir.clone();
 
+    let reset = !args.keep_ledger && config.general.reset.unwrap_or(true);
+
+    // Pretty-print program logs, optionally only for the given programs.
+    let program_logs = (args.logs || !args.logs_for.is_empty()).then_some(args.logs_for.as_slice());
+    resolve_program_filter(&config, &args.logs_for)?;
+
     // Create the context
-    let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, verbose);
+    let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, args.verbose);
 
     // Create the progress bar
     let tick_interval = Duration::from_millis(80);
@@ -212,15 +323,49 @@ impl CliEntry {
     pb.set_message("Running validator...");
 
     // Run the validator
-    validator(&context, true)?;
+    let exit = match context.backend() {
+      Backend::Inprocess if args.watch => {
+        anyhow::bail!("Watch mode is not supported by the in-process backend")
+      },
+      Backend::Inprocess if program_logs.is_some() => {
+        anyhow::bail!("Program logs need a websocket, which the in-process backend doesn't serve")
+      },
+      Backend::Inprocess => run_inprocess(&context, reset)?,
+      Backend::Validator if args.watch => {
+        pb.set_message("Running validator, watching for changes...");
+        watch_validator(context, &self.args.config, args.keep_ledger, program_logs)?
+      },
+      Backend::Validator => validator(&context, reset, program_logs)?,
+    };
 
     // Finish the progress bar
     let message = format!(
-      "Validator completed running in {}.",
+      "Validator {} after running for {}.",
+      exit,
       HumanDuration(started.elapsed())
     );
     pb.inc(1);
     pb.finish_with_message(message);
+
+    // Report a crash as an error, and a stopped validator with its signal exit code.
+    if exit.is_crash() {
+      anyhow::bail!("Validator {exit}");
+    }
+
+    let code = exit.exit_code();
+    if code != 0 {
+      std::process::exit(code);
+    }
+
     Ok(())
   }
 }
+
+/// Fallback when the in-process backend is not compiled in.
+#[cfg(not(feature = "inprocess"))]
+fn run_inprocess(
+  _context: &LumosContext,
+  _reset: bool,
+) -> anyhow::Result<lumos_svm_lib::signals::ValidatorExit> {
+  anyhow::bail!("The in-process backend needs lumos-svm to be built with the `inprocess` feature")
+}
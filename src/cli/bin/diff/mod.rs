@@ -0,0 +1,185 @@
+use std::str::FromStr as _;
+use std::sync::Arc;
+
+use anyhow::Context;
+use clap::Args;
+use lumos_svm_lib::account_diff::{
+  AccountDiff,
+  diff_accounts,
+};
+use lumos_svm_lib::cache_source::CacheSource;
+use lumos_svm_lib::decoders::DecoderRegistry;
+use lumos_svm_lib::lumos_context::LumosContext;
+use lumos_svm_lib::schema::ConfigRoot;
+use lumos_svm_lib::traits::AccountSource;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+/// The number of differing byte ranges shown per account.
+const MAX_BYTE_RANGES: usize = 10;
+
+/// The number of bytes shown per differing byte range.
+const MAX_RANGE_BYTES: usize = 16;
+
+#[derive(Debug, Args)]
+pub struct Diff {
+  /// Config name or address of the account, every configured account if omitted
+  target: Option<String>,
+}
+
+impl Diff {
+  pub fn execute(&self, config_file: &str) -> anyhow::Result<()> {
+    let config = ConfigRoot::from_file(config_file)?;
+    let config = Arc::new(config);
+    let rpc_endpoint: &str = &config.general.rpc_endpoint;
+    let cache_dir = config.general.cache_dir.clone();
+    let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, false);
+
+    let targets = self.targets(&config)?;
+    let addresses: Vec<Pubkey> = targets.iter().map(|(_, address)| *address).collect();
+    log::trace!(
+      "Comparing {} cached accounts with {}",
+      addresses.len(),
+      rpc_endpoint
+    );
+
+    let cache = CacheSource::new(&context)?;
+
+    // The cache holds the binaries of programs, not their accounts.
+    if let Some((name, address)) = targets.iter().find(|(_, address)| cache.is_program(address)) {
+      let target = name.clone().unwrap_or(address.to_string());
+      anyhow::bail!("{target} is a program, `diff` only compares accounts");
+    }
+
+    let client = RpcClient::new(rpc_endpoint);
+    let cached_accounts = cache.get_multiple_accounts(&addresses)?;
+    let live_accounts = AccountSource::get_multiple_accounts(&client, &addresses)?;
+    let registry = DecoderRegistry::for_config(&config);
+
+    let mut outdated = 0;
+    for (((name, address), cached), live) in targets.iter().zip(cached_accounts).zip(live_accounts) {
+      match name {
+        Some(name) => println!("\n{name} ({address}):"),
+        None => println!("\n{address}:"),
+      }
+
+      let (cached, live) = match (cached, live) {
+        (None, _) => {
+          println!("  Not cached, run `lumos-svm clone` first");
+          continue;
+        },
+        (Some(_), None) => {
+          println!("  No longer exists on the cluster");
+          outdated += 1;
+          continue;
+        },
+        (Some(cached), Some(live)) => (cached, live),
+      };
+
+      let mut diff = diff_accounts(&registry, address, (&cached, &cache), (&live, &client));
+
+      // The mint authority of mints patched by `mint = true` differs by design.
+      let patched = name
+        .as_ref()
+        .and_then(|name| config.account.get(name))
+        .is_some_and(|account| account.mint.unwrap_or(false));
+      if patched {
+        diff.fields.retain(|change| change.label != "Mint Authority");
+      }
+
+      if diff.is_empty() {
+        println!("  Up to date");
+        continue;
+      }
+
+      outdated += 1;
+      print_diff(&diff, &cached, &live);
+    }
+
+    println!();
+    match outdated {
+      0 => println!("All cached accounts are up to date"),
+      outdated => println!("{outdated} cached accounts differ, set `update = true` to clone them again"),
+    }
+
+    Ok(())
+  }
+
+  /// Get the accounts to compare, with their config name if they have one.
+  fn targets(&self, config: &ConfigRoot) -> anyhow::Result<Vec<(Option<String>, Pubkey)>> {
+    let Some(target) = &self.target else {
+      let mut targets: Vec<(Option<String>, Pubkey)> = config
+        .account
+        .iter()
+        .filter_map(|(name, account)| Some((Some(name.clone()), Pubkey::from_str(&account.address).ok()?)))
+        .collect();
+      targets.sort();
+      return Ok(targets);
+    };
+
+    let address = config.resolve_address(target);
+    let pubkey = Pubkey::from_str(address).with_context(|| format!("Invalid address: {target}"))?;
+    let name = (address != target).then(|| target.clone());
+    Ok(vec![(name, pubkey)])
+  }
+}
+
+/// Print the differences of an account.
+/// # Arguments
+/// * `diff` - The differences.
+/// * `cached` - The cached account.
+/// * `live` - The live account.
+fn print_diff(diff: &AccountDiff, cached: &Account, live: &Account) {
+  if let Some((cached, live)) = diff.lamports {
+    println!("  Lamports: {cached} -> {live}");
+  }
+  if let Some((cached, live)) = diff.owner {
+    println!("  Owner: {cached} -> {live}");
+  }
+  if let Some((cached, live)) = diff.data_len {
+    println!("  Data Length: {cached} -> {live} bytes");
+  }
+
+  for change in diff.fields.iter() {
+    let value = |value: &Option<String>| {
+      value
+        .as_deref()
+        .map(|value| value.replace('\n', ", "))
+        .unwrap_or("(missing)".to_string())
+    };
+    println!(
+      "  {}: {} -> {}",
+      change.label,
+      value(&change.cached),
+      value(&change.live)
+    );
+  }
+
+  for range in diff.byte_ranges.iter().take(MAX_BYTE_RANGES) {
+    let hex = |data: &[u8]| {
+      let end = range.end.min(data.len()).min(range.start + MAX_RANGE_BYTES);
+      let bytes = data.get(range.start..end).unwrap_or_default();
+      let hex: Vec<String> = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+      match (hex.is_empty(), range.len() > MAX_RANGE_BYTES) {
+        (true, _) => "-".to_string(),
+        (false, true) => format!("{}...", hex.join(" ")),
+        (false, false) => hex.join(" "),
+      }
+    };
+    println!(
+      "  Bytes {}..{}: {} -> {}",
+      range.start,
+      range.end,
+      hex(&cached.data),
+      hex(&live.data)
+    );
+  }
+
+  if diff.byte_ranges.len() > MAX_BYTE_RANGES {
+    println!(
+      "  ... {} more byte ranges",
+      diff.byte_ranges.len() - MAX_BYTE_RANGES
+    );
+  }
+}
@@ -0,0 +1,98 @@
+use std::path::Path;
+use std::str::FromStr as _;
+use std::sync::Arc;
+
+use anyhow::Context;
+use clap::Args;
+use lumos_svm_lib::connection::{
+  ConnectionInfo,
+  connection_dir,
+};
+use lumos_svm_lib::dump::dump_accounts;
+use lumos_svm_lib::lumos_context::LumosContext;
+use lumos_svm_lib::schema::ConfigRoot;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Debug, Args)]
+pub struct Dump {
+  #[arg(
+    long,
+    help = "Dump the accounts owned by this program (address or config name)"
+  )]
+  owner: Vec<String>,
+
+  #[arg(long, help = "Dump the account with this address (address or config name)")]
+  address: Vec<String>,
+
+  #[arg(
+    short,
+    long,
+    help = "Directory to write the accounts to",
+    default_value = "fixtures/accounts"
+  )]
+  out: String,
+
+  #[arg(
+    long,
+    help = "Write the accounts into the account cache instead, to load them on the next start",
+    conflicts_with = "out"
+  )]
+  to_cache: bool,
+
+  #[arg(
+    short,
+    long,
+    help = "RPC endpoint of the local validator, defaults to the one started by `lumos-svm run`"
+  )]
+  url: Option<String>,
+}
+
+impl Dump {
+  pub fn execute(&self, config_file: &str) -> anyhow::Result<()> {
+    if self.owner.is_empty() && self.address.is_empty() {
+      anyhow::bail!("Nothing to dump. Use `--owner` or `--address` to select accounts.");
+    }
+
+    let config = ConfigRoot::from_file(config_file)?;
+    let config = Arc::new(config);
+    let rpc_endpoint: &str = &config.general.rpc_endpoint;
+    let cache_dir = config.general.cache_dir.clone();
+    let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, false);
+
+    let owners = self.resolve(&config, &self.owner)?;
+    let addresses = self.resolve(&config, &self.address)?;
+    let out_dir = match self.to_cache {
+      true => context.account_cache_dir()?,
+      false => self.out.clone(),
+    };
+
+    let url = match &self.url {
+      Some(url) => url.clone(),
+      None => {
+        ConnectionInfo::read(&connection_dir(&config))
+          .map_err(|err| anyhow::anyhow!("{err}. Start the validator with `lumos-svm run` or pass `--url`."))?
+          .rpc_url
+      },
+    };
+
+    log::trace!("Dumping accounts from {} into {}", url, out_dir);
+
+    let client = RpcClient::new(url);
+    let count = dump_accounts(&client, &owners, &addresses, Path::new(&out_dir))?;
+    println!("Dumped {count} accounts into {out_dir}");
+
+    Ok(())
+  }
+
+  /// Resolve config names or addresses into public keys.
+  fn resolve(&self, config: &ConfigRoot, values: &[String]) -> anyhow::Result<Vec<Pubkey>> {
+    values
+      .iter()
+      .map(|value| {
+        let address = config.resolve_address(value);
+        Pubkey::from_str(address).with_context(|| format!("Invalid address: {value}"))
+      })
+      .collect()
+  }
+}
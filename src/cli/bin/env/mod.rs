@@ -0,0 +1,23 @@
+use clap::Args;
+use lumos_svm_lib::connection::{
+  ConnectionInfo,
+  connection_dir,
+};
+use lumos_svm_lib::schema::ConfigRoot;
+
+#[derive(Debug, Args)]
+pub struct Env {}
+
+impl Env {
+  pub fn execute(&self, config_file: &str) -> anyhow::Result<()> {
+    let config = ConfigRoot::from_file(config_file)?;
+    let dir = connection_dir(&config);
+    log::trace!("Printing connection details from {:?}", dir);
+
+    let info = ConnectionInfo::read(&dir)
+      .map_err(|err| anyhow::anyhow!("{err}. Start the validator with `lumos-svm run` first."))?;
+
+    print!("{}", info.to_shell());
+    Ok(())
+  }
+}
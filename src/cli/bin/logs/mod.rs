@@ -0,0 +1,101 @@
+use std::fs::{
+  self,
+  File,
+};
+use std::io::{
+  Read as _,
+  Seek as _,
+  SeekFrom,
+  Write as _,
+};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use clap::Args;
+use lumos_svm_lib::log_file::log_files;
+use lumos_svm_lib::lumos_context::LumosContext;
+use lumos_svm_lib::schema::ConfigRoot;
+
+/// The interval between checks for new lines when following the log.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Args)]
+pub struct Logs {
+  #[arg(short = 'n', long, help = "Number of lines to show from the end of the log")]
+  lines: Option<usize>,
+
+  #[arg(short, long, help = "Keep printing new lines as they are written")]
+  follow: bool,
+}
+
+impl Logs {
+  pub fn execute(&self, config_file: &str) -> anyhow::Result<()> {
+    let config = ConfigRoot::from_file(config_file)?;
+    let config = Arc::new(config);
+    let rpc_endpoint: &str = &config.general.rpc_endpoint;
+    let cache_dir = config.general.cache_dir.clone();
+    let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, false);
+
+    let log_file: &str = &context.validator_log_file()?;
+    let log_file = Path::new(log_file);
+    let files = log_files(log_file, context.log_retention());
+    if files.is_empty() {
+      anyhow::bail!("No validator log found: {log_file:?}");
+    }
+
+    // Read the rotated files from the oldest to the current one.
+    let mut contents = String::new();
+    for file in files.iter() {
+      contents.push_str(&fs::read_to_string(file)?);
+    }
+
+    let lines: Vec<&str> = contents.lines().collect();
+    let skip = match self.lines {
+      Some(count) => lines.len().saturating_sub(count),
+      None => 0,
+    };
+
+    for line in lines.iter().skip(skip) {
+      println!("{line}");
+    }
+
+    if self.follow {
+      self.follow(log_file)?;
+    }
+
+    Ok(())
+  }
+
+  /// Print new lines appended to the log file, starting over when it is rotated.
+  fn follow(&self, log_file: &Path) -> anyhow::Result<()> {
+    let mut position = fs::metadata(log_file).map(|metadata| metadata.len()).unwrap_or(0);
+
+    loop {
+      thread::sleep(FOLLOW_INTERVAL);
+
+      let Ok(metadata) = fs::metadata(log_file) else {
+        continue;
+      };
+
+      if metadata.len() < position {
+        position = 0;
+      }
+
+      if metadata.len() == position {
+        continue;
+      }
+
+      let mut file = File::open(log_file)?;
+      file.seek(SeekFrom::Start(position))?;
+      let mut buffer = Vec::new();
+      file.read_to_end(&mut buffer)?;
+      position += buffer.len() as u64;
+
+      let mut stdout = std::io::stdout().lock();
+      stdout.write_all(&buffer)?;
+      stdout.flush()?;
+    }
+  }
+}
@@ -2,6 +2,14 @@ use cli_entry::CliEntry;
 
 mod analyze;
 mod cli_entry;
+mod diff;
+mod dump;
+mod env;
+mod logs;
+mod mock_rpc;
+mod replay;
+mod simulate;
+mod snapshot;
 
 /// Main entry point
 fn main() -> anyhow::Result<()> {
@@ -0,0 +1,46 @@
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use clap::Args;
+use lumos_svm_lib::mock_rpc::{
+  MockRpcFixtures,
+  serve_mock_rpc,
+};
+use lumos_svm_lib::signals::ShutdownSignals;
+
+#[derive(Debug, Args)]
+pub struct MockRpc {
+  #[arg(
+    short,
+    long,
+    value_name = "DIR",
+    help = "Directory with `accounts/{address}.json` and `transactions/{signature}.json` fixtures"
+  )]
+  fixtures: String,
+
+  #[arg(short, long, default_value_t = 8999, help = "Port to serve the mock RPC on")]
+  port: u16,
+}
+
+impl MockRpc {
+  pub fn execute(&self) -> anyhow::Result<()> {
+    log::trace!("Serving mock RPC from {}", self.fixtures);
+
+    let fixtures = MockRpcFixtures::load(Path::new(&self.fixtures))?;
+    let (accounts, transactions) = fixtures.counts();
+
+    let signals = ShutdownSignals::register()?;
+    let server = serve_mock_rpc(fixtures, self.port)?;
+    println!(
+      "Serving {accounts} accounts and {transactions} transactions at {}",
+      server.url()
+    );
+
+    while signals.received().is_none() {
+      thread::sleep(Duration::from_millis(100));
+    }
+
+    Ok(())
+  }
+}
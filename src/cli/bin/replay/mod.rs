@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use clap::Args;
+use lumos_svm_lib::lumos_context::LumosContext;
+use lumos_svm_lib::program_logs::ProgramLogFormatter;
+use lumos_svm_lib::replay::replay_transaction;
+use lumos_svm_lib::schema::ConfigRoot;
+
+#[derive(Debug, Args)]
+pub struct Replay {
+  /// The transaction signature
+  signature: String,
+
+  #[arg(short, long, help = "Verbose output")]
+  verbose: bool,
+}
+
+impl Replay {
+  pub fn execute(&self, config_file: &str) -> anyhow::Result<()> {
+    log::trace!("Replaying transaction: {}", self.signature);
+
+    let config = ConfigRoot::from_file(config_file)?;
+    let config = Arc::new(config);
+    let rpc_endpoint: &str = &config.general.rpc_endpoint;
+    let cache_dir = config.general.cache_dir.clone();
+    let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, self.verbose);
+
+    let report = replay_transaction(&context, &self.signature)?;
+    let transaction = &report.transaction;
+    let simulation = &report.simulation;
+    let meta = transaction.meta.as_ref();
+    let formatter = ProgramLogFormatter::new(&config, &[])?;
+
+    println!("\nReplay:");
+    println!("------------------------");
+    println!("Signature: {}", transaction.signature);
+    println!("Slot: {}", transaction.slot);
+
+    let local_result = match &simulation.err {
+      Some(err) => format!("failed: {err}"),
+      None => "success".to_string(),
+    };
+    let on_chain_result = match meta.map(|meta| &meta.err) {
+      Some(Some(err)) => format!("failed: {err}"),
+      Some(None) => "success".to_string(),
+      None => "unknown".to_string(),
+    };
+    println!("Local Result: {local_result}");
+    println!("On-chain Result: {on_chain_result}");
+
+    let units = |units: Option<u64>| units.map(|units| units.to_string()).unwrap_or("unknown".into());
+    println!("Local Compute Units: {}", units(simulation.units_consumed));
+    println!(
+      "On-chain Compute Units: {}",
+      units(meta.and_then(|meta| meta.compute_units_consumed))
+    );
+
+    println!("\nLocal Logs:");
+    let err = simulation.err.as_ref().map(|err| err.to_string());
+    for line in formatter.format(&transaction.signature, err, &simulation.logs) {
+      println!("  {line}");
+    }
+
+    if let Some(logs) = meta.and_then(|meta| meta.log_messages.as_ref()) {
+      println!("\nOn-chain Logs:");
+      let err = meta.and_then(|meta| meta.err.as_ref()).map(|err| err.to_string());
+      for line in formatter.format(&transaction.signature, err, logs) {
+        println!("  {line}");
+      }
+    }
+
+    println!("\nBalance Changes:");
+    if report.balance_changes.is_empty() {
+      println!("  None");
+    }
+
+    for change in report.balance_changes.iter() {
+      let unit = match &change.mint {
+        Some(mint) => format!("token {mint}"),
+        None => "lamports".to_string(),
+      };
+      let on_chain = change
+        .on_chain
+        .map(|on_chain| format!("{on_chain:+}"))
+        .unwrap_or("unknown".into());
+      let marker = if change.matches() { "" } else { " (differs)" };
+      println!(
+        "  {} {unit}: local {:+}, on-chain {on_chain}{marker}",
+        change.address, change.local
+      );
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use clap::Args;
+use lumos_svm_lib::cache_source::CacheSource;
+use lumos_svm_lib::lumos_context::LumosContext;
+use lumos_svm_lib::program_errors::describe_transaction_error;
+use lumos_svm_lib::program_logs::ProgramLogFormatter;
+use lumos_svm_lib::schema::ConfigRoot;
+use lumos_svm_lib::simulate::{
+  account_changes,
+  decode_transaction,
+  simulate_with_config,
+};
+
+#[derive(Debug, Args)]
+pub struct Simulate {
+  #[arg(long, help = "The base64 encoded transaction, or a file holding it")]
+  tx: String,
+
+  #[arg(short, long, help = "Verbose output")]
+  verbose: bool,
+}
+
+impl Simulate {
+  pub fn execute(&self, config_file: &str) -> anyhow::Result<()> {
+    let config = Arc::new(ConfigRoot::from_file(config_file)?);
+    let rpc_endpoint: &str = &config.general.rpc_endpoint;
+    let cache_dir = config.general.cache_dir.clone();
+    let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, false);
+    let transaction = decode_transaction(&self.tx)?;
+    let signature = transaction
+      .signatures
+      .first()
+      .map(|signature| signature.to_string())
+      .unwrap_or_default();
+    log::trace!("Simulating transaction: {signature}");
+
+    let formatter = ProgramLogFormatter::new(&config, &[])?;
+    let result = simulate_with_config(config.as_ref().clone(), &transaction, self.verbose)?;
+
+    // The simulated state is the cache, so errors are described from it.
+    let cache = CacheSource::new(&context)?;
+
+    println!("\nSimulation:");
+    println!("------------------------");
+    println!("Signature: {signature}");
+
+    let error = result
+      .err
+      .as_ref()
+      .map(|err| describe_transaction_error(err, &transaction, &cache));
+    match &error {
+      Some(error) => println!("Result: failed: {error}"),
+      None => println!("Result: success"),
+    }
+
+    let units = result
+      .units_consumed
+      .map(|units| units.to_string())
+      .unwrap_or("unknown".into());
+    println!("Compute Units: {units}");
+
+    println!("\nLogs:");
+    for line in formatter.format(&signature, error, &result.logs) {
+      println!("  {line}");
+    }
+
+    println!("\nAccount Changes:");
+    let changes = account_changes(&result);
+    if changes.is_empty() {
+      println!("  None");
+    }
+
+    for change in changes.iter() {
+      let status = match (change.created(), change.closed()) {
+        (true, _) => " (created)",
+        (_, true) => " (closed)",
+        _ => "",
+      };
+      println!("  {}{status}", change.address);
+      println!("    Lamports: {:+}", change.lamports);
+      if let (Some(pre_owner), Some(post_owner)) = (change.pre_owner, change.post_owner) {
+        if pre_owner != post_owner {
+          println!("    Owner: {pre_owner} -> {post_owner}");
+        }
+      }
+
+      if change.data_changed {
+        println!(
+          "    Data: changed ({} -> {} bytes)",
+          change.data_len.0, change.data_len.1
+        );
+      }
+    }
+
+    Ok(())
+  }
+}
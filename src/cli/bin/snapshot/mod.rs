@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use clap::{
+  Args,
+  Subcommand,
+};
+use lumos_svm_lib::connection::{
+  ConnectionInfo,
+  connection_dir,
+};
+use lumos_svm_lib::lumos_context::LumosContext;
+use lumos_svm_lib::schema::ConfigRoot;
+use lumos_svm_lib::snapshot::{
+  SnapshotKind,
+  discard_restored_accounts,
+  restore_snapshot,
+  save_snapshot,
+};
+
+#[derive(Debug, Args)]
+pub struct Snapshot {
+  #[command(subcommand)]
+  action: SnapshotAction,
+
+  #[arg(
+    short,
+    long,
+    help = "RPC endpoint of the local validator, defaults to the one started by `lumos-svm run`"
+  )]
+  url: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum SnapshotAction {
+  /// Save the local state as a named snapshot
+  #[command(about = "Save the local state as a named snapshot")]
+  Save {
+    name: String,
+
+    #[arg(short, long, help = "Overwrite the snapshot if it already exists")]
+    force: bool,
+  },
+
+  /// Restore a named snapshot
+  #[command(about = "Restore a named snapshot")]
+  Restore { name: String },
+
+  /// Discard the accounts restored from a snapshot
+  #[command(about = "Discard the accounts restored from a snapshot")]
+  Discard,
+}
+
+impl Snapshot {
+  pub fn execute(&self, config_file: &str) -> anyhow::Result<()> {
+    let config = ConfigRoot::from_file(config_file)?;
+    let config = Arc::new(config);
+    let rpc_endpoint: &str = &config.general.rpc_endpoint;
+    let cache_dir = config.general.cache_dir.clone();
+    let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, false);
+
+    // The connection details are removed when the validator stops, so without
+    // them only a validator on the default ports can still be running.
+    let url = match &self.url {
+      Some(url) => url.clone(),
+      None => match ConnectionInfo::read(&connection_dir(&config)) {
+        Ok(info) => info.rpc_url,
+        Err(_) => context.local_rpc_url(),
+      },
+    };
+
+    match &self.action {
+      SnapshotAction::Save { name, force } => {
+        log::trace!("Saving snapshot: {}", name);
+
+        match save_snapshot(&context, name, &url, *force)? {
+          SnapshotKind::Ledger => println!("Saved ledger snapshot: {name}"),
+          SnapshotKind::Accounts(count) => {
+            println!("Saved {count} accounts from the running validator as snapshot: {name}")
+          },
+        }
+      },
+      SnapshotAction::Restore { name } => {
+        log::trace!("Restoring snapshot: {}", name);
+
+        match restore_snapshot(&context, name, &url)? {
+          SnapshotKind::Ledger => {
+            println!("Restored ledger snapshot: {name}");
+            println!("Run `lumos-svm run --keep-ledger` to resume it.");
+          },
+          SnapshotKind::Accounts(count) => {
+            println!("Restored {count} accounts from snapshot: {name}");
+            println!("Run `lumos-svm run` to load them.");
+          },
+        }
+      },
+      SnapshotAction::Discard => {
+        log::trace!("Discarding restored accounts");
+
+        let count = discard_restored_accounts(&context)?;
+        println!("Discarded {count} restored accounts");
+      },
+    }
+
+    Ok(())
+  }
+}
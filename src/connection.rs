@@ -0,0 +1,208 @@
+use serde::{
+  Deserialize,
+  Serialize,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{
+  Path,
+  PathBuf,
+};
+
+use crate::lumos_context::LumosContext;
+use crate::schema::ConfigRoot;
+
+/// The directory the connection details are written to, next to the config file.
+pub const CONNECTION_DIR: &str = ".lumos";
+
+/// The dotenv file inside the connection directory.
+const ENV_FILE: &str = "env";
+
+/// The JSON file inside the connection directory.
+const CONNECTION_FILE: &str = "connection.json";
+
+/// ConnectionInfo holds the details test frameworks need to connect to the
+/// local validator.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConnectionInfo {
+  /// The JSON RPC URL.
+  pub rpc_url: String,
+  /// The websocket URL.
+  pub ws_url: String,
+  /// The faucet address.
+  pub faucet_address: String,
+  /// The validator ledger directory.
+  pub ledger_dir: String,
+  /// The configured account names and their addresses.
+  pub accounts: BTreeMap<String, String>,
+  /// The configured program names and their addresses.
+  pub programs: BTreeMap<String, String>,
+}
+
+/// Implementation of ConnectionInfo.
+impl ConnectionInfo {
+  /// Create the connection details of the local validator.
+  pub fn from_context(context: &LumosContext) -> Self {
+    let config = &context.config;
+    let accounts = config
+      .account
+      .iter()
+      .map(|(name, account)| (name.clone(), account.address.clone()))
+      .collect();
+    let programs = config
+      .program
+      .iter()
+      .map(|(name, program)| (name.clone(), program.address.clone()))
+      .collect();
+
+    Self {
+      rpc_url: context.local_rpc_url(),
+      ws_url: context.local_ws_url(),
+      faucet_address: format!("127.0.0.1:{}", context.local_ports().faucet),
+      ledger_dir: context.ledger_dir(),
+      accounts,
+      programs,
+    }
+  }
+
+  /// Get the connection details as environment variables.
+  pub fn to_env(&self) -> Vec<(String, String)> {
+    let mut vars = vec![
+      ("LUMOS_RPC_URL".to_string(), self.rpc_url.clone()),
+      ("LUMOS_WS_URL".to_string(), self.ws_url.clone()),
+      ("LUMOS_FAUCET_ADDRESS".to_string(), self.faucet_address.clone()),
+      ("LUMOS_LEDGER_DIR".to_string(), self.ledger_dir.clone()),
+    ];
+
+    for (name, address) in self.accounts.iter() {
+      vars.push((format!("LUMOS_ACCOUNT_{}", env_key(name)), address.clone()));
+    }
+
+    for (name, address) in self.programs.iter() {
+      vars.push((format!("LUMOS_PROGRAM_{}", env_key(name)), address.clone()));
+    }
+
+    vars
+  }
+
+  /// Get the connection details as shell `export` statements.
+  pub fn to_shell(&self) -> String {
+    self
+      .to_env()
+      .iter()
+      .map(|(key, value)| format!("export {key}='{}'\n", value.replace('\'', "'\\''")))
+      .collect()
+  }
+
+  /// Get the connection details in dotenv format.
+  pub fn to_dotenv(&self) -> String {
+    self
+      .to_env()
+      .iter()
+      .map(|(key, value)| format!("{key}=\"{}\"\n", value.replace('\\', "\\\\").replace('"', "\\\"")))
+      .collect()
+  }
+
+  /// Write the dotenv and JSON files into a directory.
+  pub fn write(&self, dir: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(ENV_FILE), self.to_dotenv())?;
+    fs::write(dir.join(CONNECTION_FILE), serde_json::to_string_pretty(self)?)?;
+    Ok(())
+  }
+
+  /// Remove the dotenv and JSON files from a directory, and the directory if
+  /// nothing else is left in it.
+  pub fn remove(dir: &Path) -> anyhow::Result<()> {
+    for file in [ENV_FILE, CONNECTION_FILE] {
+      let path = dir.join(file);
+      if path.exists() {
+        fs::remove_file(path)?;
+      }
+    }
+
+    if dir.exists() && fs::read_dir(dir)?.next().is_none() {
+      fs::remove_dir(dir)?;
+    }
+
+    Ok(())
+  }
+
+  /// Read the connection details from the JSON file in a directory.
+  pub fn read(dir: &Path) -> anyhow::Result<Self> {
+    let path = dir.join(CONNECTION_FILE);
+    if !path.exists() {
+      anyhow::bail!("Connection details not found: {path:?}");
+    }
+
+    let contents = fs::read_to_string(path)?;
+    let info = serde_json::from_str(&contents)?;
+    Ok(info)
+  }
+}
+
+/// Get the directory the connection details of a config are written to.
+/// It's next to the config file, so tools find it regardless of the working
+/// directory the validator was started from.
+pub fn connection_dir(config: &ConfigRoot) -> PathBuf {
+  config
+    .config_dir
+    .as_deref()
+    .unwrap_or(Path::new(""))
+    .join(CONNECTION_DIR)
+}
+
+/// Convert a config name to an environment variable key.
+fn env_key(name: &str) -> String {
+  name
+    .chars()
+    .map(|c| match c.is_ascii_alphanumeric() {
+      true => c.to_ascii_uppercase(),
+      false => '_',
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use assert_fs::TempDir;
+
+  #[test]
+  fn it_should_write_and_read_connection_details() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let info = ConnectionInfo {
+      rpc_url: "http://127.0.0.1:8900".into(),
+      ws_url: "ws://127.0.0.1:8901".into(),
+      faucet_address: "127.0.0.1:9901".into(),
+      ledger_dir: ".lumos-ledger".into(),
+      accounts: BTreeMap::from([(
+        "usdc".to_string(),
+        "AKEWE7Bgh87GPp171b4cJPSSZfmZwQ3KaqYqXoKLNAEE".to_string(),
+      )]),
+      programs: BTreeMap::from([(
+        "orca-whirlpool".to_string(),
+        "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string(),
+      )]),
+    };
+
+    let dir = temp_dir.path().join(CONNECTION_DIR);
+    info.write(&dir)?;
+    assert_eq!(ConnectionInfo::read(&dir)?, info);
+
+    let dotenv = fs::read_to_string(dir.join(ENV_FILE))?;
+    assert!(dotenv.contains("LUMOS_RPC_URL=\"http://127.0.0.1:8900\"\n"));
+    assert!(
+      dotenv.contains("LUMOS_PROGRAM_ORCA_WHIRLPOOL=\"whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc\"\n")
+    );
+    assert!(
+      info
+        .to_shell()
+        .contains("export LUMOS_ACCOUNT_USDC='AKEWE7Bgh87GPp171b4cJPSSZfmZwQ3KaqYqXoKLNAEE'\n")
+    );
+
+    ConnectionInfo::remove(&dir)?;
+    assert!(!dir.exists());
+    Ok(())
+  }
+}
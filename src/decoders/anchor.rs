@@ -0,0 +1,482 @@
+use anyhow::Context;
+use flate2::read::ZlibDecoder;
+use serde_json::{
+  Map,
+  Value,
+  json,
+};
+use sha2::{
+  Digest as _,
+  Sha256,
+};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::io::Read as _;
+
+use super::DecodedAccount;
+use super::hexdump::decode_raw;
+use crate::solana_utils::anchor_idl_address;
+use crate::traits::{
+  AccountDecoder,
+  AccountSource,
+};
+
+/// The size of the IDL account header: discriminator, authority and data length.
+const IDL_HEADER_SIZE: usize = 8 + 32 + 4;
+
+/// The size of an Anchor account discriminator.
+const DISCRIMINATOR_SIZE: usize = 8;
+
+/// AnchorIdl is the IDL of an Anchor program, in the old or the new
+/// (Anchor 0.30+) format, used to decode its accounts.
+#[derive(Debug, Clone)]
+pub struct AnchorIdl {
+  idl: Value,
+}
+
+/// Implementation of AnchorIdl.
+impl AnchorIdl {
+  /// Parse an IDL from its JSON.
+  pub fn from_json(json: &str) -> anyhow::Result<Self> {
+    let idl: Value = serde_json::from_str(json).with_context(|| "Failed to parse IDL")?;
+    if !idl["accounts"].is_array() && !idl["types"].is_array() {
+      anyhow::bail!("Not an Anchor IDL");
+    }
+
+    Ok(Self { idl })
+  }
+
+  /// Parse an IDL from the data of the on-chain IDL account.
+  pub fn from_account_data(data: &[u8]) -> anyhow::Result<Self> {
+    if data.len() < IDL_HEADER_SIZE {
+      anyhow::bail!("IDL account is too small");
+    }
+
+    let len = u32::from_le_bytes(data[40..44].try_into()?) as usize;
+    let compressed = data
+      .get(IDL_HEADER_SIZE..IDL_HEADER_SIZE + len)
+      .with_context(|| "IDL account data is truncated")?;
+
+    let mut json = String::new();
+    ZlibDecoder::new(compressed)
+      .read_to_string(&mut json)
+      .with_context(|| "Failed to decompress IDL")?;
+    Self::from_json(&json)
+  }
+
+  /// Fetch the IDL of a program from its IDL account, `None` if it has none.
+  pub fn fetch(program_id: &Pubkey, source: &dyn AccountSource) -> anyhow::Result<Option<Self>> {
+    let idl_address = anchor_idl_address(program_id)?;
+    match source.get_account(&idl_address)? {
+      Some(account) => Ok(Some(Self::from_account_data(&account.data)?)),
+      None => Ok(None),
+    }
+  }
+
+  /// Get the name of the program.
+  pub fn name(&self) -> Option<&str> {
+    self.idl["metadata"]["name"]
+      .as_str()
+      .or(self.idl["name"].as_str())
+  }
+
+  /// Describe an error defined by the program, `None` if the IDL doesn't
+  /// define the code.
+  pub fn error(&self, code: u32) -> Option<String> {
+    let error = self.idl["errors"]
+      .as_array()?
+      .iter()
+      .find(|error| error["code"].as_u64() == Some(code as u64))?;
+    let name = error["name"].as_str()?;
+    match error["msg"].as_str() {
+      Some(msg) => Some(format!("{name}: {msg}")),
+      None => Some(name.to_string()),
+    }
+  }
+
+  /// Get the account type name matching the discriminator of the data.
+  pub fn account_name(&self, data: &[u8]) -> Option<String> {
+    let discriminator = data.get(..DISCRIMINATOR_SIZE)?;
+    self.idl["accounts"]
+      .as_array()?
+      .iter()
+      .filter_map(|account| account["name"].as_str().map(|name| (name, account)))
+      .find(|(name, account)| account_discriminator(name, account) == discriminator)
+      .map(|(name, _)| name.to_string())
+  }
+
+  /// Decode the data of an account of the program.
+  /// Returns the account type name and its fields, `None` if no account type
+  /// matches the discriminator.
+  pub fn decode_account(&self, data: &[u8]) -> anyhow::Result<Option<(String, Value)>> {
+    let Some(name) = self.account_name(data) else {
+      return Ok(None);
+    };
+
+    // The old format defines the account type inline, the new one in `types`.
+    let account = self.idl["accounts"]
+      .as_array()
+      .and_then(|accounts| accounts.iter().find(|account| account["name"] == name.as_str()));
+    let type_def = match account.map(|account| &account["type"]) {
+      Some(type_def) if type_def.is_object() => type_def,
+      _ => self
+        .type_def(&name)
+        .with_context(|| format!("Type not found in IDL: {name}"))?,
+    };
+
+    let zero_copy = self.is_zero_copy(&name, account);
+    if zero_copy {
+      anyhow::bail!("Zero copy account {name} can't be decoded");
+    }
+
+    let mut reader = Reader::new(&data[DISCRIMINATOR_SIZE..]);
+    let value = self.decode_type_def(type_def, &mut reader)?;
+    Ok(Some((name, value)))
+  }
+
+  /// Find a type definition by name.
+  fn type_def(&self, name: &str) -> Option<&Value> {
+    self.idl["types"]
+      .as_array()?
+      .iter()
+      .find(|type_def| type_def["name"] == name)
+      .map(|type_def| &type_def["type"])
+  }
+
+  /// Check if an account type uses the zero copy (bytemuck) layout.
+  fn is_zero_copy(&self, name: &str, account: Option<&Value>) -> bool {
+    let serialization = self.idl["types"]
+      .as_array()
+      .and_then(|types| types.iter().find(|type_def| type_def["name"] == name))
+      .map(|type_def| &type_def["serialization"]);
+
+    let bytemuck = serialization
+      .and_then(|serialization| serialization.as_str())
+      .is_some_and(|serialization| serialization.starts_with("bytemuck"));
+    let zero_copy = account.is_some_and(|account| account["zero_copy"] == true);
+    bytemuck || zero_copy
+  }
+
+  /// Decode a struct, enum or alias type definition.
+  fn decode_type_def(&self, type_def: &Value, reader: &mut Reader) -> anyhow::Result<Value> {
+    match type_def["kind"].as_str() {
+      Some("struct") => self.decode_fields(&type_def["fields"], reader),
+      Some("enum") => {
+        let variants = type_def["variants"]
+          .as_array()
+          .with_context(|| "Enum without variants")?;
+        let index = reader.take(1)?[0] as usize;
+        let variant = variants
+          .get(index)
+          .with_context(|| format!("Invalid enum variant: {index}"))?;
+        let name = variant["name"].as_str().unwrap_or_default();
+
+        match variant["fields"].is_array() {
+          true => Ok(json!({ name: self.decode_fields(&variant["fields"], reader)? })),
+          false => Ok(json!(name)),
+        }
+      },
+      Some("type") => self.decode_type(&type_def["alias"], reader),
+      kind => anyhow::bail!("Unsupported type kind: {kind:?}"),
+    }
+  }
+
+  /// Decode named fields into an object, or tuple fields into an array.
+  fn decode_fields(&self, fields: &Value, reader: &mut Reader) -> anyhow::Result<Value> {
+    let Some(fields) = fields.as_array() else {
+      return Ok(Value::Object(Map::new()));
+    };
+
+    let named = fields.iter().all(|field| field["name"].is_string());
+    if !named {
+      let values = fields
+        .iter()
+        .map(|field_type| self.decode_type(field_type, reader))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+      return Ok(Value::Array(values));
+    }
+
+    let mut object = Map::new();
+    for field in fields {
+      let name = field["name"].as_str().unwrap_or_default().to_string();
+      let value = self
+        .decode_type(&field["type"], reader)
+        .with_context(|| format!("Failed to decode field {name}"))?;
+      object.insert(name, value);
+    }
+
+    Ok(Value::Object(object))
+  }
+
+  /// Decode a value of the given IDL type.
+  fn decode_type(&self, type_: &Value, reader: &mut Reader) -> anyhow::Result<Value> {
+    if let Some(primitive) = type_.as_str() {
+      return reader.primitive(primitive);
+    }
+
+    if let Some(inner) = type_.get("option") {
+      return match reader.take(1)?[0] {
+        0 => Ok(Value::Null),
+        _ => self.decode_type(inner, reader),
+      };
+    }
+
+    if let Some(inner) = type_.get("coption") {
+      return match reader.u32()? {
+        0 => Ok(Value::Null),
+        _ => self.decode_type(inner, reader),
+      };
+    }
+
+    if let Some(inner) = type_.get("vec") {
+      let len = reader.u32()? as usize;
+      let values = (0..len)
+        .map(|_| self.decode_type(inner, reader))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+      return Ok(Value::Array(values));
+    }
+
+    if let Some(array) = type_.get("array") {
+      let len = array[1].as_u64().with_context(|| "Unsupported array length")? as usize;
+      let values = (0..len)
+        .map(|_| self.decode_type(&array[0], reader))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+      return Ok(Value::Array(values));
+    }
+
+    if let Some(defined) = type_.get("defined") {
+      let name = defined
+        .as_str()
+        .or(defined["name"].as_str())
+        .with_context(|| "Invalid defined type")?;
+      let type_def = self
+        .type_def(name)
+        .with_context(|| format!("Type not found in IDL: {name}"))?;
+      return self.decode_type_def(type_def, reader);
+    }
+
+    anyhow::bail!("Unsupported type: {type_}")
+  }
+}
+
+/// Get the discriminator of an IDL account, explicit in the new format and
+/// derived from the name in the old one.
+fn account_discriminator(name: &str, account: &Value) -> Vec<u8> {
+  match account["discriminator"].as_array() {
+    Some(discriminator) => discriminator
+      .iter()
+      .map(|byte| byte.as_u64().unwrap_or_default() as u8)
+      .collect(),
+    None => Sha256::digest(format!("account:{name}"))[..DISCRIMINATOR_SIZE].to_vec(),
+  }
+}
+
+/// Reader reads Borsh encoded values.
+pub(super) struct Reader<'a> {
+  data: &'a [u8],
+  offset: usize,
+}
+
+/// Implementation of Reader.
+impl<'a> Reader<'a> {
+  /// Create a reader at the start of the data.
+  pub(super) fn new(data: &'a [u8]) -> Self {
+    Self { data, offset: 0 }
+  }
+
+  /// Take the next bytes.
+  pub(super) fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+    let bytes = self
+      .data
+      .get(self.offset..self.offset + len)
+      .with_context(|| format!("Unexpected end of data at offset {}", self.offset))?;
+    self.offset += len;
+    Ok(bytes)
+  }
+
+  /// Take the next bytes as an array.
+  pub(super) fn array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+    Ok(self.take(N)?.try_into()?)
+  }
+
+  /// Read a u32, used for lengths.
+  pub(super) fn u32(&mut self) -> anyhow::Result<u32> {
+    Ok(u32::from_le_bytes(self.array()?))
+  }
+
+  /// Read a primitive type.
+  /// 128 bit integers are strings, since JSON numbers can't hold them.
+  fn primitive(&mut self, primitive: &str) -> anyhow::Result<Value> {
+    let value = match primitive {
+      "bool" => json!(self.take(1)?[0] != 0),
+      "u8" => json!(self.take(1)?[0]),
+      "i8" => json!(self.take(1)?[0] as i8),
+      "u16" => json!(u16::from_le_bytes(self.array()?)),
+      "i16" => json!(i16::from_le_bytes(self.array()?)),
+      "u32" => json!(self.u32()?),
+      "i32" => json!(i32::from_le_bytes(self.array()?)),
+      "f32" => json!(f32::from_le_bytes(self.array()?)),
+      "u64" => json!(u64::from_le_bytes(self.array()?)),
+      "i64" => json!(i64::from_le_bytes(self.array()?)),
+      "f64" => json!(f64::from_le_bytes(self.array()?)),
+      "u128" => json!(u128::from_le_bytes(self.array()?).to_string()),
+      "i128" => json!(i128::from_le_bytes(self.array()?).to_string()),
+      "publicKey" | "pubkey" => json!(Pubkey::new_from_array(self.array()?).to_string()),
+      "string" => {
+        let len = self.u32()? as usize;
+        json!(String::from_utf8_lossy(self.take(len)?))
+      },
+      "bytes" => {
+        let len = self.u32()? as usize;
+        json!(hex_string(self.take(len)?))
+      },
+      "u256" | "i256" => json!(hex_string(self.take(32)?)),
+      primitive => anyhow::bail!("Unsupported type: {primitive}"),
+    };
+
+    Ok(value)
+  }
+}
+
+/// Format bytes as a 0x prefixed hex string.
+fn hex_string(bytes: &[u8]) -> String {
+  let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+  format!("0x{hex}")
+}
+
+/// AnchorDecoder decodes the accounts of programs that published an Anchor
+/// IDL, and shows the others as a hexdump.
+/// The IDL of a program is fetched from the source of the account decoded.
+#[derive(Debug, Default)]
+pub struct AnchorDecoder;
+
+/// Implementation of AnchorDecoder.
+impl AnchorDecoder {
+  /// Get the IDL of a program, `None` if it has none or it can't be fetched.
+  fn idl(&self, program_id: &Pubkey, source: &dyn AccountSource) -> Option<AnchorIdl> {
+    match AnchorIdl::fetch(program_id, source) {
+      Ok(idl) => idl,
+      Err(err) => {
+        log::debug!("Failed to fetch the IDL of {program_id}: {err}");
+        None
+      },
+    }
+  }
+}
+
+/// Implementation of AccountDecoder for AnchorDecoder.
+impl AccountDecoder for AnchorDecoder {
+  fn name(&self) -> &str {
+    "anchor"
+  }
+
+  fn can_decode(&self, _address: &Pubkey, account: &Account) -> bool {
+    !account.executable && account.data.len() >= DISCRIMINATOR_SIZE
+  }
+
+  fn decode(
+    &self,
+    _address: &Pubkey,
+    account: &Account,
+    source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount> {
+    let Some(idl) = self.idl(&account.owner, source) else {
+      return Ok(decode_raw("Unknown", &account.data));
+    };
+
+    let program = idl.name().unwrap_or("unknown").to_string();
+    match idl.decode_account(&account.data) {
+      Ok(Some((name, value))) => Ok(
+        DecodedAccount::new(&format!("Anchor Account ({name})"))
+          .field("Program", program)
+          .json_fields(&value),
+      ),
+      Ok(None) => Ok(decode_raw("Anchor Account (unknown type)", &account.data).field("Program", program)),
+      Err(err) => {
+        let name = idl
+          .account_name(&account.data)
+          .unwrap_or("unknown type".to_string());
+        Ok(
+          decode_raw(&format!("Anchor Account ({name})"), &account.data)
+            .field("Program", program)
+            .field("Decode Error", err),
+        )
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_should_decode_accounts_with_new_and_old_idls() -> anyhow::Result<()> {
+    let authority = Pubkey::new_unique();
+    let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+    data.extend_from_slice(authority.as_ref());
+    data.extend_from_slice(&42u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(&7u16.to_le_bytes());
+    data.extend_from_slice(&5u32.to_le_bytes());
+    data.extend_from_slice(b"lumos");
+    data.push(1);
+
+    let new_idl = AnchorIdl::from_json(
+      r#"{
+        "metadata": { "name": "vault" },
+        "accounts": [{ "name": "Vault", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] }],
+        "types": [
+          {
+            "name": "Vault",
+            "type": {
+              "kind": "struct",
+              "fields": [
+                { "name": "authority", "type": "pubkey" },
+                { "name": "amount", "type": "u64" },
+                { "name": "fee", "type": { "option": "u16" } },
+                { "name": "label", "type": "string" },
+                { "name": "state", "type": { "defined": { "name": "State" } } }
+              ]
+            }
+          },
+          {
+            "name": "State",
+            "type": { "kind": "enum", "variants": [{ "name": "Open" }, { "name": "Closed" }] }
+          }
+        ]
+      }"#,
+    )?;
+
+    let (name, value) = new_idl.decode_account(&data)?.expect("the discriminator matches");
+    assert_eq!(name, "Vault");
+    assert_eq!(
+      value,
+      json!({
+        "authority": authority.to_string(),
+        "amount": 42,
+        "fee": 7,
+        "label": "lumos",
+        "state": "Closed",
+      })
+    );
+
+    // The old format derives the discriminator from the account name.
+    data[..8].copy_from_slice(&Sha256::digest("account:Vault")[..8]);
+    let old_idl = AnchorIdl::from_json(
+      r#"{
+        "name": "vault",
+        "accounts": [{
+          "name": "Vault",
+          "type": { "kind": "struct", "fields": [{ "name": "authority", "type": "publicKey" }] }
+        }]
+      }"#,
+    )?;
+
+    let (name, value) = old_idl.decode_account(&data)?.expect("the discriminator matches");
+    assert_eq!(name, "Vault");
+    assert_eq!(value, json!({ "authority": authority.to_string() }));
+    assert!(new_idl.decode_account(&data)?.is_none());
+    Ok(())
+  }
+}
@@ -0,0 +1,87 @@
+use base64::prelude::*;
+use serde_json::json;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use super::DecodedAccount;
+use crate::traits::{
+  AccountDecoder,
+  AccountSource,
+};
+
+/// The number of bytes shown by the hexdump, the rest is summarized.
+const MAX_HEXDUMP_BYTES: usize = 1024;
+
+/// HexdumpDecoder shows the raw data of accounts no other decoder knows.
+#[derive(Debug, Default)]
+pub struct HexdumpDecoder;
+
+/// Implementation of AccountDecoder for HexdumpDecoder.
+impl AccountDecoder for HexdumpDecoder {
+  fn name(&self) -> &str {
+    "hexdump"
+  }
+
+  fn can_decode(&self, _address: &Pubkey, _account: &Account) -> bool {
+    true
+  }
+
+  fn decode(
+    &self,
+    _address: &Pubkey,
+    account: &Account,
+    _source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount> {
+    Ok(decode_raw("Unknown", &account.data))
+  }
+}
+
+/// Decode raw data as its length and hexdump.
+/// The machine-readable form holds the data base64 encoded.
+pub(super) fn decode_raw(kind: &str, data: &[u8]) -> DecodedAccount {
+  let decoded = DecodedAccount::new(kind)
+    .field("Data Length", format!("{} bytes", data.len()))
+    .with_data(json!({
+      "dataLength": data.len(),
+      "data": BASE64_STANDARD.encode(data),
+    }));
+  match data.is_empty() {
+    true => decoded,
+    false => decoded.field("Data", hexdump(data, MAX_HEXDUMP_BYTES)),
+  }
+}
+
+/// Format data like `hexdump -C`, up to `max_bytes`.
+/// # Arguments
+/// * `data` - The data to format.
+/// * `max_bytes` - The number of bytes to show, the rest is summarized.
+pub fn hexdump(data: &[u8], max_bytes: usize) -> String {
+  let mut lines: Vec<String> = data[..data.len().min(max_bytes)]
+    .chunks(16)
+    .enumerate()
+    .map(|(index, chunk)| {
+      let hex: Vec<String> = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+      let (left, right) = hex.split_at(hex.len().min(8));
+      let ascii: String = chunk
+        .iter()
+        .map(|byte| match byte.is_ascii_graphic() || *byte == b' ' {
+          true => *byte as char,
+          false => '.',
+        })
+        .collect();
+
+      format!(
+        "{:08x}  {:<23}  {:<23}  |{ascii}|",
+        index * 16,
+        left.join(" "),
+        right.join(" ")
+      )
+    })
+    .collect();
+
+  if data.len() > max_bytes {
+    lines.push(format!("... {} more bytes", data.len() - max_bytes));
+  }
+
+  lines.join("\n")
+}
@@ -0,0 +1,71 @@
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use super::DecodedAccount;
+use crate::solana_utils::{
+  loader_version,
+  program_details,
+};
+use crate::traits::{
+  AccountDecoder,
+  AccountSource,
+};
+
+/// LoaderDecoder decodes the accounts of the BPF loaders: programs and the
+/// ProgramData accounts of upgradeable programs.
+#[derive(Debug, Default)]
+pub struct LoaderDecoder;
+
+/// Implementation of AccountDecoder for LoaderDecoder.
+impl AccountDecoder for LoaderDecoder {
+  fn name(&self) -> &str {
+    "loader"
+  }
+
+  fn can_decode(&self, _address: &Pubkey, account: &Account) -> bool {
+    loader_version(&account.owner).is_some()
+  }
+
+  fn decode(
+    &self,
+    address: &Pubkey,
+    account: &Account,
+    source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount> {
+    let details = program_details(source, address, account)?;
+    let data = serde_json::to_value(&details)?;
+
+    // Programs of the older loaders can't be upgraded.
+    let upgrade_authority = match (details.loader_version, details.upgrade_authority) {
+      (3 | 4, Some(authority)) => authority,
+      (3 | 4, None) => "None (immutable)".to_string(),
+      _ => "None (not upgradeable)".to_string(),
+    };
+    let idl = match details.idl_exists {
+      true => format!("found ({})", details.idl_address),
+      false => format!("not found ({})", details.idl_address),
+    };
+
+    let mut decoded = DecodedAccount::new("Program").with_data(data).field(
+      "Loader",
+      format!("{} (v{})", details.loader, details.loader_version),
+    );
+    if let Some(program_data_address) = details.program_data_address {
+      decoded = decoded.field("ProgramData Address", program_data_address);
+    }
+
+    Ok(
+      decoded
+        .field("Upgrade Authority", upgrade_authority)
+        .field(
+          "Last Deploy Slot",
+          details
+            .last_deploy_slot
+            .map(|slot| slot.to_string())
+            .unwrap_or("Unknown".to_string()),
+        )
+        .field("ELF Size", format!("{} bytes", details.elf_size))
+        .field("Anchor IDL", idl),
+    )
+  }
+}
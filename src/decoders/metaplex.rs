@@ -0,0 +1,339 @@
+use serde_json::{
+  Value,
+  json,
+};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+use super::DecodedAccount;
+use super::anchor::Reader;
+use crate::traits::{
+  AccountDecoder,
+  AccountSource,
+};
+
+/// The Metaplex Token Metadata program.
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// The account key of metadata accounts.
+const METADATA_V1_KEY: u8 = 4;
+
+/// Get the address of the Metaplex metadata account of a mint.
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+  let seeds: &[&[u8]] = &[b"metadata", TOKEN_METADATA_PROGRAM_ID.as_ref(), mint.as_ref()];
+  Pubkey::find_program_address(seeds, &TOKEN_METADATA_PROGRAM_ID).0
+}
+
+/// A creator of a Metaplex token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaplexCreator {
+  pub address: Pubkey,
+  pub verified: bool,
+  /// The share of the royalties, in percent.
+  pub share: u8,
+}
+
+/// The collection of a Metaplex token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaplexCollection {
+  pub key: Pubkey,
+  pub verified: bool,
+}
+
+/// The Metaplex metadata of a mint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaplexMetadata {
+  pub update_authority: Pubkey,
+  pub mint: Pubkey,
+  pub name: String,
+  pub symbol: String,
+  pub uri: String,
+  pub seller_fee_basis_points: u16,
+  pub creators: Vec<MetaplexCreator>,
+  pub primary_sale_happened: bool,
+  pub is_mutable: bool,
+  pub token_standard: Option<String>,
+  pub collection: Option<MetaplexCollection>,
+}
+
+/// Implementation of MetaplexMetadata.
+impl MetaplexMetadata {
+  /// Decode a Metaplex metadata account.
+  /// Only the leading fields are decoded, the ones after the collection are
+  /// skipped.
+  pub fn from_account_data(data: &[u8]) -> anyhow::Result<Self> {
+    let mut reader = Reader::new(data);
+    let key = reader.take(1)?[0];
+    if key != METADATA_V1_KEY {
+      anyhow::bail!("Not a Metaplex metadata account, key {key}");
+    }
+
+    let update_authority = read_pubkey(&mut reader)?;
+    let mint = read_pubkey(&mut reader)?;
+    let name = read_string(&mut reader)?;
+    let symbol = read_string(&mut reader)?;
+    let uri = read_string(&mut reader)?;
+    let seller_fee_basis_points = u16::from_le_bytes(reader.array()?);
+    let creators = read_option(&mut reader, |reader| {
+      let len = reader.u32()?;
+      (0..len)
+        .map(|_| {
+          Ok(MetaplexCreator {
+            address: read_pubkey(reader)?,
+            verified: read_bool(reader)?,
+            share: reader.take(1)?[0],
+          })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+    })?
+    .unwrap_or_default();
+    let primary_sale_happened = read_bool(&mut reader)?;
+    let is_mutable = read_bool(&mut reader)?;
+
+    // Accounts created by old program versions end before these fields.
+    let (token_standard, collection) = read_tail(&mut reader).unwrap_or_default();
+
+    Ok(Self {
+      update_authority,
+      mint,
+      name,
+      symbol,
+      uri,
+      seller_fee_basis_points,
+      creators,
+      primary_sale_happened,
+      is_mutable,
+      token_standard,
+      collection,
+    })
+  }
+
+  /// Fetch the Metaplex metadata of a mint, `None` if it has none.
+  pub fn fetch(mint: &Pubkey, source: &dyn AccountSource) -> anyhow::Result<Option<Self>> {
+    match source.get_account(&metadata_address(mint))? {
+      Some(account) if account.owner == TOKEN_METADATA_PROGRAM_ID => {
+        Ok(Some(Self::from_account_data(&account.data)?))
+      },
+      _ => Ok(None),
+    }
+  }
+
+  /// Get the machine-readable form of the metadata.
+  pub fn to_json(&self) -> Value {
+    let creators: Vec<Value> = self
+      .creators
+      .iter()
+      .map(|creator| {
+        json!({
+          "address": creator.address.to_string(),
+          "verified": creator.verified,
+          "share": creator.share,
+        })
+      })
+      .collect();
+    let collection = self.collection.as_ref().map(|collection| {
+      json!({
+        "key": collection.key.to_string(),
+        "verified": collection.verified,
+      })
+    });
+
+    json!({
+      "updateAuthority": self.update_authority.to_string(),
+      "mint": self.mint.to_string(),
+      "name": self.name,
+      "symbol": self.symbol,
+      "uri": self.uri,
+      "sellerFeeBasisPoints": self.seller_fee_basis_points,
+      "creators": creators,
+      "collection": collection,
+      "tokenStandard": self.token_standard,
+      "primarySaleHappened": self.primary_sale_happened,
+      "isMutable": self.is_mutable,
+    })
+  }
+
+  /// Add the metadata fields to a decoded account.
+  pub(super) fn add_fields(&self, decoded: DecodedAccount) -> DecodedAccount {
+    let creators: Vec<String> = self
+      .creators
+      .iter()
+      .map(|creator| {
+        let verified = match creator.verified {
+          true => ", verified",
+          false => "",
+        };
+        format!("{} ({}%{verified})", creator.address, creator.share)
+      })
+      .collect();
+    let collection = self
+      .collection
+      .as_ref()
+      .map(|collection| match collection.verified {
+        true => format!("{} (verified)", collection.key),
+        false => collection.key.to_string(),
+      });
+
+    decoded
+      .field("Update Authority", self.update_authority)
+      .field("Token Name", &self.name)
+      .field("Token Symbol", &self.symbol)
+      .field("Token URI", &self.uri)
+      .field("Seller Fee", format!("{} bps", self.seller_fee_basis_points))
+      .list_field("Creators", &creators)
+      .optional_field("Collection", collection)
+      .optional_field("Token Standard", self.token_standard.as_ref())
+      .field("Primary Sale Happened", self.primary_sale_happened)
+      .field("Is Mutable", self.is_mutable)
+  }
+}
+
+/// Read the token standard and the collection.
+fn read_tail(reader: &mut Reader) -> anyhow::Result<(Option<String>, Option<MetaplexCollection>)> {
+  // The edition nonce.
+  read_option(reader, |reader| Ok(reader.take(1)?[0]))?;
+
+  let token_standard = read_option(reader, |reader| {
+    let standard = match reader.take(1)?[0] {
+      0 => "Non-Fungible",
+      1 => "Fungible Asset",
+      2 => "Fungible",
+      3 => "Non-Fungible Edition",
+      4 => "Programmable Non-Fungible",
+      5 => "Programmable Non-Fungible Edition",
+      _ => "Unknown",
+    };
+    Ok(standard.to_string())
+  })?;
+
+  let collection = read_option(reader, |reader| {
+    Ok(MetaplexCollection {
+      verified: read_bool(reader)?,
+      key: read_pubkey(reader)?,
+    })
+  })?;
+
+  Ok((token_standard, collection))
+}
+
+/// Read a Borsh option.
+fn read_option<'a, T>(
+  reader: &mut Reader<'a>,
+  read: impl FnOnce(&mut Reader<'a>) -> anyhow::Result<T>,
+) -> anyhow::Result<Option<T>> {
+  match read_bool(reader)? {
+    true => Ok(Some(read(reader)?)),
+    false => Ok(None),
+  }
+}
+
+/// Read a bool.
+fn read_bool(reader: &mut Reader) -> anyhow::Result<bool> {
+  Ok(reader.take(1)?[0] != 0)
+}
+
+/// Read a public key.
+fn read_pubkey(reader: &mut Reader) -> anyhow::Result<Pubkey> {
+  Ok(Pubkey::new_from_array(reader.array()?))
+}
+
+/// Read a string, without the null padding Metaplex adds to fixed size fields.
+fn read_string(reader: &mut Reader) -> anyhow::Result<String> {
+  let len = reader.u32()? as usize;
+  let value = String::from_utf8_lossy(reader.take(len)?);
+  Ok(value.trim_end_matches('\0').to_string())
+}
+
+/// MetaplexDecoder decodes Metaplex metadata accounts.
+#[derive(Debug, Default)]
+pub struct MetaplexDecoder;
+
+/// Implementation of AccountDecoder for MetaplexDecoder.
+impl AccountDecoder for MetaplexDecoder {
+  fn name(&self) -> &str {
+    "metaplex"
+  }
+
+  fn can_decode(&self, _address: &Pubkey, account: &Account) -> bool {
+    account.owner == TOKEN_METADATA_PROGRAM_ID && account.data.first() == Some(&METADATA_V1_KEY)
+  }
+
+  fn decode(
+    &self,
+    _address: &Pubkey,
+    account: &Account,
+    _source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount> {
+    let metadata = MetaplexMetadata::from_account_data(&account.data)?;
+    let decoded = DecodedAccount::new("Metaplex Metadata")
+      .with_data(metadata.to_json())
+      .field("Mint", metadata.mint);
+    Ok(metadata.add_fields(decoded))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::decoders::TokenDecoder;
+  use crate::mock_rpc::{
+    MockRpcFixtures,
+    serve_mock_rpc,
+  };
+  use crate::test_support::{
+    account,
+    encode_metadata,
+    mint_account,
+  };
+  use solana_client::rpc_client::RpcClient;
+
+  #[test]
+  fn it_should_decode_metaplex_metadata() -> anyhow::Result<()> {
+    let metadata = MetaplexMetadata {
+      update_authority: Pubkey::new_unique(),
+      mint: Pubkey::new_unique(),
+      name: "Lumos".to_string(),
+      symbol: "LUM".to_string(),
+      uri: "https://example.com/lumos.json".to_string(),
+      seller_fee_basis_points: 500,
+      creators: vec![MetaplexCreator {
+        address: Pubkey::new_unique(),
+        verified: true,
+        share: 100,
+      }],
+      primary_sale_happened: false,
+      is_mutable: true,
+      token_standard: Some("Programmable Non-Fungible".to_string()),
+      collection: Some(MetaplexCollection {
+        key: Pubkey::new_unique(),
+        verified: true,
+      }),
+    };
+
+    let data = encode_metadata(&metadata);
+    assert_eq!(MetaplexMetadata::from_account_data(&data)?, metadata);
+
+    // Old accounts end after `is_mutable`.
+    let end = 1 + 32 + 32 + (4 + 32) + (4 + 10) + (4 + 200) + 2 + (1 + 4 + 34) + 2;
+    let old = MetaplexMetadata::from_account_data(&data[..end])?;
+    assert_eq!(old.name, "Lumos");
+    assert_eq!(old.collection, None);
+
+    let account = account(TOKEN_METADATA_PROGRAM_ID, data);
+    let address = metadata_address(&metadata.mint);
+    assert!(MetaplexDecoder.can_decode(&address, &account));
+
+    // Classic mints take their update authority from the Metaplex metadata.
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_account(address, account);
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let client = RpcClient::new(server.url());
+    let decoded = TokenDecoder::default().decode(&metadata.mint, &mint_account(0, 6), &client)?;
+    assert_eq!(
+      decoded.data["updateAuthority"],
+      json!(metadata.update_authority.to_string())
+    );
+    Ok(())
+  }
+}
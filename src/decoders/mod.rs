@@ -0,0 +1,537 @@
+//! Account decoders used by `analyze`.
+//!
+//! A `DecoderRegistry` holds the decoders and picks the first one that can
+//! decode an account, falling back to a hexdump. New account types are
+//! supported by implementing `AccountDecoder` and registering it.
+use std::str::FromStr as _;
+
+use serde_json::{
+  Value,
+  json,
+};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::schema::ConfigRoot;
+use crate::traits::{
+  AccountDecoder,
+  AccountSource,
+};
+
+mod anchor;
+mod hexdump;
+mod loader;
+mod metaplex;
+mod parsed;
+mod system;
+mod token;
+
+pub use anchor::{
+  AnchorDecoder,
+  AnchorIdl,
+};
+pub use hexdump::{
+  HexdumpDecoder,
+  hexdump,
+};
+pub use loader::LoaderDecoder;
+pub use metaplex::{
+  MetaplexCollection,
+  MetaplexDecoder,
+  MetaplexMetadata,
+  TOKEN_METADATA_PROGRAM_ID,
+  metadata_address,
+};
+pub use parsed::ParsedDecoder;
+pub use system::SystemDecoder;
+pub use token::{
+  TokenDecoder,
+  TokenExtension,
+  describe_extension,
+};
+
+/// DecodedAccount is the decoded, human readable form of an account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedAccount {
+  /// The detected account type, e.g. "Token Mint".
+  pub kind: String,
+  /// The decoded fields as labels and values, in display order.
+  /// Values spanning several lines are lists.
+  pub fields: Vec<(String, String)>,
+  /// The decoded account as a JSON object for machine-readable output, with
+  /// camelCase keys and `null` for missing values.
+  pub data: Value,
+}
+
+/// Implementation of DecodedAccount.
+impl DecodedAccount {
+  /// Create a decoded account of the given type without fields.
+  pub fn new(kind: &str) -> Self {
+    Self {
+      kind: kind.to_string(),
+      fields: vec![],
+      data: json!({}),
+    }
+  }
+
+  /// Set the machine-readable form of the account.
+  pub fn with_data(mut self, data: Value) -> Self {
+    self.data = data;
+    self
+  }
+
+  /// Add a field.
+  pub fn field(mut self, label: &str, value: impl ToString) -> Self {
+    self.fields.push((label.to_string(), value.to_string()));
+    self
+  }
+
+  /// Add an optional field, shown as "None" if missing.
+  pub fn optional_field(self, label: &str, value: Option<impl ToString>) -> Self {
+    match value {
+      Some(value) => self.field(label, value),
+      None => self.field(label, "None"),
+    }
+  }
+
+  /// Add a list field, one value per line.
+  pub fn list_field<T: ToString>(self, label: &str, values: &[T]) -> Self {
+    match values.is_empty() {
+      true => self.field(label, "None"),
+      false => {
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        self.field(label, values.join("\n"))
+      },
+    }
+  }
+
+  /// Add the fields of a JSON object, flattening nested objects into
+  /// labels like "Meta Authorized Staker". The object is also merged into
+  /// the machine-readable form.
+  pub fn json_fields(mut self, value: &Value) -> Self {
+    flatten_json(&mut self.fields, "", value);
+    if let (Some(data), Some(object)) = (self.data.as_object_mut(), value.as_object()) {
+      data.extend(object.clone());
+    }
+    self
+  }
+
+  /// Get the value of a field by its label.
+  pub fn get(&self, label: &str) -> Option<&str> {
+    self
+      .fields
+      .iter()
+      .find(|(field, _)| field == label)
+      .map(|(_, value)| value.as_str())
+  }
+}
+
+/// Decode the `{"type": ..., "info": ...}` JSON of a parsed account.
+/// # Arguments
+/// * `kind` - The account type, the parsed type is appended if not the same.
+/// * `parsed` - The parsed account.
+pub fn decode_parsed(kind: &str, parsed: &Value) -> DecodedAccount {
+  let parsed_type = parsed["type"].as_str().map(humanize);
+  let kind = match parsed_type {
+    Some(parsed_type) if parsed_type != kind => format!("{kind} ({parsed_type})"),
+    _ => kind.to_string(),
+  };
+
+  DecodedAccount::new(&kind).json_fields(&parsed["info"])
+}
+
+/// Turn a camelCase or snake_case key into a label, e.g. "authorizedVoter"
+/// into "Authorized Voter".
+pub fn humanize(key: &str) -> String {
+  let mut label = String::with_capacity(key.len() + 4);
+  let mut uppercase_next = true;
+
+  for c in key.chars() {
+    if c == '_' || c == ' ' {
+      uppercase_next = true;
+      continue;
+    }
+
+    if c.is_ascii_uppercase() && !label.is_empty() && !label.ends_with(' ') {
+      label.push(' ');
+    }
+
+    if uppercase_next && !label.is_empty() && !label.ends_with(' ') {
+      label.push(' ');
+    }
+
+    match uppercase_next {
+      true => label.push(c.to_ascii_uppercase()),
+      false => label.push(c),
+    }
+    uppercase_next = false;
+  }
+
+  label
+}
+
+/// Flatten a JSON value into labeled fields.
+fn flatten_json(fields: &mut Vec<(String, String)>, prefix: &str, value: &Value) {
+  let label = |key: &str| match prefix.is_empty() {
+    true => humanize(key),
+    false => format!("{prefix} {}", humanize(key)),
+  };
+
+  match value {
+    Value::Object(object) => {
+      for (key, value) in object.iter() {
+        match value {
+          Value::Object(_) => flatten_json(fields, &label(key), value),
+          value => fields.push((label(key), json_value(value))),
+        }
+      }
+    },
+    Value::Null => {},
+    value => fields.push((prefix.to_string(), json_value(value))),
+  }
+}
+
+/// Format a JSON value as a field value.
+/// Lists of strings are shown one per line, lists of objects by their count.
+fn json_value(value: &Value) -> String {
+  match value {
+    Value::Null => "None".to_string(),
+    Value::String(value) => value.clone(),
+    Value::Array(values) if values.is_empty() => "None".to_string(),
+    Value::Array(values) if values.iter().any(|value| value.is_object()) => {
+      format!("{} entries", values.len())
+    },
+    Value::Array(values) if values.iter().all(|value| value.is_string()) => {
+      values.iter().map(json_value).collect::<Vec<_>>().join("\n")
+    },
+    value => value.to_string(),
+  }
+}
+
+/// DecoderRegistry holds the account decoders of `analyze`.
+pub struct DecoderRegistry {
+  decoders: Vec<Box<dyn AccountDecoder>>,
+  fallback: HexdumpDecoder,
+}
+
+/// Default implementation of DecoderRegistry, with the built-in decoders.
+impl Default for DecoderRegistry {
+  fn default() -> Self {
+    Self::with_token_decoder(TokenDecoder::default())
+  }
+}
+
+/// Implementation of DecoderRegistry.
+impl DecoderRegistry {
+  /// Create a registry without decoders, only the hexdump fallback.
+  pub fn new() -> Self {
+    Self {
+      decoders: vec![],
+      fallback: HexdumpDecoder,
+    }
+  }
+
+  /// Create a registry with the built-in decoders, aware of the programs
+  /// cloned by the configuration so tokens depending on other programs are
+  /// flagged.
+  /// # Arguments
+  /// * `config` - The configuration.
+  pub fn for_config(config: &ConfigRoot) -> Self {
+    let cloned_programs = config
+      .program
+      .values()
+      .filter_map(|program| Pubkey::from_str(&program.address).ok())
+      .collect();
+
+    Self::with_token_decoder(TokenDecoder::with_cloned_programs(cloned_programs))
+  }
+
+  /// Create a registry with the built-in decoders and the given token decoder.
+  fn with_token_decoder(token_decoder: TokenDecoder) -> Self {
+    // The Anchor decoder takes any program owned account, so it goes last.
+    let mut registry = Self::new();
+    registry
+      .register(Box::new(AnchorDecoder))
+      .register(Box::new(MetaplexDecoder));
+    for decoder in ParsedDecoder::builtins() {
+      registry.register(Box::new(decoder));
+    }
+
+    registry
+      .register(Box::new(LoaderDecoder))
+      .register(Box::new(token_decoder))
+      .register(Box::new(SystemDecoder));
+    registry
+  }
+
+  /// Register a decoder. Decoders registered later are tried first, so they
+  /// can override the built-in ones.
+  pub fn register(&mut self, decoder: Box<dyn AccountDecoder>) -> &mut Self {
+    self.decoders.insert(0, decoder);
+    self
+  }
+
+  /// Get the decoder of an account, the hexdump fallback if none matches.
+  pub fn decoder_for(&self, address: &Pubkey, account: &Account) -> &dyn AccountDecoder {
+    self
+      .decoders
+      .iter()
+      .find(|decoder| decoder.can_decode(address, account))
+      .map(|decoder| decoder.as_ref())
+      .unwrap_or(&self.fallback)
+  }
+
+  /// Decode an account with the first decoder that can decode it.
+  /// # Arguments
+  /// * `address` - The account address.
+  /// * `account` - The account.
+  /// * `source` - The source to look up related accounts in.
+  pub fn decode(
+    &self,
+    address: &Pubkey,
+    account: &Account,
+    source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount> {
+    let decoder = self.decoder_for(address, account);
+    log::trace!("Decoding {address} with the {} decoder", decoder.name());
+    decoder.decode(address, account, source)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mock_rpc::{
+    MockRpcFixtures,
+    serve_mock_rpc,
+  };
+  use crate::test_support::{
+    account,
+    mint_account,
+    token_account,
+  };
+  use solana_client::rpc_client::RpcClient;
+  use solana_sdk::address_lookup_table::state::{
+    AddressLookupTable,
+    LookupTableMeta,
+  };
+  use solana_sdk::clock::Clock;
+  use solana_sdk::stake::state::StakeStateV2;
+  use solana_sdk::vote::state::{
+    VoteState,
+    VoteStateVersions,
+  };
+  use solana_sdk::{
+    address_lookup_table,
+    stake,
+    system_program,
+    sysvar,
+    vote,
+  };
+
+  #[test]
+  fn it_should_humanize_keys() -> anyhow::Result<()> {
+    assert_eq!(humanize("authorizedVoter"), "Authorized Voter");
+    assert_eq!(humanize("last_extended_slot"), "Last Extended Slot");
+    assert_eq!(humanize("rentExemptReserve"), "Rent Exempt Reserve");
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_pick_decoders_by_owner() -> anyhow::Result<()> {
+    let mint = Pubkey::new_unique();
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_account(mint, mint_account(0, 9));
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let client = RpcClient::new(server.url());
+    let registry = DecoderRegistry::default();
+
+    let wallet = registry.decode(
+      &Pubkey::new_unique(),
+      &account(system_program::ID, vec![]),
+      &client,
+    )?;
+    assert_eq!(wallet.kind, "System Account");
+    assert!(wallet.fields.is_empty());
+
+    let decoded = registry.decode(&mint, &mint_account(0, 9), &client)?;
+    assert_eq!(decoded.kind, "Token Mint");
+    assert_eq!(decoded.get("Decimals"), Some("9"));
+    assert_eq!(decoded.data["decimals"], json!(9));
+    assert_eq!(decoded.data["mintAuthority"], Value::Null);
+
+    let decoded = registry.decode(
+      &Pubkey::new_unique(),
+      &token_account(mint, Pubkey::new_unique(), 2_500_000_000),
+      &client,
+    )?;
+    assert_eq!(decoded.kind, "Token Account");
+    assert_eq!(decoded.get("Amount"), Some("2.5 (2500000000 raw)"));
+    assert_eq!(decoded.data["uiAmount"], json!("2.5"));
+
+    let unknown = registry.decode(
+      &Pubkey::new_unique(),
+      &account(Pubkey::new_unique(), b"lumos".to_vec()),
+      &client,
+    )?;
+    assert_eq!(unknown.kind, "Unknown");
+    assert_eq!(
+      unknown.get("Data"),
+      Some("00000000  6c 75 6d 6f 73                                    |lumos|")
+    );
+
+    let stake = registry.decode(
+      &Pubkey::new_unique(),
+      &account(stake::program::ID, vec![0; StakeStateV2::size_of()]),
+      &client,
+    )?;
+    assert_eq!(stake.kind, "Stake Account (Uninitialized)");
+
+    let mut vote_data = vec![0; VoteState::size_of()];
+    let node = Pubkey::new_unique();
+    let mut vote_state = VoteState::default();
+    vote_state.node_pubkey = node;
+    VoteState::serialize(&VoteStateVersions::new_current(vote_state), &mut vote_data)?;
+    let vote = registry.decode(
+      &Pubkey::new_unique(),
+      &account(vote::program::ID, vote_data),
+      &client,
+    )?;
+    assert_eq!(vote.kind, "Vote Account (Vote)");
+    assert_eq!(vote.get("Node Pubkey"), Some(node.to_string().as_str()));
+
+    let clock = Clock {
+      slot: 42,
+      ..Clock::default()
+    };
+    let decoded = registry.decode(
+      &sysvar::clock::ID,
+      &account(sysvar::ID, bincode::serialize(&clock)?),
+      &client,
+    )?;
+    assert_eq!(decoded.kind, "Sysvar (Clock)");
+    assert_eq!(decoded.get("Slot"), Some("42"));
+
+    let table_address = Pubkey::new_unique();
+    let table = AddressLookupTable {
+      meta: LookupTableMeta::default(),
+      addresses: vec![table_address].into(),
+    };
+    let decoded = registry.decode(
+      &Pubkey::new_unique(),
+      &account(address_lookup_table::program::ID, table.serialize_for_tests()?),
+      &client,
+    )?;
+    assert_eq!(decoded.kind, "Lookup Table");
+    assert_eq!(decoded.get("Addresses"), Some(table_address.to_string().as_str()));
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_report_metaplex_metadata_errors() -> anyhow::Result<()> {
+    let mint = Pubkey::new_unique();
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_account(
+      metadata_address(&mint),
+      account(TOKEN_METADATA_PROGRAM_ID, vec![9; 16]),
+    );
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let client = RpcClient::new(server.url());
+
+    let decoded = DecoderRegistry::default().decode(&mint, &mint_account(0, 6), &client)?;
+    assert_eq!(decoded.kind, "Token Mint");
+    assert_eq!(
+      decoded.get("Metadata"),
+      Some("Error: Not a Metaplex metadata account, key 9")
+    );
+    assert_eq!(decoded.get("Decimals"), Some("6"));
+    assert!(decoded.data["metadataError"].is_string());
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_replace_the_built_in_token_decoder() -> anyhow::Result<()> {
+    let config: ConfigRoot = toml::from_str(
+      r#"
+      [general]
+      rpc_endpoint = "http://localhost:8899"
+
+      [account]
+
+      [program]
+      "#,
+    )?;
+
+    let token_decoders = |registry: &DecoderRegistry| {
+      registry
+        .decoders
+        .iter()
+        .filter(|decoder| decoder.name() == TokenDecoder::default().name())
+        .count()
+    };
+    assert_eq!(token_decoders(&DecoderRegistry::default()), 1);
+    assert_eq!(token_decoders(&DecoderRegistry::for_config(&config)), 1);
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_flag_token_extensions_affecting_tests() -> anyhow::Result<()> {
+    use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+    use spl_token_2022::extension::transfer_hook::TransferHook;
+    use spl_token_2022::extension::{
+      BaseStateWithExtensionsMut as _,
+      ExtensionType,
+      StateWithExtensionsMut,
+    };
+
+    let hook_program = Pubkey::new_unique();
+    let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+      ExtensionType::TransferFeeConfig,
+      ExtensionType::TransferHook,
+    ])?;
+    let mut data = vec![0; len];
+    let mut state = StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data)?;
+    let fee = state.init_extension::<TransferFeeConfig>(true)?;
+    fee.newer_transfer_fee.transfer_fee_basis_points = 50.into();
+    fee.newer_transfer_fee.maximum_fee = 1_000.into();
+    state.init_extension::<TransferHook>(true)?.program_id = Some(hook_program).try_into()?;
+    state.base = spl_token_2022::state::Mint {
+      decimals: 6,
+      is_initialized: true,
+      ..Default::default()
+    };
+    state.pack_base();
+    state.init_account_type()?;
+
+    let mint = account(spl_token_2022::ID, data);
+    let server = serve_mock_rpc(MockRpcFixtures::new(), 0)?;
+    let client = RpcClient::new(server.url());
+
+    let decoded =
+      TokenDecoder::with_cloned_programs(Default::default()).decode(&Pubkey::new_unique(), &mint, &client)?;
+    assert_eq!(decoded.kind, "Token Mint");
+    assert_eq!(
+      decoded.get("Transfer Hook"),
+      Some(format!("Authority: None\nProgram: {hook_program}").as_str())
+    );
+    assert!(
+      decoded
+        .get("Transfer Fee Config")
+        .is_some_and(|fee| fee.contains("Newer Fee: 50 bps, max 1000 (from epoch 0)"))
+    );
+    let warnings = decoded.get("Test Warnings").unwrap_or_default();
+    assert!(warnings.contains("Transfers withhold a fee"));
+    assert!(warnings.contains(&format!("The transfer hook program {hook_program} isn't cloned")));
+
+    let decoded = TokenDecoder::with_cloned_programs([hook_program].into()).decode(
+      &Pubkey::new_unique(),
+      &mint,
+      &client,
+    )?;
+    assert_eq!(
+      decoded.get("Test Warnings"),
+      Some("Transfers withhold a fee, recipients get less than the amount sent")
+    );
+    Ok(())
+  }
+}
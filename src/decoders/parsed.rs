@@ -0,0 +1,93 @@
+use anyhow::Context;
+use serde_json::Value;
+use solana_account_decoder::parse_address_lookup_table::parse_address_lookup_table;
+use solana_account_decoder::parse_stake::parse_stake;
+use solana_account_decoder::parse_sysvar::parse_sysvar;
+use solana_account_decoder::parse_vote::parse_vote;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{
+  address_lookup_table,
+  stake,
+  sysvar,
+  vote,
+};
+
+use super::{
+  DecodedAccount,
+  decode_parsed,
+};
+use crate::traits::{
+  AccountDecoder,
+  AccountSource,
+};
+
+/// Parse the data of an account into its `{"type": ..., "info": ...}` JSON.
+type ParseFn = fn(&Pubkey, &[u8]) -> anyhow::Result<Value>;
+
+/// The native accounts decoded with the parsers of `solana_account_decoder`,
+/// as the owner, decoder name, account type and parser.
+const PARSED_ACCOUNTS: [(Pubkey, &str, &str, ParseFn); 4] = [
+  (stake::program::ID, "stake", "Stake Account", |_, data| {
+    Ok(serde_json::to_value(parse_stake(data)?)?)
+  }),
+  (vote::program::ID, "vote", "Vote Account", |_, data| {
+    Ok(serde_json::to_value(parse_vote(data)?)?)
+  }),
+  (sysvar::ID, "sysvar", "Sysvar", |address, data| {
+    Ok(serde_json::to_value(parse_sysvar(data, address)?)?)
+  }),
+  (
+    address_lookup_table::program::ID,
+    "lookup-table",
+    "Lookup Table",
+    |_, data| Ok(serde_json::to_value(parse_address_lookup_table(data)?)?),
+  ),
+];
+
+/// ParsedDecoder decodes the accounts of a native program, like stake and
+/// vote accounts, sysvars and address lookup tables.
+#[derive(Debug, Clone, Copy)]
+pub struct ParsedDecoder {
+  owner: Pubkey,
+  name: &'static str,
+  kind: &'static str,
+  parse: ParseFn,
+}
+
+/// Implementation of ParsedDecoder.
+impl ParsedDecoder {
+  /// Get the decoders of all supported native programs.
+  pub fn builtins() -> impl Iterator<Item = Self> {
+    PARSED_ACCOUNTS
+      .into_iter()
+      .map(|(owner, name, kind, parse)| Self {
+        owner,
+        name,
+        kind,
+        parse,
+      })
+  }
+}
+
+/// Implementation of AccountDecoder for ParsedDecoder.
+impl AccountDecoder for ParsedDecoder {
+  fn name(&self) -> &str {
+    self.name
+  }
+
+  fn can_decode(&self, _address: &Pubkey, account: &Account) -> bool {
+    account.owner == self.owner
+  }
+
+  fn decode(
+    &self,
+    address: &Pubkey,
+    account: &Account,
+    _source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount> {
+    let parsed = (self.parse)(address, &account.data)
+      .with_context(|| format!("Failed to parse {}", self.kind.to_lowercase()))?;
+    Ok(decode_parsed(self.kind, &parsed))
+  }
+}
@@ -0,0 +1,45 @@
+use solana_account_decoder::parse_nonce::parse_nonce;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+
+use super::hexdump::decode_raw;
+use super::{
+  DecodedAccount,
+  decode_parsed,
+};
+use crate::traits::{
+  AccountDecoder,
+  AccountSource,
+};
+
+/// SystemDecoder decodes system accounts: wallets and durable nonces.
+#[derive(Debug, Default)]
+pub struct SystemDecoder;
+
+/// Implementation of AccountDecoder for SystemDecoder.
+impl AccountDecoder for SystemDecoder {
+  fn name(&self) -> &str {
+    "system"
+  }
+
+  fn can_decode(&self, _address: &Pubkey, account: &Account) -> bool {
+    account.owner == system_program::ID
+  }
+
+  fn decode(
+    &self,
+    _address: &Pubkey,
+    account: &Account,
+    _source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount> {
+    if account.data.is_empty() {
+      return Ok(DecodedAccount::new("System Account"));
+    }
+
+    match parse_nonce(&account.data) {
+      Ok(nonce) => Ok(decode_parsed("Nonce Account", &serde_json::to_value(nonce)?)),
+      Err(_) => Ok(decode_raw("System Account", &account.data)),
+    }
+  }
+}
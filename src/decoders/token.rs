@@ -0,0 +1,466 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr as _;
+
+use serde::Serialize;
+use serde_json::{
+  Value,
+  json,
+};
+use solana_account_decoder::parse_token::{
+  UiAccountState,
+  is_known_spl_token_id,
+};
+use solana_account_decoder::parse_token_extension::{
+  UiExtension,
+  UiTransferFee,
+};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::generic_token_account::GenericTokenAccount as _;
+
+use super::{
+  DecodedAccount,
+  MetaplexMetadata,
+  humanize,
+};
+use crate::solana_utils::{
+  mint_details,
+  token_account_details,
+};
+use crate::traits::{
+  AccountDecoder,
+  AccountSource,
+};
+
+/// A Token-2022 extension of a mint or token account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenExtension {
+  /// The extension name, e.g. "Transfer Hook".
+  pub name: String,
+  /// The extension state as labels and values.
+  #[serde(skip)]
+  pub fields: Vec<(String, String)>,
+  /// The extension state as parsed by `solana account --output json`.
+  pub state: Value,
+  /// How the extension affects local tests, if it does.
+  pub warning: Option<String>,
+  /// The program invoked on every transfer, for transfer hooks.
+  pub hook_program: Option<String>,
+}
+
+/// Display implementation for TokenExtension, the name and its fields on one line.
+impl fmt::Display for TokenExtension {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let fields: Vec<String> = self
+      .fields
+      .iter()
+      .map(|(label, value)| format!("{label}: {value}"))
+      .collect();
+
+    match fields.is_empty() {
+      true => write!(f, "{}", self.name),
+      false => write!(f, "{} ({})", self.name, fields.join(", ")),
+    }
+  }
+}
+
+/// TokenDecoder decodes the mints and token accounts of SPL Token and
+/// Token-2022.
+#[derive(Debug, Default)]
+pub struct TokenDecoder {
+  /// The programs loaded by the local validator, if known. Transfer hooks
+  /// whose program isn't among them are flagged.
+  cloned_programs: Option<HashSet<Pubkey>>,
+}
+
+/// Implementation of TokenDecoder.
+impl TokenDecoder {
+  /// Create a decoder that flags transfer hook programs missing from the
+  /// given programs.
+  pub fn with_cloned_programs(cloned_programs: HashSet<Pubkey>) -> Self {
+    Self {
+      cloned_programs: Some(cloned_programs),
+    }
+  }
+
+  /// Decode a token mint.
+  /// Classic mints have no metadata extension, their Metaplex metadata is
+  /// shown instead. Metadata that can't be fetched or decoded is reported as
+  /// an error field, without failing the mint.
+  fn decode_mint(
+    &self,
+    address: &Pubkey,
+    account: &Account,
+    source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount> {
+    let details = mint_details(account)?;
+    let mut data = serde_json::to_value(&details)?;
+    data["metaplex"] = Value::Null;
+
+    let mut decoded = DecodedAccount::new("Token Mint")
+      .optional_field("Mint Authority", details.mint_authority)
+      .optional_field("Freeze Authority", details.freeze_authority);
+
+    decoded = match details.metadata {
+      Some(metadata) => decoded
+        .optional_field("Update Authority", metadata.update_authority)
+        .optional_field("Token Name", metadata.name)
+        .optional_field("Token Symbol", metadata.symbol)
+        .optional_field("Token URI", metadata.uri),
+      _ => match MetaplexMetadata::fetch(address, source) {
+        Ok(Some(metadata)) => {
+          data["updateAuthority"] = json!(metadata.update_authority.to_string());
+          data["metaplex"] = metadata.to_json();
+          metadata.add_fields(decoded.field("Metadata", "Metaplex"))
+        },
+        Ok(None) => decoded.field("Metadata", "None"),
+        Err(err) => {
+          data["metadataError"] = json!(format!("{err:#}"));
+          decoded.field("Metadata", format!("Error: {err:#}"))
+        },
+      },
+    };
+
+    decoded = decoded
+      .field("Decimals", details.decimals)
+      .field("Supply", details.supply)
+      .field("Is Initialized", details.is_initialized)
+      .field("Extensions", !details.extension_details.is_empty());
+
+    let extensions: Vec<TokenExtension> = details.extension_details.iter().map(describe_extension).collect();
+    data["extensionDetails"] = serde_json::to_value(&extensions)?;
+
+    let mut warnings = vec![];
+    for extension in extensions.iter() {
+      let fields: Vec<String> = extension
+        .fields
+        .iter()
+        .map(|(label, value)| format!("{label}: {value}"))
+        .collect();
+      decoded = match fields.is_empty() {
+        true => decoded.field(&extension.name, "Enabled"),
+        false => decoded.list_field(&extension.name, &fields),
+      };
+
+      if let Some(warning) = &extension.warning {
+        warnings.push(warning.clone());
+      }
+
+      if let (Some(hook_program), Some(cloned_programs)) = (&extension.hook_program, &self.cloned_programs) {
+        if !Pubkey::from_str(hook_program).is_ok_and(|program| cloned_programs.contains(&program)) {
+          warnings.push(format!(
+            "The transfer hook program {hook_program} isn't cloned, transfers will fail"
+          ));
+        }
+      }
+    }
+
+    if !warnings.is_empty() {
+      decoded = decoded.list_field("Test Warnings", &warnings);
+    }
+    data["testWarnings"] = json!(warnings);
+
+    Ok(decoded.with_data(data))
+  }
+}
+
+/// Implementation of AccountDecoder for TokenDecoder.
+impl AccountDecoder for TokenDecoder {
+  fn name(&self) -> &str {
+    "token"
+  }
+
+  fn can_decode(&self, _address: &Pubkey, account: &Account) -> bool {
+    is_known_spl_token_id(&account.owner)
+  }
+
+  fn decode(
+    &self,
+    address: &Pubkey,
+    account: &Account,
+    source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount> {
+    match spl_token_2022::state::Account::valid_account_data(&account.data) {
+      true => decode_token_account(account, source),
+      false => self.decode_mint(address, account, source),
+    }
+  }
+}
+
+/// Decode a token account, with the UI amount based on the decimals of its mint.
+fn decode_token_account(account: &Account, source: &dyn AccountSource) -> anyhow::Result<DecodedAccount> {
+  let mint = spl_token_2022::state::Account::unpack_account_mint_unchecked(&account.data);
+  let mint_account = source
+    .get_account(mint)?
+    .ok_or_else(|| anyhow::anyhow!("Mint account not found: {}", mint))?;
+  let decimals = mint_details(&mint_account)?.decimals;
+
+  let details = token_account_details(account, decimals)?;
+  let extensions: Vec<TokenExtension> = details.extensions.iter().map(describe_extension).collect();
+  let mut data = serde_json::to_value(&details)?;
+  data["extensions"] = serde_json::to_value(&extensions)?;
+
+  Ok(
+    DecodedAccount::new("Token Account")
+      .with_data(data)
+      .field("Mint", details.mint)
+      .field("Owner", details.owner)
+      .field(
+        "Amount",
+        format!("{} ({} raw)", details.ui_amount, details.amount),
+      )
+      .field("Decimals", details.decimals)
+      .optional_field("Delegate", details.delegate)
+      .optional_field("Delegated Amount", details.delegated_amount)
+      .optional_field("Close Authority", details.close_authority)
+      .field("State", details.state)
+      .field("Is Native", details.is_native)
+      .list_field("Extensions", &extensions),
+  )
+}
+
+/// Format an optional address or authority.
+fn or_none(value: &Option<String>) -> String {
+  value.clone().unwrap_or("None".to_string())
+}
+
+/// Describe a Token-2022 extension with its state, and how it affects local
+/// tests if it does.
+pub fn describe_extension(extension: &UiExtension) -> TokenExtension {
+  let value = serde_json::to_value(extension).unwrap_or_default();
+  let mut warning = None;
+  let mut hook_program = None;
+  let field = |label: &str, value: String| (label.to_string(), value);
+
+  let (name, fields) = match extension {
+    UiExtension::TransferFeeConfig(config) => {
+      let fee = |fee: &UiTransferFee| {
+        format!(
+          "{} bps, max {} (from epoch {})",
+          fee.transfer_fee_basis_points, fee.maximum_fee, fee.epoch
+        )
+      };
+
+      if config.older_transfer_fee.transfer_fee_basis_points > 0
+        || config.newer_transfer_fee.transfer_fee_basis_points > 0
+      {
+        warning = Some("Transfers withhold a fee, recipients get less than the amount sent".to_string());
+      }
+
+      (
+        "Transfer Fee Config",
+        vec![
+          field("Config Authority", or_none(&config.transfer_fee_config_authority)),
+          field(
+            "Withdraw Withheld Authority",
+            or_none(&config.withdraw_withheld_authority),
+          ),
+          field("Withheld Amount", config.withheld_amount.to_string()),
+          field("Older Fee", fee(&config.older_transfer_fee)),
+          field("Newer Fee", fee(&config.newer_transfer_fee)),
+        ],
+      )
+    },
+    UiExtension::TransferFeeAmount(amount) => (
+      "Transfer Fee Amount",
+      vec![field("Withheld Amount", amount.withheld_amount.to_string())],
+    ),
+    UiExtension::MintCloseAuthority(config) => (
+      "Mint Close Authority",
+      vec![field("Close Authority", or_none(&config.close_authority))],
+    ),
+    UiExtension::ConfidentialTransferMint(config) => (
+      "Confidential Transfer",
+      vec![
+        field("Authority", or_none(&config.authority)),
+        field(
+          "Auto Approve New Accounts",
+          config.auto_approve_new_accounts.to_string(),
+        ),
+        field("Auditor ElGamal Pubkey", or_none(&config.auditor_elgamal_pubkey)),
+      ],
+    ),
+    UiExtension::DefaultAccountState(config) => {
+      let state = match config.account_state {
+        UiAccountState::Uninitialized => "uninitialized",
+        UiAccountState::Initialized => "initialized",
+        UiAccountState::Frozen => "frozen",
+      };
+      if matches!(config.account_state, UiAccountState::Frozen) {
+        warning = Some("New token accounts start frozen until the freeze authority thaws them".to_string());
+      }
+
+      (
+        "Default Account State",
+        vec![field("Account State", state.to_string())],
+      )
+    },
+    UiExtension::NonTransferable => {
+      warning = Some("Tokens can't be transferred, only minted and burned".to_string());
+      ("Non-Transferable", vec![])
+    },
+    UiExtension::InterestBearingConfig(config) => (
+      "Interest-Bearing",
+      vec![
+        field("Rate Authority", or_none(&config.rate_authority)),
+        field("Current Rate", format!("{} bps", config.current_rate)),
+        field(
+          "Pre-Update Average Rate",
+          format!("{} bps", config.pre_update_average_rate),
+        ),
+        field(
+          "Initialization Timestamp",
+          config.initialization_timestamp.to_string(),
+        ),
+        field("Last Update Timestamp", config.last_update_timestamp.to_string()),
+      ],
+    ),
+    UiExtension::PermanentDelegate(config) => {
+      if config.delegate.is_some() {
+        warning = Some("The permanent delegate can transfer or burn tokens from any account".to_string());
+      }
+
+      (
+        "Permanent Delegate",
+        vec![field("Delegate", or_none(&config.delegate))],
+      )
+    },
+    UiExtension::TransferHook(config) => {
+      // Whether the hook program is loaded is checked by the TokenDecoder.
+      hook_program = config.program_id.clone();
+
+      (
+        "Transfer Hook",
+        vec![
+          field("Authority", or_none(&config.authority)),
+          field("Program", or_none(&config.program_id)),
+        ],
+      )
+    },
+    UiExtension::MetadataPointer(pointer) => (
+      "Metadata Pointer",
+      vec![
+        field("Authority", or_none(&pointer.authority)),
+        field("Metadata Address", or_none(&pointer.metadata_address)),
+      ],
+    ),
+    UiExtension::TokenMetadata(metadata) => {
+      let mut fields = vec![
+        field("Update Authority", or_none(&metadata.update_authority)),
+        field("Name", metadata.name.clone()),
+        field("Symbol", metadata.symbol.clone()),
+        field("URI", metadata.uri.clone()),
+      ];
+      fields.extend(
+        metadata
+          .additional_metadata
+          .iter()
+          .map(|(key, value)| (key.clone(), value.clone())),
+      );
+
+      ("Token Metadata", fields)
+    },
+    UiExtension::GroupPointer(pointer) => (
+      "Group Pointer",
+      vec![
+        field("Authority", or_none(&pointer.authority)),
+        field("Group Address", or_none(&pointer.group_address)),
+      ],
+    ),
+    UiExtension::GroupMemberPointer(pointer) => (
+      "Group Member Pointer",
+      vec![
+        field("Authority", or_none(&pointer.authority)),
+        field("Member Address", or_none(&pointer.member_address)),
+      ],
+    ),
+    UiExtension::TokenGroup(group) => (
+      "Token Group",
+      vec![
+        field("Update Authority", or_none(&group.update_authority)),
+        field("Size", group.size.to_string()),
+        field("Max Size", group.max_size.to_string()),
+      ],
+    ),
+    UiExtension::TokenGroupMember(member) => (
+      "Token Group Member",
+      vec![
+        field("Group", member.group.clone()),
+        field("Member Number", member.member_number.to_string()),
+      ],
+    ),
+    UiExtension::PausableConfig(config) => {
+      if config.paused {
+        warning = Some("The mint is paused, transfers, mints and burns fail".to_string());
+      }
+
+      (
+        "Pausable",
+        vec![
+          field("Authority", or_none(&config.authority)),
+          field("Paused", config.paused.to_string()),
+        ],
+      )
+    },
+    UiExtension::ScaledUiAmountConfig(config) => (
+      "Scaled UI Amount",
+      vec![
+        field("Authority", or_none(&config.authority)),
+        field("Multiplier", config.multiplier.clone()),
+        field("New Multiplier", config.new_multiplier.clone()),
+        field(
+          "New Multiplier Effective Timestamp",
+          config.new_multiplier_effective_timestamp.to_string(),
+        ),
+      ],
+    ),
+    UiExtension::ImmutableOwner => ("Immutable Owner", vec![]),
+    UiExtension::NonTransferableAccount => ("Non-Transferable Account", vec![]),
+    UiExtension::PausableAccount => ("Pausable Account", vec![]),
+    UiExtension::MemoTransfer(config) => {
+      if config.require_incoming_transfer_memos {
+        warning = Some("Incoming transfers must be preceded by a memo instruction".to_string());
+      }
+
+      (
+        "Memo Transfer",
+        vec![field(
+          "Require Incoming Transfer Memos",
+          config.require_incoming_transfer_memos.to_string(),
+        )],
+      )
+    },
+    UiExtension::CpiGuard(config) => {
+      if config.lock_cpi {
+        warning = Some("Programs can't transfer, burn or approve from this account via CPI".to_string());
+      }
+
+      ("CPI Guard", vec![field("Lock CPI", config.lock_cpi.to_string())])
+    },
+    // The remaining extensions are shown with their raw state.
+    _ => {
+      let name = value["extension"]
+        .as_str()
+        .map(humanize)
+        .unwrap_or("Unknown".to_string());
+      let decoded = DecodedAccount::new(&name).json_fields(&value["state"]);
+      return TokenExtension {
+        name,
+        fields: decoded.fields,
+        state: value["state"].clone(),
+        warning: None,
+        hook_program: None,
+      };
+    },
+  };
+
+  TokenExtension {
+    name: name.to_string(),
+    fields,
+    state: value["state"].clone(),
+    warning,
+    hook_program,
+  }
+}
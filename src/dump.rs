@@ -0,0 +1,70 @@
+use anyhow::Context;
+use hashbrown::HashMap;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::fs;
+use std::path::Path;
+
+use crate::utils::write_account_json;
+
+/// The maximum number of accounts fetched in a single `getMultipleAccounts` call.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Fetch the accounts owned by the given programs and the given addresses.
+/// Addresses that don't exist are skipped.
+/// # Arguments
+/// * `client` - The RPC client to fetch the accounts with.
+/// * `owners` - The programs whose accounts are fetched.
+/// * `addresses` - The addresses of the accounts to fetch.
+pub fn fetch_accounts(
+  client: &RpcClient,
+  owners: &[Pubkey],
+  addresses: &[Pubkey],
+) -> anyhow::Result<HashMap<Pubkey, Account>> {
+  let mut accounts = HashMap::new();
+  for owner in owners.iter() {
+    let owned = client
+      .get_program_accounts(owner)
+      .with_context(|| format!("Failed to get accounts owned by: {owner}"))?;
+    accounts.extend(owned);
+  }
+
+  for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+    let fetched = client
+      .get_multiple_accounts(chunk)
+      .with_context(|| "Failed to get multiple accounts")?;
+    for (address, account) in chunk.iter().zip(fetched) {
+      if let Some(account) = account {
+        accounts.insert(*address, account);
+      }
+    }
+  }
+
+  Ok(accounts)
+}
+
+/// Dump the accounts owned by the given programs and the given addresses into a
+/// directory, one `{address}.json` file per account.
+/// The directory can be loaded back into the validator with `--account-dir`.
+/// Returns the number of accounts written.
+/// # Arguments
+/// * `client` - The RPC client to fetch the accounts with.
+/// * `owners` - The programs whose accounts are dumped.
+/// * `addresses` - The addresses of the accounts to dump.
+/// * `out_dir` - The directory to write the accounts to.
+pub fn dump_accounts(
+  client: &RpcClient,
+  owners: &[Pubkey],
+  addresses: &[Pubkey],
+  out_dir: &Path,
+) -> anyhow::Result<usize> {
+  fs::create_dir_all(out_dir)?;
+
+  let accounts = fetch_accounts(client, owners, addresses)?;
+  for (address, account) in accounts.iter() {
+    write_account_json(out_dir, address, account)?;
+  }
+
+  Ok(accounts.len())
+}
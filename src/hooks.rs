@@ -0,0 +1,200 @@
+use anyhow::Context;
+use base64::prelude::*;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::{
+  AccountMeta,
+  Instruction,
+};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{
+  Keypair,
+  Signer as _,
+  read_keypair_file,
+};
+use solana_sdk::transaction::Transaction;
+use std::io::{
+  BufRead as _,
+  BufReader,
+};
+use std::path::PathBuf;
+use std::process::{
+  Command,
+  Stdio,
+};
+use std::str::FromStr as _;
+
+use crate::connection::ConnectionInfo;
+use crate::handle_tty_output;
+use crate::lumos_context::LumosContext;
+use crate::schema::HookConfig;
+
+/// The lamports airdropped to a hook keypair without funds.
+const HOOK_AIRDROP_LAMPORTS: u64 = 10 * LAMPORTS_PER_SOL;
+
+/// Run the post start hooks in order, stopping at the first failure.
+/// # Arguments
+/// * `context` - The lumos context.
+pub fn run_post_start_hooks(context: &LumosContext) -> anyhow::Result<()> {
+  let hooks = &context.config.hooks.post_start;
+  for (i, hook) in hooks.iter().enumerate() {
+    let _ = context
+      .pb
+      .println(format!("Running post start hook {}/{}...", i + 1, hooks.len()));
+
+    run_hook(context, hook).with_context(|| format!("Post start hook {} failed", i + 1))?;
+  }
+
+  Ok(())
+}
+
+/// Run a single hook.
+fn run_hook(context: &LumosContext, hook: &HookConfig) -> anyhow::Result<()> {
+  match (&hook.command, &hook.program) {
+    (Some(command), None) => run_command(context, command),
+    (None, Some(program)) => submit_instruction(context, hook, program),
+    (Some(_), Some(_)) => anyhow::bail!("A hook can't have both a `command` and a `program`"),
+    (None, None) => anyhow::bail!("A hook needs either a `command` or a `program`"),
+  }
+}
+
+/// Run a shell command with the connection details exported.
+fn run_command(context: &LumosContext, command: &str) -> anyhow::Result<()> {
+  let mut cmd = Command::new("sh");
+  cmd
+    .arg("-c")
+    .arg(command)
+    .envs(ConnectionInfo::from_context(context).to_env())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped());
+
+  let mut child = cmd.spawn()?;
+
+  handle_tty_output!(child.stdout, context);
+  handle_tty_output!(child.stderr, context);
+
+  let status = child.wait()?;
+  if !status.success() {
+    anyhow::bail!("Command `{command}` exited with {status}");
+  }
+
+  Ok(())
+}
+
+/// Submit the instruction of a hook to the local validator.
+fn submit_instruction(context: &LumosContext, hook: &HookConfig, program: &str) -> anyhow::Result<()> {
+  let config = &context.config;
+  let program_id = Pubkey::from_str(config.resolve_address(program))
+    .with_context(|| format!("Invalid program address: {program}"))?;
+
+  let payer = read_hook_keypair(hook)?;
+  let accounts = hook
+    .accounts
+    .iter()
+    .map(|account| {
+      let pubkey = Pubkey::from_str(config.resolve_address(&account.address))
+        .with_context(|| format!("Invalid account address: {}", account.address))?;
+      let signer = account.signer.unwrap_or(false);
+      if signer && pubkey != payer.pubkey() {
+        anyhow::bail!(
+          "Only the hook keypair can sign, but {} is marked as signer",
+          account.address
+        );
+      }
+
+      let meta = match account.writable.unwrap_or(false) {
+        true => AccountMeta::new(pubkey, signer),
+        false => AccountMeta::new_readonly(pubkey, signer),
+      };
+
+      Ok(meta)
+    })
+    .collect::<anyhow::Result<Vec<_>>>()?;
+
+  let data = match &hook.data {
+    Some(data) => BASE64_STANDARD
+      .decode(data)
+      .with_context(|| "Failed to decode base64 instruction data")?,
+    None => vec![],
+  };
+
+  let client = RpcClient::new_with_commitment(context.local_rpc_url(), CommitmentConfig::confirmed());
+
+  // Fund the payer if it has nothing to pay the fees with.
+  if client.get_balance(&payer.pubkey())? == 0 {
+    let signature = client.request_airdrop(&payer.pubkey(), HOOK_AIRDROP_LAMPORTS)?;
+    client.poll_for_signature(&signature)?;
+  }
+
+  let instruction = Instruction::new_with_bytes(program_id, &data, accounts);
+  let blockhash = client.get_latest_blockhash()?;
+  let transaction =
+    Transaction::new_signed_with_payer(&[instruction], Some(&payer.pubkey()), &[&payer], blockhash);
+
+  let signature = client
+    .send_and_confirm_transaction(&transaction)
+    .with_context(|| format!("Failed to submit instruction to program: {program}"))?;
+
+  let _ = context
+    .pb
+    .println(format!("Submitted hook transaction: {signature}"));
+  Ok(())
+}
+
+/// Read the keypair of a hook, defaulting to the Solana CLI keypair.
+fn read_hook_keypair(hook: &HookConfig) -> anyhow::Result<Keypair> {
+  let keypair = hook.keypair.as_deref().unwrap_or("~/.config/solana/id.json");
+  let path = match keypair.strip_prefix("~/") {
+    Some(relative) => {
+      let home = std::env::var("HOME").with_context(|| "Failed to get the home directory")?;
+      PathBuf::from(home).join(relative)
+    },
+    None => PathBuf::from(keypair),
+  };
+
+  read_keypair_file(&path).map_err(|err| anyhow::anyhow!("Failed to read keypair {path:?}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::schema::ConfigRoot;
+  use std::sync::Arc;
+
+  /// Create a context whose only post start hook runs the given command.
+  fn command_hook_context(command: &str) -> anyhow::Result<LumosContext> {
+    let config: ConfigRoot = toml::from_str(&format!(
+      r#"
+      [general]
+      rpc_endpoint = "http://localhost:8899"
+
+      [account]
+
+      [program]
+
+      [[hooks.post_start]]
+      command = '{command}'
+      "#
+    ))?;
+
+    Ok(LumosContext::new(
+      Arc::new(config),
+      "http://localhost:8899",
+      None,
+      false,
+    ))
+  }
+
+  #[test]
+  fn it_should_run_command_hooks_with_exported_urls() -> anyhow::Result<()> {
+    let context = command_hook_context(
+      r#"test "$LUMOS_RPC_URL" = http://127.0.0.1:8899 && test "$LUMOS_WS_URL" = ws://127.0.0.1:8900"#,
+    )?;
+    run_post_start_hooks(&context)?;
+
+    let context = command_hook_context("exit 3")?;
+    assert!(run_post_start_hooks(&context).is_err());
+    Ok(())
+  }
+}
@@ -0,0 +1,164 @@
+use anyhow::Context;
+use hashbrown::HashMap;
+use litesvm::LiteSVM;
+use solana_loader_v3_interface::get_program_data_address;
+use solana_sdk::pubkey::Pubkey;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr as _;
+use std::sync::Arc;
+
+use crate::cache_source::{
+  upgradeable_program_account,
+  upgradeable_program_data_account,
+};
+use crate::lumos_context::LumosContext;
+use crate::schema::ConfigRoot;
+use crate::traits::Pull as _;
+use crate::utils::read_account_json;
+
+/// InProcessSvm runs the cloned accounts and programs in an in-process SVM,
+/// so tests get the same state as the validator without spawning it.
+///
+/// ```no_run
+/// use lumos_svm_lib::inprocess::InProcessSvm;
+/// use lumos_svm_lib::schema::ConfigRoot;
+///
+/// let config = ConfigRoot::from_file("lumos.toml")?;
+/// let svm = InProcessSvm::load(config)?;
+/// let usdc = svm.address("usdc").expect("usdc is configured");
+/// assert!(svm.svm().get_account(&usdc).is_some());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct InProcessSvm {
+  svm: LiteSVM,
+  config: Arc<ConfigRoot>,
+}
+
+/// Implementation of InProcessSvm.
+impl InProcessSvm {
+  /// Clone the configured accounts and programs and load them into a new SVM.
+  pub fn load(config: ConfigRoot) -> anyhow::Result<Self> {
+    let config = Arc::new(config);
+    let rpc_endpoint: &str = &config.general.rpc_endpoint;
+    let cache_dir = config.general.cache_dir.clone();
+
+    let context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, false);
+    Self::from_context(&context)
+  }
+
+  /// Clone the configured accounts and programs of a context and load them
+  /// into a new SVM.
+  pub fn from_context(context: &LumosContext) -> anyhow::Result<Self> {
+    let mut svm = LiteSVM::new().with_sigverify(true);
+
+    // Pull the accounts, then load every cached account like `--account-dir` does.
+    // Accounts restored from a snapshot are loaded last, overriding cached ones.
+    for (_, account) in context.config.account.iter() {
+      account.pull(context)?;
+    }
+
+    for account_dir in [context.account_cache_dir()?, context.restored_account_dir()?] {
+      let account_dir = Path::new(&account_dir);
+      if !account_dir.exists() {
+        continue;
+      }
+
+      for entry in fs::read_dir(account_dir)? {
+        let path = entry?.path();
+        if !path.extension().is_some_and(|extension| extension == "json") {
+          continue;
+        }
+
+        let (address, account) = read_account_json(&path)?;
+        svm
+          .set_account(address, account)
+          .map_err(|err| anyhow::anyhow!("Failed to load account {address}: {err:?}"))?;
+      }
+    }
+
+    // Pull and load the programs, skipping the ones without a binary. Programs
+    // with an authority are deployed upgradeable, like `--upgradeable-program`.
+    for (_, program) in context.config.program.iter() {
+      program.pull(context)?;
+
+      let program_file = program.program_file(context)?;
+      if !program_file.exists() {
+        if program.path.is_some() {
+          anyhow::bail!("Program binary not found: {program_file:?}");
+        }
+
+        continue;
+      }
+
+      let program_id = Pubkey::from_str(&program.address)
+        .with_context(|| format!("Invalid program address: {}", program.address))?;
+      let Some(authority) = &program.authority else {
+        svm
+          .add_program_from_file(program_id, &program_file)
+          .map_err(|err| anyhow::anyhow!("Failed to load program {program_id}: {err:?}"))?;
+        continue;
+      };
+
+      let authority =
+        Pubkey::from_str(authority).with_context(|| format!("Invalid program authority: {authority}"))?;
+      let elf = fs::read(&program_file)?;
+
+      // The ProgramData account is loaded first, as the program is compiled
+      // from it when the program account is set.
+      svm
+        .set_account(
+          get_program_data_address(&program_id),
+          upgradeable_program_data_account(&elf, Some(authority))?,
+        )
+        .map_err(|err| anyhow::anyhow!("Failed to load program data of {program_id}: {err:?}"))?;
+      svm
+        .set_account(program_id, upgradeable_program_account(&program_id)?)
+        .map_err(|err| anyhow::anyhow!("Failed to load program {program_id}: {err:?}"))?;
+    }
+
+    Ok(Self {
+      svm,
+      config: context.config.clone(),
+    })
+  }
+
+  /// Get the SVM.
+  pub fn svm(&self) -> &LiteSVM {
+    &self.svm
+  }
+
+  /// Get the SVM mutably, e.g. to send transactions.
+  pub fn svm_mut(&mut self) -> &mut LiteSVM {
+    &mut self.svm
+  }
+
+  /// Enable or disable the signature verification of transactions.
+  /// This rebuilds the SVM, so set it once rather than per transaction.
+  pub fn set_sigverify(&mut self, sigverify: bool) {
+    let svm = std::mem::take(&mut self.svm);
+    self.svm = svm.with_sigverify(sigverify);
+  }
+
+  /// Enable or disable the check that transactions use the latest blockhash.
+  /// This rebuilds the SVM, so set it once rather than per transaction.
+  pub fn set_blockhash_check(&mut self, check: bool) {
+    let svm = std::mem::take(&mut self.svm);
+    self.svm = svm.with_blockhash_check(check);
+  }
+
+  /// Get the address of a configured account or program by its config name.
+  pub fn address(&self, name: &str) -> Option<Pubkey> {
+    self.addresses().remove(name)
+  }
+
+  /// Get the addresses of all configured accounts and programs by config name.
+  pub fn addresses(&self) -> HashMap<String, Pubkey> {
+    self.config.addresses()
+  }
+
+  /// Get the config the SVM was loaded from.
+  pub fn config(&self) -> &ConfigRoot {
+    &self.config
+  }
+}
@@ -0,0 +1,549 @@
+use base64::prelude::*;
+use hashbrown::HashMap;
+use litesvm::types::TransactionMetadata;
+use serde::Deserialize;
+use serde_json::{
+  Value,
+  json,
+};
+use solana_client::rpc_config::{
+  RpcAccountInfoConfig,
+  RpcSimulateTransactionAccountsConfig,
+};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{
+  TransactionError,
+  VersionedTransaction,
+};
+use std::collections::VecDeque;
+use std::str::FromStr as _;
+use std::thread;
+use std::time::Duration;
+
+use crate::inprocess::InProcessSvm;
+use crate::lumos_context::{
+  LumosContext,
+  ValidatorPorts,
+};
+use crate::rpc_server::{
+  RPC_API_VERSION,
+  RpcError,
+  RpcResult,
+  RpcServer,
+  encode_account,
+  optional_param,
+  required_param,
+  rpc_context,
+};
+use crate::signals::{
+  ShutdownSignals,
+  ValidatorExit,
+};
+use crate::utils::{
+  is_validator_port_available,
+  on_validator_ready,
+  on_validator_stopped,
+};
+
+/// The JSON-RPC error code of a failed preflight simulation.
+const TRANSACTION_SIMULATION_FAILED: i64 = -32002;
+
+/// The number of blocks a blockhash is valid for.
+const MAX_PROCESSING_AGE: u64 = 150;
+
+/// The config of the `sendTransaction` and `simulateTransaction` methods.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionConfig {
+  encoding: Option<String>,
+  #[serde(default)]
+  skip_preflight: bool,
+  #[serde(default)]
+  replace_recent_blockhash: bool,
+  #[serde(default)]
+  sig_verify: bool,
+  accounts: Option<RpcSimulateTransactionAccountsConfig>,
+}
+
+/// InProcessRpc answers the JSON-RPC methods from an in-process SVM.
+/// Every committed transaction is processed in a new slot with a new
+/// blockhash, like a validator producing blocks.
+struct InProcessRpc {
+  svm: InProcessSvm,
+  statuses: HashMap<Signature, (u64, Option<TransactionError>)>,
+  /// The blockhashes transactions may use, the latest one last.
+  blockhashes: VecDeque<Hash>,
+}
+
+/// Implementation of InProcessRpc.
+impl InProcessRpc {
+  /// Handle a method call.
+  fn handle(&mut self, method: &str, params: Value) -> RpcResult {
+    match method {
+      "getHealth" => Ok(json!("ok")),
+      "getVersion" => Ok(json!({ "solana-core": RPC_API_VERSION, "feature-set": 0 })),
+      "getSlot" => Ok(json!(self.slot())),
+      "getAccountInfo" => self.get_account_info(&params),
+      "getMultipleAccounts" => self.get_multiple_accounts(&params),
+      "getBalance" => self.get_balance(&params),
+      "getLatestBlockhash" => self.get_latest_blockhash(),
+      "sendTransaction" => self.send_transaction(&params),
+      "simulateTransaction" => self.simulate_transaction(&params),
+      "getSignatureStatuses" => self.get_signature_statuses(&params),
+      "requestAirdrop" => self.request_airdrop(&params),
+      method => Err(RpcError::method_not_found(method)),
+    }
+  }
+
+  /// Get the current slot of the SVM.
+  fn slot(&self) -> u64 {
+    self.svm.svm().get_sysvar::<Clock>().slot
+  }
+
+  /// Advance to the next slot with a new blockhash, so clients waiting for
+  /// a new blockhash make progress. The previous blockhashes stay valid for
+  /// `MAX_PROCESSING_AGE` slots.
+  fn advance_slot(&mut self) {
+    let slot = self.slot();
+    let svm = self.svm.svm_mut();
+    svm.warp_to_slot(slot + 1);
+    svm.expire_blockhash();
+
+    self.blockhashes.push_back(svm.latest_blockhash());
+    while self.blockhashes.len() as u64 > MAX_PROCESSING_AGE {
+      self.blockhashes.pop_front();
+    }
+  }
+
+  /// Check the signatures and the blockhash of a transaction, which the SVM
+  /// doesn't check for the server. Any recent blockhash is accepted, not only
+  /// the latest one, and durable nonce transactions are left to the SVM.
+  /// # Arguments
+  /// * `transaction` - The transaction to check.
+  /// * `sigverify` - Whether to verify the signatures.
+  fn check_transaction(
+    &self,
+    transaction: &VersionedTransaction,
+    sigverify: bool,
+  ) -> Result<(), TransactionError> {
+    if sigverify && transaction.verify_with_results().contains(&false) {
+      return Err(TransactionError::SignatureFailure);
+    }
+
+    let blockhash = transaction.message.recent_blockhash();
+    if !self.blockhashes.contains(blockhash) && !transaction.uses_durable_nonce() {
+      return Err(TransactionError::BlockhashNotFound);
+    }
+
+    Ok(())
+  }
+
+  /// Get an account, `getAccountInfo`.
+  fn get_account_info(&self, params: &Value) -> RpcResult {
+    let pubkey = pubkey_param(params, 0)?;
+    let config: RpcAccountInfoConfig = optional_param(params, 1)?.unwrap_or_default();
+    let value = match self.svm.svm().get_account(&pubkey) {
+      Some(account) => encode_account(&pubkey, &account, &config),
+      None => Value::Null,
+    };
+
+    Ok(json!({ "context": rpc_context(self.slot()), "value": value }))
+  }
+
+  /// Get a list of accounts, `getMultipleAccounts`.
+  fn get_multiple_accounts(&self, params: &Value) -> RpcResult {
+    let addresses: Vec<String> = required_param(params, 0)?;
+    let config: RpcAccountInfoConfig = optional_param(params, 1)?.unwrap_or_default();
+    let value = addresses
+      .iter()
+      .map(|address| {
+        let pubkey = parse_pubkey(address)?;
+        let value = match self.svm.svm().get_account(&pubkey) {
+          Some(account) => encode_account(&pubkey, &account, &config),
+          None => Value::Null,
+        };
+
+        Ok(value)
+      })
+      .collect::<Result<Vec<_>, RpcError>>()?;
+
+    Ok(json!({ "context": rpc_context(self.slot()), "value": value }))
+  }
+
+  /// Get the lamports of an account, `getBalance`.
+  fn get_balance(&self, params: &Value) -> RpcResult {
+    let pubkey = pubkey_param(params, 0)?;
+    let balance = self.svm.svm().get_balance(&pubkey).unwrap_or(0);
+    Ok(json!({ "context": rpc_context(self.slot()), "value": balance }))
+  }
+
+  /// Get the latest blockhash, `getLatestBlockhash`.
+  fn get_latest_blockhash(&self) -> RpcResult {
+    let slot = self.slot();
+    let value = json!({
+      "blockhash": self.svm.svm().latest_blockhash().to_string(),
+      "lastValidBlockHeight": slot + MAX_PROCESSING_AGE,
+    });
+
+    Ok(json!({ "context": rpc_context(slot), "value": value }))
+  }
+
+  /// Execute a transaction, `sendTransaction`.
+  /// Failed transactions are rejected like a failed preflight unless it is skipped.
+  /// A transaction that was already processed is ignored, like a validator
+  /// drops resent transactions.
+  fn send_transaction(&mut self, params: &Value) -> RpcResult {
+    let config: TransactionConfig = optional_param(params, 1)?.unwrap_or_default();
+    let transaction = transaction_param(params, &config)?;
+    let signature = transaction.signatures.first().copied().unwrap_or_default();
+    if self.statuses.contains_key(&signature) {
+      return Ok(json!(signature.to_string()));
+    }
+
+    let slot = self.slot();
+    let result = match self.check_transaction(&transaction, true) {
+      Ok(()) => self
+        .svm
+        .svm_mut()
+        .send_transaction(transaction)
+        .map_err(|failed| (failed.err, failed.meta)),
+      Err(err) => Err((err, TransactionMetadata::default())),
+    };
+
+    match result {
+      Ok(_) => {
+        self.statuses.insert(signature, (slot, None));
+        self.advance_slot();
+      },
+      Err((err, _)) if config.skip_preflight => {
+        self.statuses.insert(signature, (slot, Some(err)));
+        self.advance_slot();
+      },
+      Err((failed, meta)) => {
+        let mut err = RpcError::new(
+          TRANSACTION_SIMULATION_FAILED,
+          format!("Transaction simulation failed: {failed}"),
+        );
+        err.data = Some(json!({
+          "err": failed,
+          "logs": meta.logs,
+          "accounts": null,
+          "unitsConsumed": meta.compute_units_consumed,
+          "returnData": null,
+        }));
+        return Err(err);
+      },
+    }
+
+    Ok(json!(signature.to_string()))
+  }
+
+  /// Simulate a transaction without committing it, `simulateTransaction`.
+  fn simulate_transaction(&mut self, params: &Value) -> RpcResult {
+    let config: TransactionConfig = optional_param(params, 1)?.unwrap_or_default();
+    let mut transaction = transaction_param(params, &config)?;
+    if config.replace_recent_blockhash {
+      transaction
+        .message
+        .set_recent_blockhash(self.svm.svm().latest_blockhash());
+    }
+
+    // Signatures are only verified if asked, like `solana-test-validator`.
+    let (err, meta, post_accounts) = match self.check_transaction(&transaction, config.sig_verify) {
+      Ok(()) => match self.svm.svm_mut().simulate_transaction(transaction) {
+        Ok(info) => (None, info.meta, info.post_accounts),
+        Err(failed) => (Some(failed.err), failed.meta, vec![]),
+      },
+      Err(err) => (Some(err), TransactionMetadata::default(), vec![]),
+    };
+
+    // Failed simulations don't return the accounts.
+    let accounts = match (&err, config.accounts) {
+      (None, Some(accounts)) => {
+        let account_config = RpcAccountInfoConfig {
+          encoding: accounts.encoding,
+          ..RpcAccountInfoConfig::default()
+        };
+        let value = accounts
+          .addresses
+          .iter()
+          .map(|address| {
+            let pubkey = parse_pubkey(address)?;
+            let account = post_accounts
+              .iter()
+              .find(|(post_pubkey, _)| *post_pubkey == pubkey)
+              .map(|(_, account)| Account::from(account.clone()))
+              .or_else(|| self.svm.svm().get_account(&pubkey));
+            let value = match account {
+              Some(account) => encode_account(&pubkey, &account, &account_config),
+              None => Value::Null,
+            };
+
+            Ok(value)
+          })
+          .collect::<Result<Vec<_>, RpcError>>()?;
+
+        Value::Array(value)
+      },
+      _ => Value::Null,
+    };
+
+    let value = json!({
+      "err": err,
+      "logs": meta.logs,
+      "accounts": accounts,
+      "unitsConsumed": meta.compute_units_consumed,
+      "returnData": null,
+    });
+
+    Ok(json!({ "context": rpc_context(self.slot()), "value": value }))
+  }
+
+  /// Get the statuses of sent transactions, `getSignatureStatuses`.
+  fn get_signature_statuses(&self, params: &Value) -> RpcResult {
+    let signatures: Vec<String> = required_param(params, 0)?;
+    let value = signatures
+      .iter()
+      .map(|signature| {
+        let signature = Signature::from_str(signature)
+          .map_err(|err| RpcError::invalid_params(format!("Invalid signature {signature}: {err}")))?;
+
+        let status = self.statuses.get(&signature).map(|(slot, err)| {
+          let status = match err {
+            Some(err) => json!({ "Err": err }),
+            None => json!({ "Ok": null }),
+          };
+
+          json!({
+            "slot": slot,
+            "confirmations": null,
+            "err": err,
+            "status": status,
+            "confirmationStatus": "finalized",
+          })
+        });
+
+        Ok(status.unwrap_or(Value::Null))
+      })
+      .collect::<Result<Vec<_>, RpcError>>()?;
+
+    Ok(json!({ "context": rpc_context(self.slot()), "value": value }))
+  }
+
+  /// Airdrop lamports to an account, `requestAirdrop`.
+  fn request_airdrop(&mut self, params: &Value) -> RpcResult {
+    let pubkey = pubkey_param(params, 0)?;
+    let lamports: u64 = required_param(params, 1)?;
+    let slot = self.slot();
+
+    match self.svm.svm_mut().airdrop(&pubkey, lamports) {
+      Ok(meta) => {
+        self.statuses.insert(meta.signature, (slot, None));
+        self.advance_slot();
+        Ok(json!(meta.signature.to_string()))
+      },
+      Err(failed) => Err(RpcError::new(
+        TRANSACTION_SIMULATION_FAILED,
+        format!("Airdrop failed: {}", failed.err),
+      )),
+    }
+  }
+}
+
+/// Parse a base58 encoded public key.
+fn parse_pubkey(address: &str) -> Result<Pubkey, RpcError> {
+  Pubkey::from_str(address)
+    .map_err(|err| RpcError::invalid_params(format!("Invalid pubkey {address}: {err}")))
+}
+
+/// Parse a public key param.
+fn pubkey_param(params: &Value, index: usize) -> Result<Pubkey, RpcError> {
+  let address: String = required_param(params, index)?;
+  parse_pubkey(&address)
+}
+
+/// Decode the encoded transaction of the first param.
+fn transaction_param(params: &Value, config: &TransactionConfig) -> Result<VersionedTransaction, RpcError> {
+  let encoded: String = required_param(params, 0)?;
+  let bytes = match config.encoding.as_deref().unwrap_or("base58") {
+    "base58" => bs58::decode(&encoded)
+      .into_vec()
+      .map_err(|err| RpcError::invalid_params(format!("Invalid base58 transaction: {err}")))?,
+    "base64" => BASE64_STANDARD
+      .decode(&encoded)
+      .map_err(|err| RpcError::invalid_params(format!("Invalid base64 transaction: {err}")))?,
+    encoding => {
+      return Err(RpcError::invalid_params(format!(
+        "Unsupported encoding: {encoding}"
+      )));
+    },
+  };
+
+  bincode::deserialize(&bytes).map_err(|err| RpcError::invalid_params(format!("Invalid transaction: {err}")))
+}
+
+/// Serve a subset of the JSON-RPC API from an in-process SVM.
+/// The server is stopped when the returned handle is dropped.
+/// # Arguments
+/// * `svm` - The in-process SVM to serve.
+/// * `port` - The port to listen on, a free port is picked if 0.
+pub fn serve_inprocess(svm: InProcessSvm, port: u16) -> anyhow::Result<RpcServer> {
+  // The checks of the SVM are disabled once for the lifetime of the server,
+  // `check_transaction` does them instead.
+  let mut svm = svm;
+  svm.set_sigverify(false);
+  svm.set_blockhash_check(false);
+
+  let blockhashes = VecDeque::from([svm.svm().latest_blockhash()]);
+  let mut rpc = InProcessRpc {
+    svm,
+    statuses: HashMap::new(),
+    blockhashes,
+  };
+
+  RpcServer::start(port, move |method, params| rpc.handle(method, params))
+}
+
+/// Load the config into an in-process SVM and serve it over JSON-RPC until a
+/// shutdown signal is received, in place of `solana-test-validator`.
+/// There is no ledger, so every run starts from the cloned state.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `reset` - Whether the ledger should be reset, a kept ledger is reported
+///   as unsupported.
+pub fn run_inprocess(context: &LumosContext, reset: bool) -> anyhow::Result<ValidatorExit> {
+  if !reset {
+    let msg = "The in-process backend has no ledger to keep, starting from the cloned state";
+    log::warn!("{msg}");
+    let _ = context.pb.println(format!("Warning: {msg}."));
+  }
+
+  // Use the requested port, otherwise fall back to a different port if the default is taken.
+  let mut ports = context.ports.unwrap_or_default();
+  if context.ports.is_none() && !is_validator_port_available(ports.rpc) {
+    ports.rpc = 8900;
+  }
+
+  let svm = InProcessSvm::from_context(context)?;
+  let server = serve_inprocess(svm, ports.rpc)?;
+  context.set_local_ports(ValidatorPorts {
+    rpc: server.port(),
+    ..ports
+  })?;
+
+  let signals = ShutdownSignals::register()?;
+  on_validator_ready(context, None)?;
+
+  loop {
+    if let Some(signal) = signals.received() {
+      on_validator_stopped(context)?;
+      return Ok(ValidatorExit::Stopped {
+        signal,
+        killed: false,
+      });
+    }
+
+    thread::sleep(Duration::from_millis(100));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::schema::ConfigRoot;
+  use crate::utils::write_account_json;
+  use assert_fs::TempDir;
+  use solana_client::rpc_client::RpcClient;
+  use solana_sdk::native_token::LAMPORTS_PER_SOL;
+  use solana_sdk::signature::{
+    Keypair,
+    Signer as _,
+  };
+  use solana_sdk::system_instruction;
+  use solana_sdk::transaction::Transaction;
+
+  #[test]
+  fn it_should_serve_the_inprocess_svm() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config: ConfigRoot = toml::from_str(&format!(
+      r#"
+      [general]
+      rpc_endpoint = "http://127.0.0.1:1"
+      cache_dir = {:?}
+
+      [account]
+
+      [program]
+      "#,
+      temp_dir.path()
+    ))?;
+
+    // Cached accounts are loaded without cloning them.
+    let (address, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let cached = Account {
+      lamports: 1_000_000,
+      data: b"lumos".to_vec(),
+      owner,
+      executable: false,
+      rent_epoch: 0,
+    };
+    write_account_json(&temp_dir.path().join("accounts"), &address, &cached)?;
+
+    let server = serve_inprocess(InProcessSvm::load(config)?, 0)?;
+    let client = RpcClient::new(server.url());
+
+    let account = client.get_account(&address)?;
+    assert_eq!(
+      (account.lamports, account.data, account.owner),
+      (1_000_000, b"lumos".to_vec(), owner)
+    );
+
+    let (payer, recipient) = (Keypair::new(), Pubkey::new_unique());
+    client.request_airdrop(&payer.pubkey(), LAMPORTS_PER_SOL)?;
+    let transfer = |lamports: u64, blockhash: Hash| {
+      Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+          &payer.pubkey(),
+          &recipient,
+          lamports,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        blockhash,
+      )
+    };
+
+    // Every committed transaction advances the slot and the blockhash.
+    let (slot, blockhash) = (client.get_slot()?, client.get_latest_blockhash()?);
+    let signature = client.send_transaction(&transfer(LAMPORTS_PER_SOL / 10, blockhash))?;
+    assert_eq!(client.get_slot()?, slot + 1);
+    assert_ne!(client.get_latest_blockhash()?, blockhash);
+    assert_eq!(client.get_balance(&recipient)?, LAMPORTS_PER_SOL / 10);
+
+    let statuses = client.get_signature_statuses(&[signature, Signature::new_unique()])?;
+    assert_eq!(statuses.value[0].as_ref().map(|status| status.slot), Some(slot));
+    assert!(
+      statuses.value[0]
+        .as_ref()
+        .is_some_and(|status| status.err.is_none())
+    );
+    assert!(statuses.value[1].is_none());
+
+    // An older blockhash is still recent, and simulations aren't committed.
+    let simulated = client.simulate_transaction(&transfer(LAMPORTS_PER_SOL / 5, blockhash))?;
+    assert_eq!(simulated.value.err, None);
+    assert!(simulated.value.units_consumed.is_some_and(|units| units > 0));
+    assert_eq!(client.get_balance(&recipient)?, LAMPORTS_PER_SOL / 10);
+
+    let simulated = client.simulate_transaction(&transfer(2 * LAMPORTS_PER_SOL, blockhash))?;
+    assert!(simulated.value.err.is_some());
+
+    let simulated = client.simulate_transaction(&transfer(LAMPORTS_PER_SOL / 5, Hash::new_unique()))?;
+    assert_eq!(simulated.value.err, Some(TransactionError::BlockhashNotFound));
+    Ok(())
+  }
+}
@@ -1,12 +1,41 @@
+pub mod account_diff;
+pub mod archive;
+pub mod cache_source;
+pub mod connection;
+pub mod decoders;
+pub mod dump;
 pub mod file;
+pub mod hooks;
 pub mod idl_utils;
+
+#[cfg(feature = "inprocess")]
+pub mod inprocess;
+
+#[cfg(feature = "inprocess")]
+pub mod inprocess_rpc;
+
+pub mod log_file;
 pub mod lumos_context;
+pub mod lumos_validator;
 
 #[macro_use]
 pub mod macros;
 
+pub mod mock_rpc;
+pub mod program_errors;
+pub mod program_logs;
+pub mod replay;
+pub mod rpc_server;
 pub mod schema;
+pub mod signals;
+pub mod simulate;
+pub mod snapshot;
 pub mod solana_utils;
+
+#[cfg(test)]
+mod test_support;
+
 pub mod traits;
 pub mod utils;
 pub mod version;
+pub mod watch;
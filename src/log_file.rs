@@ -0,0 +1,137 @@
+use std::fs::{
+  self,
+  File,
+  OpenOptions,
+};
+use std::io::{
+  self,
+  Write as _,
+};
+use std::path::{
+  Path,
+  PathBuf,
+};
+
+/// The default maximum size of a log file before it is rotated, 10 MiB.
+pub const DEFAULT_LOG_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// The default number of rotated log files to keep.
+pub const DEFAULT_LOG_RETENTION: usize = 5;
+
+/// RotatingLog is a line based log file that is rotated by size.
+/// Rotated files are named `{path}.1` (newest) up to `{path}.{retention}` (oldest).
+pub struct RotatingLog {
+  path: PathBuf,
+  file: File,
+  size: u64,
+  max_size: u64,
+  retention: usize,
+}
+
+/// Implementation of RotatingLog.
+impl RotatingLog {
+  /// Open a log file for appending, creating its directory if needed.
+  /// # Arguments
+  /// * `path` - The path of the log file.
+  /// * `max_size` - The size in bytes after which the file is rotated.
+  /// * `retention` - The number of rotated files to keep.
+  pub fn open(path: &Path, max_size: u64, retention: usize) -> io::Result<Self> {
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+
+    Ok(Self {
+      path: path.to_path_buf(),
+      file,
+      size,
+      max_size,
+      retention,
+    })
+  }
+
+  /// Write a line to the log, rotating the file first if it is full.
+  pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+    if self.size > 0 && self.size + line.len() as u64 + 1 > self.max_size {
+      self.rotate()?;
+    }
+
+    writeln!(self.file, "{line}")?;
+    self.size += line.len() as u64 + 1;
+    Ok(())
+  }
+
+  /// Rotate the log files, dropping the oldest one past the retention count.
+  fn rotate(&mut self) -> io::Result<()> {
+    self.file.flush()?;
+
+    if self.retention == 0 {
+      self.file = File::create(&self.path)?;
+      self.size = 0;
+      return Ok(());
+    }
+
+    let oldest = rotated_path(&self.path, self.retention);
+    if oldest.exists() {
+      fs::remove_file(oldest)?;
+    }
+
+    for index in (1..self.retention).rev() {
+      let from = rotated_path(&self.path, index);
+      if from.exists() {
+        fs::rename(from, rotated_path(&self.path, index + 1))?;
+      }
+    }
+
+    fs::rename(&self.path, rotated_path(&self.path, 1))?;
+    self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+    self.size = 0;
+    Ok(())
+  }
+}
+
+/// Get the path of a rotated log file.
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+  let mut rotated = path.as_os_str().to_owned();
+  rotated.push(format!(".{index}"));
+  PathBuf::from(rotated)
+}
+
+/// Get the existing log files from the oldest rotated file to the current one.
+pub fn log_files(path: &Path, retention: usize) -> Vec<PathBuf> {
+  (1..=retention)
+    .rev()
+    .map(|index| rotated_path(path, index))
+    .chain(std::iter::once(path.to_path_buf()))
+    .filter(|path| path.exists())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use assert_fs::TempDir;
+
+  #[test]
+  fn it_should_rotate_log_by_size_and_keep_retention() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("validator.log");
+
+    let mut log = RotatingLog::open(&path, 10, 2)?;
+    for line in ["first", "second", "third", "fourth"] {
+      log.write_line(line)?;
+    }
+
+    assert_eq!(fs::read_to_string(&path)?, "fourth\n");
+    assert_eq!(fs::read_to_string(rotated_path(&path, 1))?, "third\n");
+    assert_eq!(fs::read_to_string(rotated_path(&path, 2))?, "second\n");
+    assert!(!rotated_path(&path, 3).exists());
+    assert_eq!(
+      log_files(&path, 2),
+      vec![rotated_path(&path, 2), rotated_path(&path, 1), path]
+    );
+    Ok(())
+  }
+}
@@ -9,16 +9,52 @@ use std::sync::{
 };
 
 use crate::file::ToUtf8 as _;
-use crate::schema::ConfigRoot;
+use crate::log_file::{
+  DEFAULT_LOG_MAX_SIZE,
+  DEFAULT_LOG_RETENTION,
+};
+use crate::schema::{
+  Backend,
+  ConfigRoot,
+};
+
+/// The ports used by the local validator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidatorPorts {
+  /// The JSON RPC port, the websocket port is the next one.
+  pub rpc: u16,
+  /// The faucet port.
+  pub faucet: u16,
+  /// The gossip port, the validator default if `None`.
+  pub gossip: Option<u16>,
+  /// The first and last port of the range the validator binds its other
+  /// services to, the validator default if `None`.
+  pub dynamic_port_range: Option<(u16, u16)>,
+}
+
+/// Default implementation of ValidatorPorts.
+impl Default for ValidatorPorts {
+  fn default() -> Self {
+    Self {
+      rpc: 8899,
+      faucet: 9900,
+      gossip: None,
+      dynamic_port_range: None,
+    }
+  }
+}
 
 /// LumosContext is a struct that holds the configuration of the lumos-svm program.
 pub struct LumosContext {
   rpc_endpoint: Arc<RwLock<String>>,
+  local_ports: Arc<RwLock<ValidatorPorts>>,
   cache_dir: Option<String>,
   pub config: Arc<ConfigRoot>,
   pub pb: Arc<MultiProgress>,
   pub pb_style: ProgressStyle,
   pub verbose: bool,
+  /// Ports to run the local validator on, picked automatically if `None`.
+  pub ports: Option<ValidatorPorts>,
 }
 
 /// Implementation of LumosContext.
@@ -28,12 +64,14 @@ impl LumosContext {
     Self {
       config: config.clone(),
       rpc_endpoint: Arc::new(RwLock::new(rpc_endpoint.into())),
+      local_ports: Arc::new(RwLock::new(ValidatorPorts::default())),
       cache_dir,
       pb: Arc::new(MultiProgress::new()),
       pb_style: ProgressStyle::with_template("{spinner:.green} [{prefix:.bold.dim}] {wide_msg:.cyan/blue} ")
         .expect("Failed to create progress style")
         .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏⦿"),
       verbose,
+      ports: None,
     }
   }
 
@@ -55,6 +93,30 @@ impl LumosContext {
       .unwrap_or_else(|_| "http://localhost:8899".into())
   }
 
+  /// Set the ports used by the local validator.
+  pub fn set_local_ports(&self, ports: ValidatorPorts) -> anyhow::Result<()> {
+    self
+      .local_ports
+      .write()
+      .map(|mut local_ports| *local_ports = ports)
+      .map_err(|_| anyhow::anyhow!("Failed to set local ports"))
+  }
+
+  /// Get the ports used by the local validator.
+  pub fn local_ports(&self) -> ValidatorPorts {
+    self.local_ports.read().map(|ports| *ports).unwrap_or_default()
+  }
+
+  /// Get the RPC URL of the local validator.
+  pub fn local_rpc_url(&self) -> String {
+    format!("http://127.0.0.1:{}", self.local_ports().rpc)
+  }
+
+  /// Get the websocket URL of the local validator.
+  pub fn local_ws_url(&self) -> String {
+    format!("ws://127.0.0.1:{}", self.local_ports().rpc + 1)
+  }
+
   /// Get the mint authority.
   pub fn mint_authority(&self) -> Option<String> {
     self.config.general.mint_authority.clone()
@@ -65,6 +127,49 @@ impl LumosContext {
     self.cache_dir.clone().unwrap_or(".lumos-cache".into())
   }
 
+  /// Get the validator ledger directory.
+  pub fn ledger_dir(&self) -> String {
+    self
+      .config
+      .general
+      .ledger_dir
+      .clone()
+      .unwrap_or(".lumos-ledger".into())
+  }
+
+  /// Get the validator log file, kept inside the ledger directory.
+  pub fn validator_log_file(&self) -> anyhow::Result<String> {
+    let ledger_dir = self.ledger_dir();
+    let path = Path::new(&ledger_dir).join("lumos-validator.log");
+    let path = path.to_utf8()?;
+    Ok(path.to_string())
+  }
+
+  /// Get the maximum size of the validator log file before it is rotated.
+  pub fn log_max_size(&self) -> u64 {
+    self.config.general.log_max_size.unwrap_or(DEFAULT_LOG_MAX_SIZE)
+  }
+
+  /// Get the number of rotated validator log files to keep.
+  pub fn log_retention(&self) -> usize {
+    self.config.general.log_retention.unwrap_or(DEFAULT_LOG_RETENTION)
+  }
+
+  /// Get the backend running the cloned state.
+  pub fn backend(&self) -> Backend {
+    self.config.general.backend.unwrap_or_default()
+  }
+
+  /// Get the snapshot directory.
+  pub fn snapshot_dir(&self) -> String {
+    self
+      .config
+      .general
+      .snapshot_dir
+      .clone()
+      .unwrap_or(".lumos-snapshots".into())
+  }
+
   /// Get the program cache directory.
   pub fn program_cache_dir(&self) -> anyhow::Result<String> {
     self.cache_dir_join("programs")
@@ -75,6 +180,12 @@ impl LumosContext {
     self.cache_dir_join("accounts")
   }
 
+  /// Get the directory of the accounts restored from a snapshot, loaded on top
+  /// of the account cache.
+  pub fn restored_account_dir(&self) -> anyhow::Result<String> {
+    self.cache_dir_join("restored")
+  }
+
   /// Join the cache directory with a path.
   fn cache_dir_join(&self, p: &str) -> anyhow::Result<String> {
     let cache_dir = self.cache_dir();
@@ -0,0 +1,308 @@
+use anyhow::Context;
+use hashbrown::HashMap;
+use signal_hook::consts::SIGTERM;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::path::{
+  Path,
+  PathBuf,
+};
+use std::process::Child;
+use std::sync::Arc;
+use std::time::{
+  Duration,
+  Instant,
+};
+use std::{
+  fs,
+  thread,
+};
+
+use crate::lumos_context::{
+  LumosContext,
+  ValidatorPorts,
+};
+use crate::schema::ConfigRoot;
+use crate::signals::stop_child;
+use crate::utils::{
+  is_validator_port_available,
+  is_validator_running,
+  run_ledger_hooks,
+  spawn_validator,
+};
+
+/// The default time to wait for the validator to become ready.
+const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The number of random ports to try before giving up.
+const PORT_ATTEMPTS: usize = 50;
+
+/// The number of ports in the dynamic port range of a validator, above the
+/// minimum `solana-test-validator` accepts.
+const DYNAMIC_PORT_RANGE_WIDTH: u16 = 50;
+
+/// LumosValidator is a handle to a local validator spawned from Rust code,
+/// typically from a `#[test]`. The validator is stopped and its temporary
+/// ledger removed when the handle is dropped.
+///
+/// ```no_run
+/// use lumos_svm_lib::lumos_validator::LumosValidator;
+/// use lumos_svm_lib::schema::ConfigRoot;
+///
+/// let config = ConfigRoot::from_file("lumos.toml")?;
+/// let validator = LumosValidator::builder(config).spawn()?;
+/// let client = validator.rpc_client();
+/// let usdc = validator.address("usdc").expect("usdc is configured");
+/// assert!(client.get_account(&usdc).is_ok());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct LumosValidator {
+  child: Child,
+  context: LumosContext,
+  temp_ledger: Option<PathBuf>,
+}
+
+/// LumosValidatorBuilder configures a LumosValidator before it is spawned.
+pub struct LumosValidatorBuilder {
+  config: ConfigRoot,
+  ledger_dir: Option<String>,
+  ports: Option<ValidatorPorts>,
+  reset: bool,
+  verbose: bool,
+  startup_timeout: Duration,
+}
+
+/// Implementation of LumosValidatorBuilder.
+impl LumosValidatorBuilder {
+  /// Use this ledger directory instead of an isolated temporary one.
+  /// The directory is kept when the validator is dropped.
+  pub fn ledger_dir(mut self, ledger_dir: &str) -> Self {
+    self.ledger_dir = Some(ledger_dir.into());
+    self
+  }
+
+  /// Use these ports instead of picking free ones.
+  pub fn ports(mut self, ports: ValidatorPorts) -> Self {
+    self.ports = Some(ports);
+    self
+  }
+
+  /// Whether to reset a ledger directory set with `ledger_dir`, defaults to true.
+  pub fn reset(mut self, reset: bool) -> Self {
+    self.reset = reset;
+    self
+  }
+
+  /// Mirror the validator output to the terminal.
+  pub fn verbose(mut self, verbose: bool) -> Self {
+    self.verbose = verbose;
+    self
+  }
+
+  /// The time to wait for the validator to become ready, defaults to 60 seconds.
+  pub fn startup_timeout(mut self, timeout: Duration) -> Self {
+    self.startup_timeout = timeout;
+    self
+  }
+
+  /// Clone the configured accounts and programs, spawn the validator and wait
+  /// until it is ready and the post start hooks have run.
+  pub fn spawn(self) -> anyhow::Result<LumosValidator> {
+    let mut config = self.config;
+    let temp_ledger = match &self.ledger_dir {
+      Some(_) => None,
+      None => Some(temp_ledger_dir()),
+    };
+
+    config.general.ledger_dir = match (&self.ledger_dir, &temp_ledger) {
+      (Some(ledger_dir), _) => Some(ledger_dir.clone()),
+      (None, Some(temp_ledger)) => Some(temp_ledger.to_string_lossy().into_owned()),
+      (None, None) => None,
+    };
+
+    let config = Arc::new(config);
+    let rpc_endpoint: &str = &config.general.rpc_endpoint;
+    let cache_dir = config.general.cache_dir.clone();
+
+    let mut context = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, self.verbose);
+    context.ports = match self.ports {
+      Some(ports) => Some(ports),
+      None => Some(free_ports()?),
+    };
+
+    let child = match spawn_validator(&context, self.reset) {
+      Ok(child) => child,
+      Err(err) => {
+        if let Some(temp_ledger) = &temp_ledger {
+          let _ = remove_ledger(temp_ledger);
+        }
+
+        return Err(err);
+      },
+    };
+
+    let mut validator = LumosValidator {
+      child,
+      context,
+      temp_ledger,
+    };
+
+    // The handle is dropped on error, stopping the validator again.
+    validator.wait_until_ready(self.startup_timeout)?;
+    run_ledger_hooks(&validator.context)?;
+
+    Ok(validator)
+  }
+}
+
+/// Implementation of LumosValidator.
+impl LumosValidator {
+  /// Create a builder for a validator running the given config.
+  pub fn builder(config: ConfigRoot) -> LumosValidatorBuilder {
+    LumosValidatorBuilder {
+      config,
+      ledger_dir: None,
+      ports: None,
+      reset: true,
+      verbose: false,
+      startup_timeout: DEFAULT_STARTUP_TIMEOUT,
+    }
+  }
+
+  /// Get the JSON RPC URL of the validator.
+  pub fn rpc_url(&self) -> String {
+    self.context.local_rpc_url()
+  }
+
+  /// Get the websocket URL of the validator.
+  pub fn ws_url(&self) -> String {
+    self.context.local_ws_url()
+  }
+
+  /// Get an RPC client connected to the validator with confirmed commitment.
+  pub fn rpc_client(&self) -> RpcClient {
+    RpcClient::new_with_commitment(self.rpc_url(), CommitmentConfig::confirmed())
+  }
+
+  /// Get the address of a configured account or program by its config name.
+  pub fn address(&self, name: &str) -> Option<Pubkey> {
+    self.addresses().remove(name)
+  }
+
+  /// Get the addresses of all configured accounts and programs by config name.
+  pub fn addresses(&self) -> HashMap<String, Pubkey> {
+    self.context.config.addresses()
+  }
+
+  /// Get the ledger directory of the validator.
+  pub fn ledger_dir(&self) -> PathBuf {
+    PathBuf::from(self.context.ledger_dir())
+  }
+
+  /// Get the lumos context the validator was spawned with.
+  pub fn context(&self) -> &LumosContext {
+    &self.context
+  }
+
+  /// Wait until the validator answers health checks.
+  fn wait_until_ready(&mut self, timeout: Duration) -> anyhow::Result<()> {
+    let rpc_url: &str = &self.rpc_url();
+    let started = Instant::now();
+    loop {
+      if let Some(status) = self.child.try_wait()? {
+        anyhow::bail!(
+          "Validator exited with {status} before it was ready, see {:?}",
+          self.context.validator_log_file()
+        );
+      }
+
+      if is_validator_running(rpc_url) {
+        return Ok(());
+      }
+
+      if started.elapsed() > timeout {
+        anyhow::bail!("Validator was not ready after {timeout:?}: {rpc_url}");
+      }
+
+      thread::sleep(Duration::from_millis(500));
+    }
+  }
+}
+
+/// Drop implementation of LumosValidator.
+impl Drop for LumosValidator {
+  fn drop(&mut self) {
+    match &self.temp_ledger {
+      // The temporary ledger is thrown away, so there is no need to wait for
+      // a graceful shutdown.
+      Some(temp_ledger) => {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = remove_ledger(temp_ledger);
+      },
+      None => {
+        if let Err(err) = stop_child(&mut self.child, SIGTERM) {
+          log::warn!("Failed to stop validator: {err}");
+        }
+      },
+    }
+  }
+}
+
+/// Get a unique temporary ledger directory.
+fn temp_ledger_dir() -> PathBuf {
+  let suffix: u64 = rand::random();
+  std::env::temp_dir().join(format!("lumos-ledger-{}-{suffix:x}", std::process::id()))
+}
+
+/// Remove a ledger directory if it exists.
+fn remove_ledger(ledger_dir: &Path) -> anyhow::Result<()> {
+  if ledger_dir.exists() {
+    fs::remove_dir_all(ledger_dir).with_context(|| format!("Failed to remove ledger: {ledger_dir:?}"))?;
+  }
+
+  Ok(())
+}
+
+/// Pick random free ports, so validators of parallel tests don't collide.
+/// The websocket port next to the rpc port has to be free too, and so does
+/// the whole dynamic port range the validator binds its other services to.
+fn free_ports() -> anyhow::Result<ValidatorPorts> {
+  for _ in 0..PORT_ATTEMPTS {
+    let rpc = rand::random_range(20000..30000);
+    let faucet = rand::random_range(30000..35000);
+    let gossip = rand::random_range(35000..40000);
+    let start = rand::random_range(40000..60000);
+    let end = start + DYNAMIC_PORT_RANGE_WIDTH;
+    let ports = [rpc, rpc + 1, faucet, gossip].into_iter().chain(start..end);
+    if ports.into_iter().all(is_validator_port_available) {
+      return Ok(ValidatorPorts {
+        rpc,
+        faucet,
+        gossip: Some(gossip),
+        dynamic_port_range: Some((start, end)),
+      });
+    }
+  }
+
+  anyhow::bail!("Failed to find free ports for the validator")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_should_pick_free_validator_ports() -> anyhow::Result<()> {
+    let ports = free_ports()?;
+    assert!(is_validator_port_available(ports.rpc));
+    assert!(is_validator_port_available(ports.rpc + 1));
+    assert!(is_validator_port_available(ports.faucet));
+    let (start, end) = ports.dynamic_port_range.unwrap();
+    assert_eq!(end - start, DYNAMIC_PORT_RANGE_WIDTH);
+    assert!(ports.gossip.is_some_and(|gossip| gossip < start));
+    assert_ne!(temp_ledger_dir(), temp_ledger_dir());
+    Ok(())
+  }
+}
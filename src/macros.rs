@@ -12,6 +12,26 @@ macro_rules! handle_tty_output {
       }
     });
   };
+  ($output:expr, $context:expr, $log:expr, $mirror:expr) => {
+    let output = $output
+      .take()
+      .with_context(|| format!("Failed to open {}", stringify!($output)))?;
+    let pb = $context.pb.clone();
+    let log = $log.clone();
+    let mirror = $mirror;
+    std::thread::spawn(move || {
+      let reader = BufReader::new(output);
+      for line in reader.lines().map_while(Result::ok) {
+        if let Ok(mut log) = log.lock() {
+          let _ = log.write_line(&line);
+        }
+
+        if mirror {
+          let _ = pb.println(line);
+        }
+      }
+    });
+  };
 }
 
 #[macro_export]
@@ -0,0 +1,354 @@
+use anyhow::Context;
+use hashbrown::HashMap;
+use serde_json::{
+  Value,
+  json,
+};
+use solana_account_decoder::parse_account_data::SplTokenAdditionalDataV2;
+use solana_account_decoder::parse_token::{
+  is_known_spl_token_id,
+  token_amount_to_ui_amount_v3,
+};
+use solana_client::rpc_config::{
+  RpcAccountInfoConfig,
+  RpcProgramAccountsConfig,
+};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_client::rpc_response::RpcTokenAccountBalance;
+use solana_loader_v3_interface::get_program_data_address;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::StateWithExtensions;
+use spl_token_2022::generic_token_account::GenericTokenAccount as _;
+use std::fs;
+use std::path::{
+  Path,
+  PathBuf,
+};
+use std::str::FromStr as _;
+
+use crate::cache_source::{
+  upgradeable_program_account,
+  upgradeable_program_data_account,
+};
+use crate::rpc_server::{
+  RPC_API_VERSION,
+  RpcError,
+  RpcResult,
+  RpcServer,
+  encode_account,
+  optional_param,
+  required_param,
+  rpc_context,
+};
+use crate::utils::read_account_json;
+
+/// The directory inside the fixtures directory holding the accounts.
+const ACCOUNTS_DIR: &str = "accounts";
+
+/// The directory inside the fixtures directory holding the transactions.
+const TRANSACTIONS_DIR: &str = "transactions";
+
+/// The slot reported by the mock server.
+const MOCK_SLOT: u64 = 1;
+
+/// The number of token accounts returned by `getTokenLargestAccounts`.
+const MAX_LARGEST_ACCOUNTS: usize = 20;
+
+/// MockRpcFixtures holds the accounts and transactions a mock RPC server
+/// answers with.
+///
+/// A fixtures directory holds `accounts/{address}.json` files in the format of
+/// `solana account --output json` (like the account cache or `lumos-svm dump`),
+/// and `transactions/{signature}.json` files with the `getTransaction` result.
+#[derive(Debug, Default, Clone)]
+pub struct MockRpcFixtures {
+  accounts: HashMap<Pubkey, Account>,
+  transactions: HashMap<String, Value>,
+}
+
+/// Implementation of MockRpcFixtures.
+impl MockRpcFixtures {
+  /// Create empty fixtures.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Load the fixtures from a directory.
+  pub fn load(dir: &Path) -> anyhow::Result<Self> {
+    if !dir.exists() {
+      anyhow::bail!("Fixtures directory not found: {dir:?}");
+    }
+
+    let mut fixtures = Self::new();
+
+    for path in json_files(&dir.join(ACCOUNTS_DIR))? {
+      let (address, account) = read_account_json(&path)?;
+      fixtures.add_account(address, account);
+    }
+
+    for path in json_files(&dir.join(TRANSACTIONS_DIR))? {
+      let signature = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .with_context(|| format!("Invalid transaction file name: {path:?}"))?
+        .to_string();
+      let contents = fs::read_to_string(&path)?;
+      let transaction = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse transaction file: {path:?}"))?;
+      fixtures.add_transaction(&signature, transaction);
+    }
+
+    Ok(fixtures)
+  }
+
+  /// Add an account.
+  pub fn add_account(&mut self, address: Pubkey, account: Account) -> &mut Self {
+    self.accounts.insert(address, account);
+    self
+  }
+
+  /// Add an upgradeable program with its ProgramData account, so it can be
+  /// dumped with `solana program dump`.
+  /// # Arguments
+  /// * `program_id` - The address of the program.
+  /// * `elf` - The program binary.
+  pub fn add_program(&mut self, program_id: Pubkey, elf: &[u8]) -> anyhow::Result<&mut Self> {
+    self.accounts.insert(
+      get_program_data_address(&program_id),
+      upgradeable_program_data_account(elf, None)?,
+    );
+    self
+      .accounts
+      .insert(program_id, upgradeable_program_account(&program_id)?);
+    Ok(self)
+  }
+
+  /// Add a transaction with its `getTransaction` result.
+  pub fn add_transaction(&mut self, signature: &str, transaction: Value) -> &mut Self {
+    self.transactions.insert(signature.to_string(), transaction);
+    self
+  }
+
+  /// Get the number of accounts and transactions.
+  pub fn counts(&self) -> (usize, usize) {
+    (self.accounts.len(), self.transactions.len())
+  }
+
+  /// Handle a method call.
+  fn handle(&self, method: &str, params: Value) -> RpcResult {
+    match method {
+      "getHealth" => Ok(json!("ok")),
+      "getVersion" => Ok(json!({ "solana-core": RPC_API_VERSION, "feature-set": 0 })),
+      "getSlot" => Ok(json!(MOCK_SLOT)),
+      "getAccountInfo" => self.get_account_info(&params),
+      "getMultipleAccounts" => self.get_multiple_accounts(&params),
+      "getProgramAccounts" => self.get_program_accounts(&params),
+      "getTokenLargestAccounts" => self.get_token_largest_accounts(&params),
+      "getTransaction" => self.get_transaction(&params),
+      method => Err(RpcError::method_not_found(method)),
+    }
+  }
+
+  /// Get an account, `getAccountInfo`.
+  fn get_account_info(&self, params: &Value) -> RpcResult {
+    let pubkey = pubkey_param(params, 0)?;
+    let config: RpcAccountInfoConfig = optional_param(params, 1)?.unwrap_or_default();
+    let value = match self.accounts.get(&pubkey) {
+      Some(account) => encode_account(&pubkey, account, &config),
+      None => Value::Null,
+    };
+
+    Ok(json!({ "context": rpc_context(MOCK_SLOT), "value": value }))
+  }
+
+  /// Get a list of accounts, `getMultipleAccounts`.
+  fn get_multiple_accounts(&self, params: &Value) -> RpcResult {
+    let addresses: Vec<String> = required_param(params, 0)?;
+    let config: RpcAccountInfoConfig = optional_param(params, 1)?.unwrap_or_default();
+    let value = addresses
+      .iter()
+      .map(|address| {
+        let pubkey = parse_pubkey(address)?;
+        let value = match self.accounts.get(&pubkey) {
+          Some(account) => encode_account(&pubkey, account, &config),
+          None => Value::Null,
+        };
+
+        Ok(value)
+      })
+      .collect::<Result<Vec<_>, RpcError>>()?;
+
+    Ok(json!({ "context": rpc_context(MOCK_SLOT), "value": value }))
+  }
+
+  /// Get the accounts owned by a program, `getProgramAccounts`.
+  fn get_program_accounts(&self, params: &Value) -> RpcResult {
+    let program_id = pubkey_param(params, 0)?;
+    let config: RpcProgramAccountsConfig = optional_param(params, 1)?.unwrap_or_default();
+    let filters = config.filters.unwrap_or_default();
+
+    let mut accounts: Vec<_> = self
+      .accounts
+      .iter()
+      .filter(|(_, account)| account.owner == program_id)
+      .filter(|(_, account)| filters.iter().all(|filter| filter_allows(filter, &account.data)))
+      .collect();
+    accounts.sort_by_key(|(pubkey, _)| **pubkey);
+
+    let value: Vec<Value> = accounts
+      .into_iter()
+      .map(|(pubkey, account)| {
+        json!({
+          "pubkey": pubkey.to_string(),
+          "account": encode_account(pubkey, account, &config.account_config),
+        })
+      })
+      .collect();
+
+    match config.with_context.unwrap_or(false) {
+      true => Ok(json!({ "context": rpc_context(MOCK_SLOT), "value": value })),
+      false => Ok(json!(value)),
+    }
+  }
+
+  /// Get the largest token accounts of a mint, `getTokenLargestAccounts`.
+  fn get_token_largest_accounts(&self, params: &Value) -> RpcResult {
+    let mint = pubkey_param(params, 0)?;
+    let decimals = self
+      .accounts
+      .get(&mint)
+      .and_then(|account| StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data).ok())
+      .map(|state| state.base.decimals)
+      .ok_or_else(|| RpcError::invalid_params(format!("Invalid param: not a Token mint {mint}")))?;
+
+    let mut balances: Vec<(Pubkey, u64)> = self
+      .accounts
+      .iter()
+      .filter(|(_, account)| is_known_spl_token_id(&account.owner))
+      .filter_map(|(pubkey, account)| {
+        let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).ok()?;
+        (state.base.mint == mint).then_some((*pubkey, state.base.amount))
+      })
+      .collect();
+    balances.sort_by(|(a, a_amount), (b, b_amount)| b_amount.cmp(a_amount).then(a.cmp(b)));
+    balances.truncate(MAX_LARGEST_ACCOUNTS);
+
+    let additional_data = SplTokenAdditionalDataV2::with_decimals(decimals);
+    let value: Vec<RpcTokenAccountBalance> = balances
+      .into_iter()
+      .map(|(address, amount)| RpcTokenAccountBalance {
+        address: address.to_string(),
+        amount: token_amount_to_ui_amount_v3(amount, &additional_data),
+      })
+      .collect();
+
+    Ok(json!({ "context": rpc_context(MOCK_SLOT), "value": value }))
+  }
+
+  /// Get a transaction, `getTransaction`.
+  fn get_transaction(&self, params: &Value) -> RpcResult {
+    let signature: String = required_param(params, 0)?;
+    Ok(self.transactions.get(&signature).cloned().unwrap_or(Value::Null))
+  }
+}
+
+/// Check if an account data matches a `getProgramAccounts` filter.
+fn filter_allows(filter: &RpcFilterType, data: &[u8]) -> bool {
+  match filter {
+    RpcFilterType::DataSize(size) => data.len() as u64 == *size,
+    RpcFilterType::Memcmp(compare) => compare.bytes_match(data),
+    RpcFilterType::TokenAccountState => spl_token_2022::state::Account::valid_account_data(data),
+  }
+}
+
+/// Parse a base58 encoded public key.
+fn parse_pubkey(address: &str) -> Result<Pubkey, RpcError> {
+  Pubkey::from_str(address)
+    .map_err(|err| RpcError::invalid_params(format!("Invalid pubkey {address}: {err}")))
+}
+
+/// Parse a public key param.
+fn pubkey_param(params: &Value, index: usize) -> Result<Pubkey, RpcError> {
+  let address: String = required_param(params, index)?;
+  parse_pubkey(&address)
+}
+
+/// Get the JSON files of a directory, an empty list if it doesn't exist.
+fn json_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+  if !dir.exists() {
+    return Ok(vec![]);
+  }
+
+  let mut files = vec![];
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.extension().is_some_and(|extension| extension == "json") {
+      files.push(path);
+    }
+  }
+
+  files.sort();
+  Ok(files)
+}
+
+/// Serve the fixtures as a mock upstream RPC server.
+/// The server is stopped when the returned handle is dropped.
+/// # Arguments
+/// * `fixtures` - The accounts and transactions to answer with.
+/// * `port` - The port to listen on, a free port is picked if 0.
+pub fn serve_mock_rpc(fixtures: MockRpcFixtures, port: u16) -> anyhow::Result<RpcServer> {
+  RpcServer::start(port, move |method, params| fixtures.handle(method, params))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::traits::AccountSource;
+  use crate::utils::write_account_json;
+  use assert_fs::TempDir;
+  use solana_client::rpc_client::RpcClient;
+
+  #[test]
+  fn it_should_serve_accounts_and_transactions_from_fixtures() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mint = Pubkey::new_unique();
+    let account = Account {
+      lamports: 1_461_600,
+      data: vec![7; 82],
+      owner: spl_token::ID,
+      executable: false,
+      rent_epoch: 0,
+    };
+
+    write_account_json(&temp_dir.path().join(ACCOUNTS_DIR), &mint, &account)?;
+    fs::create_dir_all(temp_dir.path().join(TRANSACTIONS_DIR))?;
+    fs::write(
+      temp_dir.path().join(TRANSACTIONS_DIR).join("sig.json"),
+      r#"{"slot": 42}"#,
+    )?;
+
+    let fixtures = MockRpcFixtures::load(temp_dir.path())?;
+    assert_eq!(fixtures.counts(), (1, 1));
+    assert_eq!(
+      fixtures.handle("getTransaction", json!(["sig"]))?,
+      json!({ "slot": 42 })
+    );
+
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let client = RpcClient::new(server.url());
+    assert_eq!(client.get_account(&mint)?, account);
+    assert!(client.get_account(&Pubkey::new_unique()).is_err());
+    assert_eq!(
+      client.get_multiple_accounts(&[mint, Pubkey::new_unique()])?.len(),
+      2
+    );
+    assert_eq!(
+      AccountSource::get_multiple_accounts(&client, &[mint, mint])?,
+      vec![Some(account.clone()), Some(account.clone())]
+    );
+    assert_eq!(client.get_program_accounts(&spl_token::ID)?.len(), 1);
+    assert!(client.get_program_accounts(&spl_token_2022::ID)?.is_empty());
+    Ok(())
+  }
+}
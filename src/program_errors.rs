@@ -0,0 +1,404 @@
+use num_traits::FromPrimitive as _;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemError;
+use solana_sdk::system_program;
+use solana_sdk::transaction::{
+  TransactionError,
+  VersionedTransaction,
+};
+
+use crate::decoders::AnchorIdl;
+use crate::solana_utils::anchor_idl_address;
+use crate::traits::AccountSource;
+
+/// The errors of the Anchor framework, shared by all Anchor programs,
+/// from the `ErrorCode` of anchor-lang 0.30.
+const ANCHOR_ERRORS: &[(u32, &str)] = &[
+  (
+    100,
+    "InstructionMissing: 8 byte instruction identifier not provided",
+  ),
+  (
+    101,
+    "InstructionFallbackNotFound: fallback functions are not supported",
+  ),
+  (
+    102,
+    "InstructionDidNotDeserialize: the program could not deserialize the given instruction",
+  ),
+  (
+    103,
+    "InstructionDidNotSerialize: the program could not serialize the given instruction",
+  ),
+  (
+    1000,
+    "IdlInstructionStub: the program was compiled without idl instructions",
+  ),
+  (
+    1001,
+    "IdlInstructionInvalidProgram: invalid program given to the IDL instruction",
+  ),
+  (
+    1002,
+    "IdlAccountNotEmpty: IDL account must be empty in order to resize, try closing first",
+  ),
+  (
+    1500,
+    "EventInstructionStub: the program was compiled without `event-cpi` feature",
+  ),
+  (2000, "ConstraintMut: a mut constraint was violated"),
+  (2001, "ConstraintHasOne: a has one constraint was violated"),
+  (2002, "ConstraintSigner: a signer constraint was violated"),
+  (2003, "ConstraintRaw: a raw constraint was violated"),
+  (2004, "ConstraintOwner: an owner constraint was violated"),
+  (
+    2005,
+    "ConstraintRentExempt: a rent exemption constraint was violated",
+  ),
+  (2006, "ConstraintSeeds: a seeds constraint was violated"),
+  (
+    2007,
+    "ConstraintExecutable: an executable constraint was violated",
+  ),
+  (
+    2008,
+    "ConstraintState: deprecated error, feel free to replace with something else",
+  ),
+  (
+    2009,
+    "ConstraintAssociated: an associated constraint was violated",
+  ),
+  (
+    2010,
+    "ConstraintAssociatedInit: an associated init constraint was violated",
+  ),
+  (2011, "ConstraintClose: a close constraint was violated"),
+  (2012, "ConstraintAddress: an address constraint was violated"),
+  (2013, "ConstraintZero: expected zero account discriminant"),
+  (2014, "ConstraintTokenMint: a token mint constraint was violated"),
+  (
+    2015,
+    "ConstraintTokenOwner: a token owner constraint was violated",
+  ),
+  (
+    2016,
+    "ConstraintMintMintAuthority: a mint mint authority constraint was violated",
+  ),
+  (
+    2017,
+    "ConstraintMintFreezeAuthority: a mint freeze authority constraint was violated",
+  ),
+  (
+    2018,
+    "ConstraintMintDecimals: a mint decimals constraint was violated",
+  ),
+  (2019, "ConstraintSpace: a space constraint was violated"),
+  (
+    2020,
+    "ConstraintAccountIsNone: a required account for the constraint is None",
+  ),
+  (
+    2021,
+    "ConstraintTokenTokenProgram: a token account token program constraint was violated",
+  ),
+  (
+    2022,
+    "ConstraintMintTokenProgram: a mint token program constraint was violated",
+  ),
+  (
+    2023,
+    "ConstraintAssociatedTokenTokenProgram: an associated token account token program constraint was violated",
+  ),
+  (
+    2024,
+    "ConstraintMintGroupPointerExtension: a group pointer extension constraint was violated",
+  ),
+  (
+    2025,
+    "ConstraintMintGroupPointerExtensionAuthority: a group pointer extension authority constraint was violated",
+  ),
+  (
+    2026,
+    "ConstraintMintGroupPointerExtensionGroupAddress: a group pointer extension group address constraint was violated",
+  ),
+  (
+    2027,
+    "ConstraintMintGroupMemberPointerExtension: a group member pointer extension constraint was violated",
+  ),
+  (
+    2028,
+    "ConstraintMintGroupMemberPointerExtensionAuthority: a group member pointer extension authority constraint was violated",
+  ),
+  (
+    2029,
+    "ConstraintMintGroupMemberPointerExtensionMemberAddress: a group member pointer extension member address constraint was violated",
+  ),
+  (
+    2030,
+    "ConstraintMintMetadataPointerExtension: a metadata pointer extension constraint was violated",
+  ),
+  (
+    2031,
+    "ConstraintMintMetadataPointerExtensionAuthority: a metadata pointer extension authority constraint was violated",
+  ),
+  (
+    2032,
+    "ConstraintMintMetadataPointerExtensionMetadataAddress: a metadata pointer extension metadata address constraint was violated",
+  ),
+  (
+    2033,
+    "ConstraintMintCloseAuthorityExtension: a close authority extension constraint was violated",
+  ),
+  (
+    2034,
+    "ConstraintMintCloseAuthorityExtensionAuthority: a close authority extension authority constraint was violated",
+  ),
+  (
+    2035,
+    "ConstraintMintPermanentDelegateExtension: a permanent delegate extension constraint was violated",
+  ),
+  (
+    2036,
+    "ConstraintMintPermanentDelegateExtensionDelegate: a permanent delegate extension delegate constraint was violated",
+  ),
+  (
+    2037,
+    "ConstraintMintTransferHookExtension: a transfer hook extension constraint was violated",
+  ),
+  (
+    2038,
+    "ConstraintMintTransferHookExtensionAuthority: a transfer hook extension authority constraint was violated",
+  ),
+  (
+    2039,
+    "ConstraintMintTransferHookExtensionProgramId: a transfer hook extension transfer hook program id constraint was violated",
+  ),
+  (2500, "RequireViolated: a require expression was violated"),
+  (2501, "RequireEqViolated: a require_eq expression was violated"),
+  (
+    2502,
+    "RequireKeysEqViolated: a require_keys_eq expression was violated",
+  ),
+  (2503, "RequireNeqViolated: a require_neq expression was violated"),
+  (
+    2504,
+    "RequireKeysNeqViolated: a require_keys_neq expression was violated",
+  ),
+  (2505, "RequireGtViolated: a require_gt expression was violated"),
+  (2506, "RequireGteViolated: a require_gte expression was violated"),
+  (
+    3000,
+    "AccountDiscriminatorAlreadySet: the account discriminator was already set on this account",
+  ),
+  (
+    3001,
+    "AccountDiscriminatorNotFound: no 8 byte discriminator was found on the account",
+  ),
+  (
+    3002,
+    "AccountDiscriminatorMismatch: 8 byte discriminator did not match what was expected",
+  ),
+  (
+    3003,
+    "AccountDidNotDeserialize: failed to deserialize the account",
+  ),
+  (3004, "AccountDidNotSerialize: failed to serialize the account"),
+  (
+    3005,
+    "AccountNotEnoughKeys: not enough account keys given to the instruction",
+  ),
+  (3006, "AccountNotMutable: the given account is not mutable"),
+  (
+    3007,
+    "AccountOwnedByWrongProgram: the given account is owned by a different program than expected",
+  ),
+  (3008, "InvalidProgramId: program ID was not as expected"),
+  (
+    3009,
+    "InvalidProgramExecutable: program account is not executable",
+  ),
+  (3010, "AccountNotSigner: the given account did not sign"),
+  (
+    3011,
+    "AccountNotSystemOwned: the given account is not owned by the system program",
+  ),
+  (
+    3012,
+    "AccountNotInitialized: the program expected this account to be already initialized",
+  ),
+  (
+    3013,
+    "AccountNotProgramData: the given account is not a program data account",
+  ),
+  (
+    3014,
+    "AccountNotAssociatedTokenAccount: the given account is not the associated token account",
+  ),
+  (
+    3015,
+    "AccountSysvarMismatch: the given public key does not match the required sysvar",
+  ),
+  (
+    3016,
+    "AccountReallocExceedsLimit: the account reallocation exceeds the MAX_PERMITTED_DATA_INCREASE limit",
+  ),
+  (
+    3017,
+    "AccountDuplicateReallocs: the account was duplicated for more than one reallocation",
+  ),
+  (
+    4100,
+    "DeclaredProgramIdMismatch: the declared program id does not match the actual program id",
+  ),
+  (
+    4101,
+    "TryingToInitPayerAsProgramAccount: you cannot/should not initialize the payer account as a program account",
+  ),
+  (4102, "InvalidNumericConversion: error during numeric conversion"),
+  (
+    5000,
+    "Deprecated: the API being used is deprecated and should no longer be used",
+  ),
+];
+
+/// Decode a custom program error code of a known program.
+/// Anchor errors are only decoded for programs with an IDL account, the
+/// errors defined by the program from the `errors` of its IDL.
+/// Returns `None` if the program or the code is not known.
+/// # Arguments
+/// * `program_id` - The program that returned the error.
+/// * `code` - The custom error code.
+/// * `source` - The source to look up the IDL account of the program in.
+pub fn decode_program_error(program_id: &Pubkey, code: u32, source: &dyn AccountSource) -> Option<String> {
+  if *program_id == system_program::ID {
+    return SystemError::from_u32(code).map(|err| format!("{err:?}: {err}"));
+  }
+
+  if *program_id == spl_token::ID {
+    return spl_token::error::TokenError::from_u32(code).map(|err| format!("{err:?}: {err}"));
+  }
+
+  if *program_id == spl_token_2022::ID {
+    return spl_token_2022::error::TokenError::from_u32(code).map(|err| format!("{err:?}: {err}"));
+  }
+
+  if let Some((_, err)) = ANCHOR_ERRORS.iter().find(|(anchor_code, _)| *anchor_code == code) {
+    return is_anchor_program(program_id, source).then(|| format!("{err} (Anchor)"));
+  }
+
+  match AnchorIdl::fetch(program_id, source) {
+    Ok(idl) => idl?.error(code),
+    Err(err) => {
+      log::debug!("Failed to fetch the IDL of {program_id}: {err}");
+      None
+    },
+  }
+}
+
+/// Check if a program is an Anchor program, by the existence of its IDL account.
+fn is_anchor_program(program_id: &Pubkey, source: &dyn AccountSource) -> bool {
+  let idl_account = anchor_idl_address(program_id).and_then(|address| source.get_account(&address));
+  match idl_account {
+    Ok(account) => account.is_some(),
+    Err(err) => {
+      log::debug!("Failed to get the IDL account of {program_id}: {err}");
+      false
+    },
+  }
+}
+
+/// Describe a transaction error, decoding custom program errors of known programs.
+/// # Arguments
+/// * `err` - The transaction error.
+/// * `transaction` - The transaction that failed, to find the failing program.
+/// * `source` - The source to look up the IDL account of the failing program in.
+pub fn describe_transaction_error(
+  err: &TransactionError,
+  transaction: &VersionedTransaction,
+  source: &dyn AccountSource,
+) -> String {
+  let TransactionError::InstructionError(index, InstructionError::Custom(code)) = err else {
+    return err.to_string();
+  };
+
+  let message = &transaction.message;
+  let program_id = message
+    .instructions()
+    .get(*index as usize)
+    .and_then(|instruction| {
+      message
+        .static_account_keys()
+        .get(instruction.program_id_index as usize)
+    });
+
+  let decoded = program_id.and_then(|program_id| decode_program_error(program_id, *code, source));
+  match (program_id, decoded) {
+    (Some(program_id), Some(decoded)) => {
+      format!("Instruction {index} of {program_id} failed with custom error {code} ({code:#x}): {decoded}")
+    },
+    (Some(program_id), None) => {
+      format!("Instruction {index} of {program_id} failed with custom error {code} ({code:#x})")
+    },
+    (None, _) => err.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mock_rpc::{
+    MockRpcFixtures,
+    serve_mock_rpc,
+  };
+  use crate::test_support::{
+    account,
+    encode_idl,
+  };
+  use solana_client::rpc_client::RpcClient;
+
+  #[test]
+  fn it_should_decode_known_program_errors() -> anyhow::Result<()> {
+    let anchor_program = Pubkey::new_unique();
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_account(
+      anchor_idl_address(&anchor_program)?,
+      account(
+        anchor_program,
+        encode_idl(
+          r#"{
+            "metadata": { "name": "vault" },
+            "accounts": [],
+            "errors": [{ "code": 6000, "name": "VaultLocked", "msg": "The vault is locked" }]
+          }"#,
+        )?,
+      ),
+    );
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let client = RpcClient::new(server.url());
+
+    assert_eq!(
+      decode_program_error(&spl_token::ID, 1, &client).as_deref(),
+      Some("InsufficientFunds: Insufficient funds")
+    );
+    assert!(
+      decode_program_error(&system_program::ID, 1, &client)
+        .is_some_and(|err| err.starts_with("ResultWithNegativeLamports"))
+    );
+    assert!(
+      decode_program_error(&anchor_program, 2006, &client)
+        .is_some_and(|err| err.starts_with("ConstraintSeeds"))
+    );
+    assert!(
+      decode_program_error(&anchor_program, 2019, &client)
+        .is_some_and(|err| err.starts_with("ConstraintSpace"))
+    );
+    assert_eq!(
+      decode_program_error(&anchor_program, 6000, &client).as_deref(),
+      Some("VaultLocked: The vault is locked")
+    );
+    assert_eq!(decode_program_error(&anchor_program, 6001, &client), None);
+    assert_eq!(decode_program_error(&Pubkey::new_unique(), 2006, &client), None);
+    Ok(())
+  }
+}
@@ -0,0 +1,269 @@
+use anyhow::Context;
+use hashbrown::{
+  HashMap,
+  HashSet,
+};
+use solana_client::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{
+  RpcTransactionLogsConfig,
+  RpcTransactionLogsFilter,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{
+  compute_budget,
+  pubkey,
+  system_program,
+};
+use std::str::FromStr as _;
+use std::thread;
+
+use crate::lumos_context::LumosContext;
+use crate::schema::ConfigRoot;
+
+/// Well known programs that are not part of the config.
+const KNOWN_PROGRAMS: &[(Pubkey, &str)] = &[
+  (system_program::ID, "system"),
+  (spl_token::ID, "spl_token"),
+  (spl_token_2022::ID, "spl_token_2022"),
+  (
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"),
+    "associated_token",
+  ),
+  (compute_budget::ID, "compute_budget"),
+];
+
+/// ProgramLogFormatter pretty-prints the logs of a transaction, nesting the
+/// program invocations and resolving program ids to their config names.
+pub struct ProgramLogFormatter {
+  names: HashMap<String, String>,
+  filter: HashSet<String>,
+}
+
+/// Implementation of ProgramLogFormatter.
+impl ProgramLogFormatter {
+  /// Create a new ProgramLogFormatter.
+  /// # Arguments
+  /// * `config` - The config to resolve program names from.
+  /// * `filter` - Only show transactions invoking these programs (config names
+  ///   or addresses), all transactions are shown if empty.
+  pub fn new(config: &ConfigRoot, filter: &[String]) -> anyhow::Result<Self> {
+    let mut names: HashMap<String, String> = KNOWN_PROGRAMS
+      .iter()
+      .map(|(address, name)| (address.to_string(), name.to_string()))
+      .collect();
+
+    for (name, program) in config.program.iter() {
+      names.insert(program.address.clone(), name.clone());
+    }
+
+    let filter = resolve_program_filter(config, filter)?
+      .iter()
+      .map(|program_id| program_id.to_string())
+      .collect();
+
+    Ok(Self { names, filter })
+  }
+
+  /// Check if the transaction logs invoke one of the filtered programs.
+  pub fn matches(&self, logs: &[String]) -> bool {
+    if self.filter.is_empty() {
+      return true;
+    }
+
+    logs.iter().any(|line| {
+      parse_invoke(line)
+        .map(|(program_id, _)| self.filter.contains(program_id))
+        .unwrap_or(false)
+    })
+  }
+
+  /// Format the logs of a transaction.
+  /// # Arguments
+  /// * `signature` - The transaction signature.
+  /// * `err` - The transaction error, if it failed.
+  /// * `logs` - The raw log lines of the transaction.
+  pub fn format(&self, signature: &str, err: Option<String>, logs: &[String]) -> Vec<String> {
+    let mut lines = Vec::with_capacity(logs.len() + 1);
+    match err {
+      Some(err) => lines.push(format!("Transaction {signature} failed: {err}")),
+      None => lines.push(format!("Transaction {signature}")),
+    }
+
+    let mut depth = 0;
+    for line in logs.iter() {
+      if let Some((program_id, invoke_depth)) = parse_invoke(line) {
+        depth = invoke_depth;
+        lines.push(format!(
+          "{}{} invoke",
+          indent(depth),
+          self.program_name(program_id)
+        ));
+        continue;
+      }
+
+      if let Some(message) = line.strip_prefix("Program log: ") {
+        lines.push(format!("{}log: {message}", indent(depth + 1)));
+        continue;
+      }
+
+      if let Some(data) = line.strip_prefix("Program data: ") {
+        lines.push(format!("{}data: {data}", indent(depth + 1)));
+        continue;
+      }
+
+      if let Some(data) = line.strip_prefix("Program return: ") {
+        let data = data.split_once(' ').map(|(_, data)| data).unwrap_or(data);
+        lines.push(format!("{}return: {data}", indent(depth + 1)));
+        continue;
+      }
+
+      let Some(rest) = line.strip_prefix("Program ") else {
+        lines.push(format!("{}{line}", indent(depth + 1)));
+        continue;
+      };
+
+      let (_, result) = rest.split_once(' ').unwrap_or((rest, ""));
+      if let Some(units) = result.strip_prefix("consumed ") {
+        lines.push(format!("{}consumed {units}", indent(depth + 1)));
+      } else if result == "success" || result.starts_with("failed") {
+        lines.push(format!("{}{result}", indent(depth + 1)));
+        depth = depth.saturating_sub(1);
+      } else {
+        lines.push(format!("{}{line}", indent(depth + 1)));
+      }
+    }
+
+    lines
+  }
+
+  /// Get the display name of a program.
+  fn program_name(&self, program_id: &str) -> String {
+    match self.names.get(program_id) {
+      Some(name) => format!("{name} ({program_id})"),
+      None => program_id.to_string(),
+    }
+  }
+}
+
+/// Parse a `Program <id> invoke [<depth>]` line.
+fn parse_invoke(line: &str) -> Option<(&str, usize)> {
+  let rest = line.strip_prefix("Program ")?;
+  let (program_id, rest) = rest.split_once(" invoke [")?;
+  let depth = rest.strip_suffix(']')?.parse().ok()?;
+  Some((program_id, depth))
+}
+
+/// Resolve the programs to show the logs of from config names or addresses.
+/// Returns an error for a value that is neither, as it would hide all logs.
+/// # Arguments
+/// * `config` - The config to resolve program names from.
+/// * `filter` - The config names or addresses of the programs.
+pub fn resolve_program_filter(config: &ConfigRoot, filter: &[String]) -> anyhow::Result<Vec<Pubkey>> {
+  filter
+    .iter()
+    .map(|value| {
+      let address = config.resolve_address(value);
+      Pubkey::from_str(address)
+        .with_context(|| format!("Unknown program: {value}, expected a config name or an address"))
+    })
+    .collect()
+}
+
+/// Get the indentation for a nesting depth.
+fn indent(depth: usize) -> String {
+  "  ".repeat(depth)
+}
+
+/// Subscribe to the program logs of the local validator and print them.
+/// The subscription ends when the validator stops.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `filter` - Only show transactions invoking these programs (config names
+///   or addresses), all transactions are shown if empty.
+pub fn subscribe_program_logs(context: &LumosContext, filter: &[String]) -> anyhow::Result<()> {
+  let formatter = ProgramLogFormatter::new(&context.config, filter)?;
+  let ws_url: &str = &context.local_ws_url();
+  let config = RpcTransactionLogsConfig {
+    commitment: Some(CommitmentConfig::confirmed()),
+  };
+
+  let (subscription, receiver) = PubsubClient::logs_subscribe(ws_url, RpcTransactionLogsFilter::All, config)
+    .with_context(|| format!("Failed to subscribe to program logs: {ws_url}"))?;
+
+  let pb = context.pb.clone();
+  thread::spawn(move || {
+    // Keep the subscription alive as long as logs are received.
+    let _subscription = subscription;
+    for response in receiver.iter() {
+      let logs = response.value;
+      if !formatter.matches(&logs.logs) {
+        continue;
+      }
+
+      let err = logs.err.map(|err| err.to_string());
+      for line in formatter.format(&logs.signature, err, &logs.logs) {
+        let _ = pb.println(line);
+      }
+    }
+  });
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_should_format_nested_program_logs() -> anyhow::Result<()> {
+    let config: ConfigRoot = toml::from_str(
+      r#"
+      [general]
+      rpc_endpoint = "http://localhost:8899"
+
+      [account]
+
+      [program.orca_whirlpool]
+      address = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"
+      "#,
+    )?;
+
+    let logs: Vec<String> = [
+      "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc invoke [1]",
+      "Program log: Instruction: Swap",
+      "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [2]",
+      "Program log: Instruction: Transfer",
+      "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4645 of 1381000 compute units",
+      "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
+      "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc consumed 51233 of 1400000 compute units",
+      "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc success",
+    ]
+    .iter()
+    .map(|line| line.to_string())
+    .collect();
+
+    let formatter = ProgramLogFormatter::new(&config, &["orca_whirlpool".to_string()])?;
+    assert!(formatter.matches(&logs));
+    assert_eq!(
+      formatter.format("sig", None, &logs),
+      vec![
+        "Transaction sig",
+        "  orca_whirlpool (whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc) invoke",
+        "    log: Instruction: Swap",
+        "    spl_token (TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA) invoke",
+        "      log: Instruction: Transfer",
+        "      consumed 4645 of 1381000 compute units",
+        "      success",
+        "    consumed 51233 of 1400000 compute units",
+        "    success",
+      ]
+    );
+
+    let formatter = ProgramLogFormatter::new(&config, &[system_program::ID.to_string()])?;
+    assert!(!formatter.matches(&logs));
+
+    assert!(ProgramLogFormatter::new(&config, &["orca_whirlpol".to_string()]).is_err());
+    Ok(())
+  }
+}
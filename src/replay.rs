@@ -0,0 +1,576 @@
+use anyhow::Context;
+use base64::prelude::*;
+use hashbrown::HashMap;
+use serde::Deserialize;
+use serde_json::{
+  Value,
+  json,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::{
+  TransactionError,
+  VersionedTransaction,
+};
+use solana_sdk::{
+  bpf_loader,
+  bpf_loader_deprecated,
+  bpf_loader_upgradeable,
+  native_loader,
+  sysvar,
+};
+use spl_token_2022::extension::{
+  StateWithExtensions,
+  StateWithExtensionsMut,
+};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::str::FromStr as _;
+
+use crate::dump::fetch_accounts;
+use crate::lumos_context::LumosContext;
+use crate::lumos_validator::LumosValidator;
+use crate::schema::{
+  AccountConfig,
+  ConfigRoot,
+  GeneralConfig,
+  ProgramConfig,
+};
+use crate::simulate::{
+  SimulationResult,
+  simulate_transaction,
+};
+use crate::utils::write_account_json;
+
+/// The loaders of deployed programs, which are cloned as programs.
+const BPF_LOADER_IDS: &[Pubkey] = &[
+  bpf_loader_deprecated::ID,
+  bpf_loader::ID,
+  bpf_loader_upgradeable::ID,
+];
+
+/// A transaction fetched from the upstream cluster with its on-chain result.
+#[derive(Debug)]
+pub struct ReplayTransaction {
+  /// The transaction signature.
+  pub signature: String,
+  /// The slot the transaction landed in.
+  pub slot: u64,
+  /// The transaction.
+  pub transaction: VersionedTransaction,
+  /// The account keys of the message, followed by the keys loaded from lookup tables.
+  pub account_keys: Vec<Pubkey>,
+  /// The on-chain result, if the transaction has status metadata.
+  pub meta: Option<OnChainResult>,
+}
+
+/// The on-chain result of a transaction.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnChainResult {
+  /// The transaction error, if it failed.
+  pub err: Option<TransactionError>,
+  /// The program logs.
+  #[serde(default)]
+  pub log_messages: Option<Vec<String>>,
+  /// The compute units consumed.
+  #[serde(default)]
+  pub compute_units_consumed: Option<u64>,
+  /// The lamports of the account keys before the transaction.
+  pub pre_balances: Vec<u64>,
+  /// The lamports of the account keys after the transaction.
+  pub post_balances: Vec<u64>,
+  /// The token balances before the transaction.
+  #[serde(default)]
+  pub pre_token_balances: Option<Vec<TokenBalance>>,
+  /// The token balances after the transaction.
+  #[serde(default)]
+  pub post_token_balances: Option<Vec<TokenBalance>>,
+  /// The keys loaded from address lookup tables.
+  #[serde(default)]
+  loaded_addresses: Option<LoadedAddresses>,
+}
+
+/// A token balance of an account key.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBalance {
+  /// The index of the account in the account keys.
+  pub account_index: usize,
+  /// The token mint.
+  pub mint: String,
+  /// The token amount.
+  pub ui_token_amount: TokenAmount,
+}
+
+/// A raw token amount.
+#[derive(Debug, Deserialize)]
+pub struct TokenAmount {
+  /// The amount in base units.
+  pub amount: String,
+}
+
+/// The keys loaded from address lookup tables.
+#[derive(Debug, Default, Deserialize)]
+struct LoadedAddresses {
+  writable: Vec<String>,
+  readonly: Vec<String>,
+}
+
+/// The `getTransaction` result.
+#[derive(Debug, Deserialize)]
+struct TransactionResult {
+  slot: u64,
+  transaction: Vec<String>,
+  meta: Option<OnChainResult>,
+}
+
+/// The change of an account balance, in lamports or token base units.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BalanceChange {
+  /// The account address.
+  pub address: Pubkey,
+  /// The token mint, or `None` for lamports.
+  pub mint: Option<Pubkey>,
+  /// The change in the local replay.
+  pub local: i128,
+  /// The change on chain, if known.
+  pub on_chain: Option<i128>,
+}
+
+/// Implementation of BalanceChange.
+impl BalanceChange {
+  /// Check if the local change matches the on-chain change.
+  pub fn matches(&self) -> bool {
+    self.on_chain.map_or(true, |on_chain| on_chain == self.local)
+  }
+}
+
+/// The report of a replayed transaction.
+#[derive(Debug)]
+pub struct ReplayReport {
+  /// The transaction and its on-chain result.
+  pub transaction: ReplayTransaction,
+  /// The local result.
+  pub simulation: SimulationResult,
+  /// The lamport and token balance changes.
+  pub balance_changes: Vec<BalanceChange>,
+}
+
+/// Fetch a transaction with its on-chain result.
+/// # Arguments
+/// * `client` - The RPC client of the upstream cluster.
+/// * `signature` - The transaction signature.
+pub fn fetch_transaction(client: &RpcClient, signature: &str) -> anyhow::Result<ReplayTransaction> {
+  let config = json!({
+    "encoding": "base64",
+    "commitment": "confirmed",
+    "maxSupportedTransactionVersion": 0,
+  });
+
+  let result: Value = client
+    .send(RpcRequest::GetTransaction, json!([signature, config]))
+    .with_context(|| format!("Failed to get transaction: {signature}"))?;
+  if result.is_null() {
+    anyhow::bail!("Transaction not found: {signature}");
+  }
+
+  let result: TransactionResult = serde_json::from_value(result)?;
+  let encoded = result
+    .transaction
+    .first()
+    .with_context(|| "Transaction is missing its data")?;
+  let transaction: VersionedTransaction = bincode::deserialize(&BASE64_STANDARD.decode(encoded)?)?;
+
+  let mut account_keys = transaction.message.static_account_keys().to_vec();
+  if let Some(loaded) = result
+    .meta
+    .as_ref()
+    .and_then(|meta| meta.loaded_addresses.as_ref())
+  {
+    for address in loaded.writable.iter().chain(loaded.readonly.iter()) {
+      account_keys.push(Pubkey::from_str(address)?);
+    }
+  }
+
+  Ok(ReplayTransaction {
+    signature: signature.to_string(),
+    slot: result.slot,
+    transaction,
+    account_keys,
+    meta: result.meta,
+  })
+}
+
+/// Create the config cloning the accounts and programs a transaction needs.
+/// Builtin programs and sysvars are skipped as they exist on every validator.
+/// # Arguments
+/// * `base` - The config to take the upstream endpoint from.
+/// * `cache_dir` - The cache directory of the cloned state.
+/// * `accounts` - The upstream accounts referenced by the transaction.
+pub fn replay_config(base: &ConfigRoot, cache_dir: &str, accounts: &HashMap<Pubkey, Account>) -> ConfigRoot {
+  let mut config = ConfigRoot {
+    general: GeneralConfig {
+      rpc_endpoint: base.general.rpc_endpoint.clone(),
+      cache_dir: Some(cache_dir.to_string()),
+      ..GeneralConfig::default()
+    },
+    ..ConfigRoot::default()
+  };
+
+  for (address, account) in accounts.iter() {
+    // Builtin programs and sysvars exist on every validator.
+    if account.owner == native_loader::ID || account.owner == sysvar::ID {
+      continue;
+    }
+
+    let name = address.to_string();
+    if account.executable && BPF_LOADER_IDS.contains(&account.owner) {
+      let program = ProgramConfig {
+        address: name.clone(),
+        authority: None,
+        update: None,
+        path: None,
+      };
+      config.program.insert(name, program);
+    } else {
+      let account = AccountConfig {
+        address: name.clone(),
+        update: None,
+        mint: None,
+        clone_metadata: None,
+      };
+      config.account.insert(name, account);
+    }
+  }
+
+  config
+}
+
+/// Rewind the fetched accounts of a transaction to their state before it, from
+/// the pre balances of its metadata. Accounts without lamports before the
+/// transaction are removed, the others get their pre lamports and token
+/// amounts. Other changes of the account data can't be rewound.
+/// # Arguments
+/// * `transaction` - The transaction and its on-chain result.
+/// * `accounts` - The accounts fetched at their current state.
+pub fn rewind_accounts(transaction: &ReplayTransaction, accounts: &mut HashMap<Pubkey, Account>) {
+  let Some(meta) = transaction.meta.as_ref() else {
+    return;
+  };
+
+  for (address, lamports) in transaction.account_keys.iter().zip(meta.pre_balances.iter()) {
+    match *lamports {
+      0 => {
+        accounts.remove(address);
+      },
+      lamports => {
+        if let Some(account) = accounts.get_mut(address) {
+          account.lamports = lamports;
+        }
+      },
+    }
+  }
+
+  for (index, (_, amount)) in token_balances(meta.pre_token_balances.as_ref()) {
+    let Some(account) = transaction
+      .account_keys
+      .get(index)
+      .and_then(|address| accounts.get_mut(address))
+    else {
+      continue;
+    };
+
+    if account.owner != spl_token::ID && account.owner != spl_token_2022::ID {
+      continue;
+    }
+
+    if let Ok(mut state) = StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack(&mut account.data)
+    {
+      state.base.amount = amount;
+      state.pack_base();
+    }
+  }
+}
+
+/// Compute the lamport and token balance changes of a replay, next to the
+/// on-chain changes.
+pub fn balance_changes(transaction: &ReplayTransaction, simulation: &SimulationResult) -> Vec<BalanceChange> {
+  let mut changes = vec![];
+  let meta = transaction.meta.as_ref();
+
+  for (index, address) in transaction.account_keys.iter().enumerate() {
+    let pre = lamports(simulation.pre_accounts.get(index));
+    let post = lamports(simulation.post_accounts.get(index));
+    let on_chain = meta.and_then(|meta| {
+      let pre = meta.pre_balances.get(index)?;
+      let post = meta.post_balances.get(index)?;
+      Some(*post as i128 - *pre as i128)
+    });
+
+    let change = BalanceChange {
+      address: *address,
+      mint: None,
+      local: post as i128 - pre as i128,
+      on_chain,
+    };
+
+    if change.local != 0 || change.on_chain.is_some_and(|on_chain| on_chain != 0) {
+      changes.push(change);
+    }
+  }
+
+  let on_chain_pre = token_balances(meta.and_then(|meta| meta.pre_token_balances.as_ref()));
+  let on_chain_post = token_balances(meta.and_then(|meta| meta.post_token_balances.as_ref()));
+
+  let mut indexes: BTreeSet<usize> = on_chain_pre.keys().chain(on_chain_post.keys()).copied().collect();
+  for index in 0..transaction.account_keys.len() {
+    let is_token = |accounts: &[Option<Account>]| token_amount(accounts.get(index)).is_some();
+    if is_token(&simulation.pre_accounts) || is_token(&simulation.post_accounts) {
+      indexes.insert(index);
+    }
+  }
+
+  for index in indexes {
+    let Some(address) = transaction.account_keys.get(index) else {
+      continue;
+    };
+
+    let pre = token_amount(simulation.pre_accounts.get(index));
+    let post = token_amount(simulation.post_accounts.get(index));
+    let mint = post.or(pre).map(|(mint, _)| mint).or_else(|| {
+      on_chain_post
+        .get(&index)
+        .or(on_chain_pre.get(&index))
+        .map(|(mint, _)| *mint)
+    });
+
+    let local = post.map(|(_, amount)| amount).unwrap_or(0) as i128
+      - pre.map(|(_, amount)| amount).unwrap_or(0) as i128;
+    let on_chain = meta.map(|_| {
+      let pre = on_chain_pre.get(&index).map(|(_, amount)| *amount).unwrap_or(0);
+      let post = on_chain_post.get(&index).map(|(_, amount)| *amount).unwrap_or(0);
+      post as i128 - pre as i128
+    });
+
+    let change = BalanceChange {
+      address: *address,
+      mint,
+      local,
+      on_chain,
+    };
+
+    if change.local != 0 || change.on_chain.is_some_and(|on_chain| on_chain != 0) {
+      changes.push(change);
+    }
+  }
+
+  changes
+}
+
+/// Get the lamports of an optional account.
+fn lamports(account: Option<&Option<Account>>) -> u64 {
+  account
+    .and_then(Option::as_ref)
+    .map(|account| account.lamports)
+    .unwrap_or(0)
+}
+
+/// Get the mint and amount of an optional token account.
+fn token_amount(account: Option<&Option<Account>>) -> Option<(Pubkey, u64)> {
+  let account = account.and_then(Option::as_ref)?;
+  if account.owner != spl_token::ID && account.owner != spl_token_2022::ID {
+    return None;
+  }
+
+  let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).ok()?;
+  Some((state.base.mint, state.base.amount))
+}
+
+/// Index the token balances by account index.
+fn token_balances(balances: Option<&Vec<TokenBalance>>) -> HashMap<usize, (Pubkey, u64)> {
+  balances
+    .into_iter()
+    .flatten()
+    .filter_map(|balance| {
+      let mint = Pubkey::from_str(&balance.mint).ok()?;
+      let amount = balance.ui_token_amount.amount.parse().ok()?;
+      Some((balance.account_index, (mint, amount)))
+    })
+    .collect()
+}
+
+/// Replay a transaction of the upstream cluster against its cloned state.
+/// The referenced accounts are rewound to their pre balances and written into
+/// a separate cache once, so replays are repeatable. Only lamports and
+/// token amounts are rewound, other data is the one at the time of the first
+/// replay, not at the transaction's slot. Accounts closed by the transaction
+/// can't be fetched and are missing.
+/// # Arguments
+/// * `context` - The lumos context with the upstream endpoint.
+/// * `signature` - The transaction signature.
+pub fn replay_transaction(context: &LumosContext, signature: &str) -> anyhow::Result<ReplayReport> {
+  let client = RpcClient::new(context.rpc_endpoint());
+  let transaction = fetch_transaction(&client, signature)?;
+
+  // Lookup tables are needed to load the transaction locally.
+  let mut addresses = transaction.account_keys.clone();
+  if let Some(lookups) = transaction.transaction.message.address_table_lookups() {
+    addresses.extend(lookups.iter().map(|lookup| lookup.account_key));
+  }
+
+  let mut accounts = fetch_accounts(&client, &[], &addresses)?;
+  rewind_accounts(&transaction, &mut accounts);
+
+  let cache_dir = Path::new(&context.cache_dir()).join("replay").join(signature);
+  let config = replay_config(
+    &context.config,
+    cache_dir.to_str().with_context(|| "Invalid cache directory")?,
+    &accounts,
+  );
+
+  // Write the fetched accounts to the cache so they aren't cloned again,
+  // keeping the ones of an earlier replay.
+  let account_dir = cache_dir.join("accounts");
+  for (address, account) in accounts.iter() {
+    if config.account.contains_key(&address.to_string())
+      && !account_dir.join(format!("{address}.json")).exists()
+    {
+      write_account_json(&account_dir, address, account)?;
+    }
+  }
+
+  let validator = LumosValidator::builder(config).verbose(context.verbose).spawn()?;
+  let simulation = simulate_transaction(
+    &validator.rpc_client(),
+    &transaction.transaction,
+    &transaction.account_keys,
+  )?;
+
+  let balance_changes = balance_changes(&transaction, &simulation);
+  Ok(ReplayReport {
+    transaction,
+    simulation,
+    balance_changes,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mock_rpc::{
+    MockRpcFixtures,
+    serve_mock_rpc,
+  };
+  use crate::test_support::token_account;
+  use solana_sdk::instruction::{
+    AccountMeta,
+    Instruction,
+  };
+  use solana_sdk::message::Message;
+  use solana_sdk::signature::{
+    Keypair,
+    Signer as _,
+  };
+  use solana_sdk::system_program;
+  use solana_sdk::transaction::Transaction;
+
+  #[test]
+  fn it_should_fetch_transaction_and_diff_balances() -> anyhow::Result<()> {
+    let payer = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let instruction = Instruction::new_with_bincode(
+      system_program::ID,
+      &(2u32, 1_000u64),
+      vec![
+        AccountMeta::new(payer.pubkey(), true),
+        AccountMeta::new(recipient, false),
+      ],
+    );
+    let message = Message::new(&[instruction], Some(&payer.pubkey()));
+    let transaction = Transaction::new(&[&payer], message, Default::default());
+    let signature = transaction.signatures[0].to_string();
+
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_transaction(
+      &signature,
+      json!({
+        "slot": 42,
+        "transaction": [BASE64_STANDARD.encode(bincode::serialize(&transaction)?), "base64"],
+        "meta": {
+          "err": null,
+          "logMessages": ["Program 11111111111111111111111111111111 invoke [1]"],
+          "computeUnitsConsumed": 150,
+          "preBalances": [10_000, 0, 1],
+          "postBalances": [3_000, 1_000, 1],
+          "preTokenBalances": [],
+          "postTokenBalances": [],
+        },
+      }),
+    );
+
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let client = RpcClient::new(server.url());
+    let fetched = fetch_transaction(&client, &signature)?;
+    assert_eq!(fetched.slot, 42);
+    assert_eq!(fetched.account_keys[..2], [payer.pubkey(), recipient]);
+    assert!(fetch_transaction(&client, "unknown").is_err());
+
+    let account = |lamports| {
+      Some(Account {
+        lamports,
+        ..Account::default()
+      })
+    };
+    let simulation = SimulationResult {
+      addresses: fetched.account_keys.clone(),
+      err: None,
+      logs: vec![],
+      units_consumed: Some(150),
+      pre_accounts: vec![account(10_000), None, account(1)],
+      post_accounts: vec![account(4_000), account(1_000), account(1)],
+    };
+
+    let changes = balance_changes(&fetched, &simulation);
+    assert_eq!(changes.len(), 2);
+    assert_eq!((changes[0].local, changes[0].on_chain), (-6_000, Some(-7_000)));
+    assert!(!changes[0].matches());
+    assert!(changes[1].matches());
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_rewind_accounts_to_the_pre_balances() -> anyhow::Result<()> {
+    let (payer, created, token) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mint = Pubkey::new_unique();
+    let transaction = ReplayTransaction {
+      signature: "sig".to_string(),
+      slot: 42,
+      transaction: VersionedTransaction::default(),
+      account_keys: vec![payer, created, token],
+      meta: Some(serde_json::from_value(json!({
+        "err": null,
+        "preBalances": [10_000, 0, 2_039_280],
+        "postBalances": [3_000, 1_000, 2_039_280],
+        "preTokenBalances": [{ "accountIndex": 2, "mint": mint.to_string(), "uiTokenAmount": { "amount": "700" } }],
+      }))?),
+    };
+
+    let mut accounts = HashMap::from([
+      (payer, Account::new(3_000, 0, &Pubkey::default())),
+      (created, Account::new(1_000, 0, &Pubkey::default())),
+      (token, token_account(mint, payer, 500)),
+    ]);
+
+    rewind_accounts(&transaction, &mut accounts);
+    assert_eq!(accounts[&payer].lamports, 10_000);
+    assert!(!accounts.contains_key(&created));
+    assert_eq!(
+      token_amount(Some(&accounts.get(&token).cloned())).map(|(_, amount)| amount),
+      Some(700)
+    );
+    Ok(())
+  }
+}
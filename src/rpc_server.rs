@@ -0,0 +1,276 @@
+use anyhow::Context;
+use serde_json::{
+  Value,
+  json,
+};
+use solana_account_decoder::{
+  UiAccountEncoding,
+  encode_ui_account,
+};
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+use std::sync::Arc;
+use std::thread::{
+  self,
+  JoinHandle,
+};
+use tiny_http::{
+  Header,
+  Method,
+  Response,
+  Server,
+};
+
+/// The Solana RPC API version reported to clients.
+pub const RPC_API_VERSION: &str = "2.2.0";
+
+/// The JSON-RPC error code of an unknown method.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The JSON-RPC error code of invalid method parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// The JSON-RPC error code of a request that is not valid JSON-RPC.
+const INVALID_REQUEST: i64 = -32600;
+
+/// The JSON-RPC error code of a request body that is not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+
+/// RpcError is a JSON-RPC error returned by a method handler.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+  pub code: i64,
+  pub message: String,
+  pub data: Option<Value>,
+}
+
+/// Implementation of RpcError.
+impl RpcError {
+  /// Create a new RpcError without data.
+  pub fn new(code: i64, message: impl Into<String>) -> Self {
+    Self {
+      code,
+      message: message.into(),
+      data: None,
+    }
+  }
+
+  /// Create an error for an unknown method.
+  pub fn method_not_found(method: &str) -> Self {
+    Self::new(METHOD_NOT_FOUND, format!("Method not found: {method}"))
+  }
+
+  /// Create an error for invalid method parameters.
+  pub fn invalid_params(message: impl Into<String>) -> Self {
+    Self::new(INVALID_PARAMS, message)
+  }
+
+  /// Convert the error to its JSON representation.
+  fn to_json(&self) -> Value {
+    match &self.data {
+      Some(data) => json!({ "code": self.code, "message": self.message, "data": data }),
+      None => json!({ "code": self.code, "message": self.message }),
+    }
+  }
+}
+
+/// Display implementation of RpcError.
+impl fmt::Display for RpcError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "RPC error {}: {}", self.code, self.message)
+  }
+}
+
+/// Error implementation of RpcError.
+impl std::error::Error for RpcError {}
+
+/// The result of a JSON-RPC method handler.
+pub type RpcResult = Result<Value, RpcError>;
+
+/// RpcServer serves JSON-RPC over HTTP on a local port from a background
+/// thread. Requests are handled one at a time, including batches.
+/// The server is stopped when it is dropped.
+pub struct RpcServer {
+  server: Arc<Server>,
+  port: u16,
+  handle: Option<JoinHandle<()>>,
+}
+
+/// Implementation of RpcServer.
+impl RpcServer {
+  /// Start serving JSON-RPC on a local port.
+  /// # Arguments
+  /// * `port` - The port to listen on, a free port is picked if 0.
+  /// * `handler` - Handles a method call with its params.
+  pub fn start<F>(port: u16, mut handler: F) -> anyhow::Result<Self>
+  where
+    F: FnMut(&str, Value) -> RpcResult + Send + 'static,
+  {
+    let server = Server::http(("127.0.0.1", port))
+      .map_err(|err| anyhow::anyhow!("Failed to start RPC server on port {port}: {err}"))?;
+    let port = server
+      .server_addr()
+      .to_ip()
+      .map(|addr| addr.port())
+      .with_context(|| "Failed to get the RPC server address")?;
+
+    let server = Arc::new(server);
+    let incoming = server.clone();
+    let handle = thread::spawn(move || {
+      for mut request in incoming.incoming_requests() {
+        if *request.method() != Method::Post {
+          let _ = request.respond(Response::empty(405));
+          continue;
+        }
+
+        let mut body = String::new();
+        let response = match request.as_reader().read_to_string(&mut body) {
+          Ok(_) => handle_body(&body, &mut handler),
+          Err(err) => error_response(Value::Null, &RpcError::new(PARSE_ERROR, err.to_string())),
+        };
+
+        let header = Header::from_bytes("Content-Type", "application/json").expect("Static header is valid");
+        let _ = request.respond(Response::from_string(response.to_string()).with_header(header));
+      }
+    });
+
+    Ok(Self {
+      server,
+      port,
+      handle: Some(handle),
+    })
+  }
+
+  /// Get the port the server listens on.
+  pub fn port(&self) -> u16 {
+    self.port
+  }
+
+  /// Get the URL of the server.
+  pub fn url(&self) -> String {
+    format!("http://127.0.0.1:{}", self.port)
+  }
+}
+
+/// Drop implementation of RpcServer.
+impl Drop for RpcServer {
+  fn drop(&mut self) {
+    self.server.unblock();
+    if let Some(handle) = self.handle.take() {
+      let _ = handle.join();
+    }
+  }
+}
+
+/// Handle a request body holding a single request or a batch.
+fn handle_body<F>(body: &str, handler: &mut F) -> Value
+where
+  F: FnMut(&str, Value) -> RpcResult,
+{
+  let request: Value = match serde_json::from_str(body) {
+    Ok(request) => request,
+    Err(err) => return error_response(Value::Null, &RpcError::new(PARSE_ERROR, err.to_string())),
+  };
+
+  match request {
+    Value::Array(requests) => Value::Array(
+      requests
+        .into_iter()
+        .map(|request| handle_request(request, handler))
+        .collect(),
+    ),
+    request => handle_request(request, handler),
+  }
+}
+
+/// Handle a single JSON-RPC request.
+fn handle_request<F>(request: Value, handler: &mut F) -> Value
+where
+  F: FnMut(&str, Value) -> RpcResult,
+{
+  let id = request.get("id").cloned().unwrap_or(Value::Null);
+  let Some(method) = request.get("method").and_then(Value::as_str) else {
+    return error_response(id, &RpcError::new(INVALID_REQUEST, "Invalid request"));
+  };
+
+  let params = request.get("params").cloned().unwrap_or(Value::Array(vec![]));
+  log::trace!("RPC request: {method} {params}");
+
+  match handler(method, params) {
+    Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+    Err(err) => error_response(id, &err),
+  }
+}
+
+/// Create a JSON-RPC error response.
+fn error_response(id: Value, err: &RpcError) -> Value {
+  json!({ "jsonrpc": "2.0", "error": err.to_json(), "id": id })
+}
+
+/// Get a positional param, or null if it's missing.
+pub fn param(params: &Value, index: usize) -> Value {
+  params.get(index).cloned().unwrap_or(Value::Null)
+}
+
+/// Deserialize a required positional param.
+pub fn required_param<T: serde::de::DeserializeOwned>(params: &Value, index: usize) -> Result<T, RpcError> {
+  serde_json::from_value(param(params, index))
+    .map_err(|err| RpcError::invalid_params(format!("Invalid param {index}: {err}")))
+}
+
+/// Deserialize an optional positional param, like a config object.
+pub fn optional_param<T: serde::de::DeserializeOwned>(
+  params: &Value,
+  index: usize,
+) -> Result<Option<T>, RpcError> {
+  match param(params, index) {
+    Value::Null => Ok(None),
+    value => serde_json::from_value(value)
+      .map(Some)
+      .map_err(|err| RpcError::invalid_params(format!("Invalid param {index}: {err}"))),
+  }
+}
+
+/// Get the response context with the current slot.
+pub fn rpc_context(slot: u64) -> Value {
+  json!({ "slot": slot, "apiVersion": RPC_API_VERSION })
+}
+
+/// Encode an account like the `getAccountInfo` method does.
+/// Base64 is used if no encoding is requested.
+pub fn encode_account(pubkey: &Pubkey, account: &Account, config: &RpcAccountInfoConfig) -> Value {
+  let encoding = config.encoding.unwrap_or(UiAccountEncoding::Base64);
+  let account = encode_ui_account(pubkey, account, encoding, None, config.data_slice);
+  serde_json::to_value(account).unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use solana_client::rpc_client::RpcClient;
+
+  #[test]
+  fn it_should_serve_json_rpc_requests() -> anyhow::Result<()> {
+    let server = RpcServer::start(0, |method, params| match method {
+      "getHealth" => Ok(json!("ok")),
+      "getBalance" => Ok(json!({ "context": { "slot": 1 }, "value": params.as_array().map(Vec::len) })),
+      method => Err(RpcError::method_not_found(method)),
+    })?;
+
+    let client = RpcClient::new(server.url());
+    assert!(client.get_health().is_ok());
+    assert_eq!(client.get_balance(&solana_sdk::pubkey::Pubkey::new_unique())?, 2);
+    assert!(client.get_slot().is_err());
+
+    let mut handler = |_: &str, _: Value| Ok(json!(1));
+    let batch = handle_body(
+      r#"[{"jsonrpc":"2.0","id":1,"method":"a"},{"jsonrpc":"2.0","id":2}]"#,
+      &mut handler,
+    );
+    assert_eq!(batch[0]["result"], json!(1));
+    assert_eq!(batch[1]["error"]["code"], json!(INVALID_REQUEST));
+    Ok(())
+  }
+}
@@ -1,11 +1,18 @@
+use std::path::Path;
+use std::str::FromStr as _;
+
+use anyhow::Context;
 use serde::Deserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
 
+use crate::decoders::metadata_address;
 use crate::lumos_context::LumosContext;
 use crate::traits::Pull;
 use crate::utils::clone_account;
 
 /// The account configuration definition.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct AccountConfig {
   /// The public key address of the account.
   pub address: String,
@@ -15,15 +22,57 @@ pub struct AccountConfig {
 
   /// Check if account can be modify for minting.
   pub mint: Option<bool>,
+
+  /// Check if the Metaplex metadata account of the mint should be cloned too.
+  pub clone_metadata: Option<bool>,
+}
+
+/// Implementation of the account configuration.
+impl AccountConfig {
+  /// Clone the account, and its Metaplex metadata account if configured.
+  /// # Arguments
+  /// * `context` - The Lumos context.
+  /// * `update` - Whether to clone the accounts even if they're cached.
+  pub fn clone_accounts(&self, context: &LumosContext, update: bool) -> anyhow::Result<()> {
+    clone_account(context, &self.address, update, self.mint.unwrap_or(false))?;
+
+    if self.clone_metadata.unwrap_or(false) {
+      let mint = Pubkey::from_str(&self.address)?;
+      let metadata = metadata_address(&mint);
+      let cached = Path::new(&context.account_cache_dir()?)
+        .join(format!("{metadata}.json"))
+        .exists();
+
+      // Mints without Metaplex metadata, like Token-2022 mints using the
+      // metadata extension, only get a warning.
+      if (update || !cached) && !account_exists(context, &metadata)? {
+        let msg = format!("Mint {mint} has no Metaplex metadata account {metadata}, not cloning it");
+        log::warn!("{msg}");
+        let _ = context.pb.println(format!("Warning: {msg}."));
+        return Ok(());
+      }
+
+      clone_account(context, &metadata.to_string(), update, false)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Check if an account exists on the configured cluster.
+fn account_exists(context: &LumosContext, address: &Pubkey) -> anyhow::Result<bool> {
+  let client = RpcClient::new(context.rpc_endpoint());
+  let account = client
+    .get_account_with_commitment(address, client.commitment())
+    .with_context(|| format!("Failed to fetch account: {address}"))?;
+  Ok(account.value.is_some())
 }
 
 /// An implementation of the account configuration.
 impl Pull for AccountConfig {
   /// Pulls the account configuration.
   fn pull(&self, context: &LumosContext) -> anyhow::Result<()> {
-    let update = self.update.unwrap_or(false);
-    let mint = self.mint.unwrap_or(false);
-    clone_account(context, &self.address, update, mint)
+    self.clone_accounts(context, self.update.unwrap_or(false))
   }
 
   /// Get the address of the account.
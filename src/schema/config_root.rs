@@ -1,18 +1,28 @@
 use std::fs;
-use std::path::Path;
+use std::path::{
+  Path,
+  PathBuf,
+};
 
 use hashbrown::HashMap;
 use serde::Deserialize;
+use sha2::{
+  Digest as _,
+  Sha256,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr as _;
 
 use super::{
   AccountConfig,
   GeneralConfig,
+  HooksConfig,
   ProgramConfig,
 };
 
 /// ConfigRoot is a struct that holds the configuration of the lumos-svm
 /// program.
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Default, Clone)]
 pub struct ConfigRoot {
   /// General configuration
   pub general: GeneralConfig,
@@ -20,6 +30,12 @@ pub struct ConfigRoot {
   pub account: HashMap<String, AccountConfig>,
   /// List of svm programs
   pub program: HashMap<String, ProgramConfig>,
+  /// Hooks to run around the validator lifecycle
+  #[serde(default)]
+  pub hooks: HooksConfig,
+  /// The directory of the config file, set by `from_file`
+  #[serde(skip)]
+  pub config_dir: Option<PathBuf>,
 }
 
 /// Implementation of ConfigRoot.
@@ -32,8 +48,179 @@ impl ConfigRoot {
     }
 
     let contents = fs::read_to_string(filepath)?;
-    let root: Self = toml::from_str(&contents)?;
+    let mut root: Self = toml::from_str(&contents)?;
+    root.config_dir = filepath.parent().map(Path::to_path_buf);
 
     Ok(root)
   }
+
+  /// Resolve a configured account or program name to its address.
+  /// Values that are not a configured name are returned as is.
+  pub fn resolve_address<'a>(&'a self, value: &'a str) -> &'a str {
+    if let Some(account) = self.account.get(value) {
+      return &account.address;
+    }
+
+    if let Some(program) = self.program.get(value) {
+      return &program.address;
+    }
+
+    value
+  }
+
+  /// Get the addresses of all configured accounts and programs by config name.
+  /// Entries with an invalid address are skipped, and a program shadows an
+  /// account with the same name.
+  pub fn addresses(&self) -> HashMap<String, Pubkey> {
+    let accounts = self
+      .account
+      .iter()
+      .map(|(name, account)| (name, &account.address));
+    let programs = self
+      .program
+      .iter()
+      .map(|(name, program)| (name, &program.address));
+
+    accounts
+      .chain(programs)
+      .filter_map(|(name, address)| Some((name.clone(), Pubkey::from_str(address).ok()?)))
+      .collect()
+  }
+
+  /// Create a fingerprint of the configured accounts and programs.
+  /// The fingerprint is used to detect if a persisted ledger was created
+  /// from a different set of accounts and programs, or from a different
+  /// build of a local program.
+  pub fn fingerprint(&self) -> String {
+    let accounts = self.account.iter().map(|(name, account)| {
+      let line = format!(
+        "account.{} {} mint={}",
+        name,
+        account.address,
+        account.mint.unwrap_or(false)
+      );
+
+      // Only set when enabled, so existing ledgers keep their fingerprint.
+      match account.clone_metadata.unwrap_or(false) {
+        true => format!("{line} metadata=true"),
+        false => line,
+      }
+    });
+
+    let programs = self.program.iter().map(|(name, program)| {
+      let line = format!(
+        "program.{} {} authority={}",
+        name,
+        program.address,
+        program.authority.as_deref().unwrap_or("none")
+      );
+
+      // A kept ledger ignores the programs passed at genesis, so a rebuilt
+      // local binary has to reset it.
+      match &program.path {
+        Some(path) => format!("{line} path={path} sha256={}", file_digest(Path::new(path))),
+        None => line,
+      }
+    });
+
+    let mut lines: Vec<String> = accounts.chain(programs).collect();
+    lines.sort();
+    lines.join("\n")
+  }
+}
+
+/// Get the hex encoded SHA-256 digest of a file, or `missing` if it can't be read.
+fn file_digest(path: &Path) -> String {
+  match fs::read(path) {
+    Ok(contents) => Sha256::digest(contents)
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect(),
+    Err(_) => "missing".into(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_should_change_fingerprint_when_entries_change() -> anyhow::Result<()> {
+    let config = |mint: bool| {
+      toml::from_str::<ConfigRoot>(&format!(
+        r#"
+        [general]
+        rpc_endpoint = "http://localhost:8899"
+
+        [account.usdc]
+        address = "AKEWE7Bgh87GPp171b4cJPSSZfmZwQ3KaqYqXoKLNAEE"
+        mint = {mint}
+
+        [program]
+        "#
+      ))
+    };
+    let (root, changed) = (config(false)?, config(true)?);
+
+    assert_eq!(root.fingerprint(), root.fingerprint());
+    assert_ne!(root.fingerprint(), changed.fingerprint());
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_change_fingerprint_when_local_program_is_rebuilt() -> anyhow::Result<()> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let program = temp_dir.path().join("program.so");
+    fs::write(&program, "elf")?;
+
+    let root: ConfigRoot = toml::from_str(&format!(
+      r#"
+      [general]
+      rpc_endpoint = "http://localhost:8899"
+
+      [account]
+
+      [program.local]
+      address = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"
+      path = {program:?}
+      "#
+    ))?;
+
+    let fingerprint = root.fingerprint();
+    assert_eq!(root.fingerprint(), fingerprint);
+
+    fs::write(&program, "rebuilt elf")?;
+    assert_ne!(root.fingerprint(), fingerprint);
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_resolve_config_names_to_addresses() -> anyhow::Result<()> {
+    let root: ConfigRoot = toml::from_str(
+      r#"
+      [general]
+      rpc_endpoint = "http://localhost:8899"
+
+      [account.usdc]
+      address = "AKEWE7Bgh87GPp171b4cJPSSZfmZwQ3KaqYqXoKLNAEE"
+
+      [program.orca_whirlpool]
+      address = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"
+      "#,
+    )?;
+
+    assert_eq!(
+      root.resolve_address("usdc"),
+      "AKEWE7Bgh87GPp171b4cJPSSZfmZwQ3KaqYqXoKLNAEE"
+    );
+    assert_eq!(
+      root.resolve_address("orca_whirlpool"),
+      "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"
+    );
+    assert_eq!(
+      root.resolve_address("GU7NS9xCwgNPiAdJ69iusFrRfawjDDPjeMBovhV1d4kn"),
+      "GU7NS9xCwgNPiAdJ69iusFrRfawjDDPjeMBovhV1d4kn"
+    );
+    Ok(())
+  }
 }
@@ -1,7 +1,7 @@
 use serde::Deserialize;
 
 /// The general configuration for the program.
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Default, Clone)]
 pub struct GeneralConfig {
   /// The RPC endpoint to use.
   pub rpc_endpoint: String,
@@ -12,8 +12,35 @@ pub struct GeneralConfig {
   /// Validator ledger directory.
   pub ledger_dir: Option<String>,
 
+  /// Snapshot directory where saved ledgers and account dumps are kept.
+  pub snapshot_dir: Option<String>,
+
+  /// Maximum size in bytes of the validator log file before it is rotated.
+  pub log_max_size: Option<u64>,
+
+  /// Number of rotated validator log files to keep.
+  pub log_retention: Option<usize>,
+
+  /// The backend running the cloned state, defaults to `validator`.
+  pub backend: Option<Backend>,
+
+  /// Reset the validator ledger on every run.
+  /// Set to false to resume an existing ledger, defaults to true.
+  pub reset: Option<bool>,
+
   /// The mint authority.
   /// This is the public key of the mint authority that will
   /// be use to override the tokens mint authority.
   pub mint_authority: Option<String>,
 }
+
+/// The backend running the cloned accounts and programs.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+  /// Spawn a `solana-test-validator` process.
+  #[default]
+  Validator,
+  /// Load the state into an in-process SVM, requires the `inprocess` feature.
+  Inprocess,
+}
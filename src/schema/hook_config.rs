@@ -0,0 +1,46 @@
+use serde::Deserialize;
+
+/// The hooks configuration.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct HooksConfig {
+  /// Hooks that run once the validator is ready.
+  #[serde(default)]
+  pub post_start: Vec<HookConfig>,
+}
+
+/// The hook configuration definition.
+/// A hook either runs a shell command or submits an instruction.
+#[derive(Debug, Deserialize, Clone)]
+pub struct HookConfig {
+  /// The shell command to run.
+  /// The connection details, like `LUMOS_RPC_URL` and `LUMOS_WS_URL`, are exported to it.
+  pub command: Option<String>,
+
+  /// The program to invoke, a config name or an address.
+  pub program: Option<String>,
+
+  /// The accounts passed to the instruction.
+  #[serde(default)]
+  pub accounts: Vec<HookAccountConfig>,
+
+  /// The base64 encoded instruction data.
+  pub data: Option<String>,
+
+  /// The keypair file that pays for and signs the transaction.
+  /// Defaults to the Solana CLI keypair.
+  pub keypair: Option<String>,
+}
+
+/// The account passed to a hook instruction.
+#[derive(Debug, Deserialize, Clone)]
+pub struct HookAccountConfig {
+  /// The public key address of the account, or a config name.
+  pub address: String,
+
+  /// Check if the account signs the transaction.
+  /// Only the hook keypair can sign.
+  pub signer: Option<bool>,
+
+  /// Check if the account is writable.
+  pub writable: Option<bool>,
+}
@@ -1,9 +1,18 @@
 mod account_config;
 mod config_root;
 mod general_config;
+mod hook_config;
 mod program_config;
 
 pub use account_config::AccountConfig;
 pub use config_root::ConfigRoot;
-pub use general_config::GeneralConfig;
+pub use general_config::{
+  Backend,
+  GeneralConfig,
+};
+pub use hook_config::{
+  HookAccountConfig,
+  HookConfig,
+  HooksConfig,
+};
 pub use program_config::ProgramConfig;
@@ -1,11 +1,15 @@
 use serde::Deserialize;
+use std::path::{
+  Path,
+  PathBuf,
+};
 
 use crate::lumos_context::LumosContext;
 use crate::traits::Pull;
 use crate::utils::clone_program;
 
 /// The program configuration definition.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct ProgramConfig {
   /// The public key address of the program.
   pub address: String,
@@ -15,11 +19,34 @@ pub struct ProgramConfig {
 
   /// Check if the program should be updated.
   pub update: Option<bool>,
+
+  /// Path to a locally built program binary.
+  /// If set, the program is loaded from this path instead of being cloned.
+  pub path: Option<String>,
+}
+
+/// Implementation of the program configuration.
+impl ProgramConfig {
+  /// Get the path of the program binary loaded into the validator.
+  /// This is the local path if set, otherwise the cloned program in the cache.
+  pub fn program_file(&self, context: &LumosContext) -> anyhow::Result<PathBuf> {
+    if let Some(path) = &self.path {
+      return Ok(PathBuf::from(path));
+    }
+
+    let cache_dir: &str = &context.program_cache_dir()?;
+    Ok(Path::new(cache_dir).join(format!("{}.so", self.address)))
+  }
 }
 
 impl Pull for ProgramConfig {
   /// Pull the program.
   fn pull(&self, context: &LumosContext) -> anyhow::Result<()> {
+    // Local programs are not cloned.
+    if self.path.is_some() {
+      return Ok(());
+    }
+
     let update = self.update.unwrap_or(false);
     clone_program(context, &self.address, update)
   }
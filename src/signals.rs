@@ -0,0 +1,212 @@
+use anyhow::Context;
+use signal_hook::SigId;
+use signal_hook::consts::{
+  SIGINT,
+  SIGTERM,
+};
+use std::process::{
+  Child,
+  ExitStatus,
+};
+use std::sync::Arc;
+use std::sync::atomic::{
+  AtomicUsize,
+  Ordering,
+};
+use std::time::{
+  Duration,
+  Instant,
+};
+use std::{
+  fmt,
+  thread,
+};
+
+/// The grace period given to the validator to shut down after a signal
+/// before it is killed.
+pub const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// The interval between checks of the validator process.
+const WAIT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How the managed validator exited.
+#[derive(Debug)]
+pub enum ValidatorExit {
+  /// The validator was stopped by a forwarded signal.
+  /// `killed` is set if it didn't exit within the grace period.
+  Stopped { signal: i32, killed: bool },
+  /// The validator exited on its own.
+  Exited(ExitStatus),
+}
+
+/// Implementation of ValidatorExit.
+impl ValidatorExit {
+  /// Check if the validator crashed, i.e. exited on its own with a failure.
+  pub fn is_crash(&self) -> bool {
+    matches!(self, Self::Exited(status) if !status.success())
+  }
+
+  /// Get the exit code to report for this exit.
+  /// A stopped validator follows the shell convention of `128 + signal`.
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      Self::Stopped { signal, .. } => 128 + signal,
+      Self::Exited(status) if status.success() => 0,
+      Self::Exited(_) => 1,
+    }
+  }
+}
+
+/// Display implementation of ValidatorExit.
+impl fmt::Display for ValidatorExit {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Stopped {
+        signal,
+        killed: false,
+      } => write!(f, "stopped by {}", signal_name(*signal)),
+      Self::Stopped { signal, killed: true } => write!(
+        f,
+        "killed after not stopping within {}s of {}",
+        SHUTDOWN_GRACE_PERIOD.as_secs(),
+        signal_name(*signal)
+      ),
+      Self::Exited(status) if status.success() => write!(f, "exited"),
+      Self::Exited(status) => write!(f, "crashed with {status}"),
+    }
+  }
+}
+
+/// ShutdownSignals catches SIGINT and SIGTERM so they can be forwarded to the
+/// validator instead of terminating lumos-svm. The handlers are removed on drop.
+pub struct ShutdownSignals {
+  received: Arc<AtomicUsize>,
+  ids: Vec<SigId>,
+}
+
+/// Implementation of ShutdownSignals.
+impl ShutdownSignals {
+  /// Register the signal handlers.
+  pub fn register() -> anyhow::Result<Self> {
+    let received = Arc::new(AtomicUsize::new(0));
+    let ids = [SIGINT, SIGTERM]
+      .iter()
+      .map(|signal| signal_hook::flag::register_usize(*signal, received.clone(), *signal as usize))
+      .collect::<Result<Vec<_>, _>>()
+      .with_context(|| "Failed to register signal handlers")?;
+
+    Ok(Self { received, ids })
+  }
+
+  /// Get the last signal received, if any.
+  pub fn received(&self) -> Option<i32> {
+    match self.received.load(Ordering::SeqCst) {
+      0 => None,
+      signal => Some(signal as i32),
+    }
+  }
+}
+
+/// Drop implementation of ShutdownSignals.
+impl Drop for ShutdownSignals {
+  fn drop(&mut self) {
+    for id in self.ids.drain(..) {
+      signal_hook::low_level::unregister(id);
+    }
+  }
+}
+
+/// Wait for the validator to exit, forwarding any shutdown signal to it.
+/// # Arguments
+/// * `child` - The validator process.
+/// * `signals` - The registered shutdown signals.
+pub fn wait_forwarding_signals(
+  child: &mut Child,
+  signals: &ShutdownSignals,
+) -> anyhow::Result<ValidatorExit> {
+  loop {
+    if let Some(status) = child.try_wait()? {
+      return Ok(ValidatorExit::Exited(status));
+    }
+
+    if let Some(signal) = signals.received() {
+      let killed = stop_child(child, signal)?;
+      return Ok(ValidatorExit::Stopped { signal, killed });
+    }
+
+    thread::sleep(WAIT_INTERVAL);
+  }
+}
+
+/// Stop a child process by sending it a signal, and kill it if it doesn't exit
+/// within the grace period.
+/// Returns true if the child had to be killed.
+/// # Arguments
+/// * `child` - The process to stop.
+/// * `signal` - The signal to send.
+pub fn stop_child(child: &mut Child, signal: i32) -> anyhow::Result<bool> {
+  if child.try_wait()?.is_some() {
+    return Ok(false);
+  }
+
+  send_signal(child, signal)?;
+
+  let started = Instant::now();
+  while started.elapsed() < SHUTDOWN_GRACE_PERIOD {
+    if child.try_wait()?.is_some() {
+      return Ok(false);
+    }
+
+    thread::sleep(WAIT_INTERVAL);
+  }
+
+  child.kill()?;
+  child.wait()?;
+  Ok(true)
+}
+
+/// Send a signal to a child process.
+fn send_signal(child: &Child, signal: i32) -> anyhow::Result<()> {
+  let pid = libc::pid_t::try_from(child.id())?;
+
+  // SAFETY: `kill` has no memory safety requirements and the pid belongs to
+  // a child that has not been reaped yet.
+  let result = unsafe { libc::kill(pid, signal) };
+  if result != 0 {
+    let err = std::io::Error::last_os_error();
+    anyhow::bail!("Failed to send {} to validator: {err}", signal_name(signal));
+  }
+
+  Ok(())
+}
+
+/// Get the name of a shutdown signal.
+fn signal_name(signal: i32) -> String {
+  match signal {
+    SIGINT => "SIGINT".into(),
+    SIGTERM => "SIGTERM".into(),
+    _ => format!("signal {signal}"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::process::Command;
+
+  #[test]
+  fn it_should_stop_child_with_signal() -> anyhow::Result<()> {
+    let mut child = Command::new("sleep").arg("30").spawn()?;
+
+    let killed = stop_child(&mut child, SIGTERM)?;
+    assert!(!killed);
+
+    let exit = ValidatorExit::Stopped {
+      signal: SIGTERM,
+      killed,
+    };
+    assert_eq!(exit.exit_code(), 143);
+    assert!(!exit.is_crash());
+    Ok(())
+  }
+}
@@ -0,0 +1,384 @@
+use anyhow::Context;
+use base64::prelude::*;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{
+  RpcSimulateTransactionAccountsConfig,
+  RpcSimulateTransactionConfig,
+};
+use solana_sdk::account::Account;
+use solana_sdk::address_lookup_table::state::AddressLookupTable;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::{
+  TransactionError,
+  VersionedTransaction,
+};
+use std::fs;
+use std::path::Path;
+
+use crate::lumos_validator::LumosValidator;
+use crate::schema::{
+  Backend,
+  ConfigRoot,
+};
+
+/// The result of a simulated transaction.
+#[derive(Debug)]
+pub struct SimulationResult {
+  /// The accounts the simulation was requested with, in order.
+  pub addresses: Vec<Pubkey>,
+  /// The transaction error, if it failed.
+  pub err: Option<TransactionError>,
+  /// The program logs.
+  pub logs: Vec<String>,
+  /// The compute units consumed.
+  pub units_consumed: Option<u64>,
+  /// The accounts before the simulation, in the order they were requested.
+  pub pre_accounts: Vec<Option<Account>>,
+  /// The accounts after the simulation, in the order they were requested.
+  pub post_accounts: Vec<Option<Account>>,
+}
+
+/// Simulate a transaction against a local validator.
+/// The recent blockhash is replaced and signatures are not verified, so
+/// transactions signed for another cluster can be simulated.
+/// # Arguments
+/// * `client` - The RPC client of the local validator.
+/// * `transaction` - The transaction to simulate.
+/// * `addresses` - The accounts to return the state of, before and after.
+pub fn simulate_transaction(
+  client: &RpcClient,
+  transaction: &VersionedTransaction,
+  addresses: &[Pubkey],
+) -> anyhow::Result<SimulationResult> {
+  let pre_accounts = client
+    .get_multiple_accounts(addresses)
+    .with_context(|| "Failed to get the accounts before the simulation")?;
+
+  let config = RpcSimulateTransactionConfig {
+    sig_verify: false,
+    replace_recent_blockhash: true,
+    accounts: Some(RpcSimulateTransactionAccountsConfig {
+      encoding: Some(UiAccountEncoding::Base64),
+      addresses: addresses.iter().map(|address| address.to_string()).collect(),
+    }),
+    ..RpcSimulateTransactionConfig::default()
+  };
+
+  let result = client
+    .simulate_transaction_with_config(transaction, config)
+    .with_context(|| "Failed to simulate transaction")?
+    .value;
+
+  // Failed simulations don't return the accounts, so nothing changed.
+  let post_accounts = match result.accounts {
+    Some(accounts) => accounts
+      .into_iter()
+      .map(|account| account.and_then(|account| account.decode::<Account>()))
+      .collect(),
+    None => pre_accounts.clone(),
+  };
+
+  Ok(SimulationResult {
+    addresses: addresses.to_vec(),
+    err: result.err,
+    logs: result.logs.unwrap_or_default(),
+    units_consumed: result.units_consumed,
+    pre_accounts,
+    post_accounts,
+  })
+}
+
+/// The change of an account made by a simulated transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountChange {
+  /// The account address.
+  pub address: Pubkey,
+  /// The lamports difference.
+  pub lamports: i128,
+  /// The owner before the simulation, if it existed.
+  pub pre_owner: Option<Pubkey>,
+  /// The owner after the simulation, if it still exists.
+  pub post_owner: Option<Pubkey>,
+  /// The data length before and after the simulation.
+  pub data_len: (usize, usize),
+  /// Whether the account data changed.
+  pub data_changed: bool,
+}
+
+/// Implementation of AccountChange.
+impl AccountChange {
+  /// Check if the account was created by the transaction.
+  pub fn created(&self) -> bool {
+    self.pre_owner.is_none() && self.post_owner.is_some()
+  }
+
+  /// Check if the account was closed by the transaction.
+  pub fn closed(&self) -> bool {
+    self.pre_owner.is_some() && self.post_owner.is_none()
+  }
+}
+
+/// Get the accounts changed by a simulated transaction.
+/// # Arguments
+/// * `result` - The simulation result.
+pub fn account_changes(result: &SimulationResult) -> Vec<AccountChange> {
+  result
+    .addresses
+    .iter()
+    .zip(result.pre_accounts.iter().zip(result.post_accounts.iter()))
+    .filter(|(_, (pre, post))| pre != post)
+    .map(|(address, (pre, post))| {
+      let lamports =
+        |account: &Option<Account>| account.as_ref().map_or(0, |account| account.lamports as i128);
+      let data = |account: &Option<Account>| {
+        account
+          .as_ref()
+          .map(|account| account.data.clone())
+          .unwrap_or_default()
+      };
+
+      let (pre_data, post_data) = (data(pre), data(post));
+
+      AccountChange {
+        address: *address,
+        lamports: lamports(post) - lamports(pre),
+        pre_owner: pre.as_ref().map(|account| account.owner),
+        post_owner: post.as_ref().map(|account| account.owner),
+        data_len: (pre_data.len(), post_data.len()),
+        data_changed: pre_data != post_data,
+      }
+    })
+    .collect()
+}
+
+/// Get the accounts loaded by a transaction, the static account keys followed
+/// by the writable and then the readonly addresses of its lookup tables.
+/// # Arguments
+/// * `client` - The RPC client to get the address lookup tables from.
+/// * `transaction` - The transaction.
+pub fn transaction_addresses(
+  client: &RpcClient,
+  transaction: &VersionedTransaction,
+) -> anyhow::Result<Vec<Pubkey>> {
+  let mut addresses = transaction.message.static_account_keys().to_vec();
+  let Some(lookups) = transaction.message.address_table_lookups() else {
+    return Ok(addresses);
+  };
+
+  let (mut writable, mut readonly) = (vec![], vec![]);
+  for lookup in lookups {
+    let table_address = lookup.account_key;
+    let account = client
+      .get_account(&table_address)
+      .with_context(|| format!("Failed to get address lookup table: {table_address}"))?;
+    let table = AddressLookupTable::deserialize(&account.data)
+      .map_err(|err| anyhow::anyhow!("Failed to deserialize address lookup table {table_address}: {err}"))?;
+    let resolve =
+      |indexes: &[u8]| {
+        indexes
+          .iter()
+          .map(|index| {
+            table.addresses.get(*index as usize).copied().with_context(|| {
+              format!("Index {index} is out of range of address lookup table {table_address}")
+            })
+          })
+          .collect::<anyhow::Result<Vec<_>>>()
+      };
+    writable.extend(resolve(&lookup.writable_indexes)?);
+    readonly.extend(resolve(&lookup.readonly_indexes)?);
+  }
+
+  addresses.extend(writable);
+  addresses.extend(readonly);
+  Ok(addresses)
+}
+
+/// Decode a serialized transaction, legacy or versioned.
+/// # Arguments
+/// * `input` - The base64 encoded transaction, or a file holding it either
+///   base64 encoded or as raw bytes.
+pub fn decode_transaction(input: &str) -> anyhow::Result<VersionedTransaction> {
+  let path = Path::new(input);
+  let bytes = match path.is_file() {
+    true => {
+      let contents = fs::read(path).with_context(|| format!("Failed to read transaction file: {path:?}"))?;
+      let text = String::from_utf8_lossy(&contents);
+      match BASE64_STANDARD.decode(text.trim()) {
+        Ok(bytes) => bytes,
+        Err(_) => contents,
+      }
+    },
+    false => BASE64_STANDARD
+      .decode(input.trim())
+      .with_context(|| "Transaction is neither a file nor base64 encoded")?,
+  };
+
+  bincode::deserialize(&bytes).with_context(|| "Failed to deserialize transaction")
+}
+
+/// Boot the configured state and simulate a transaction against it.
+/// The state is served by a temporary validator, or an in-process SVM with
+/// `general.backend = "inprocess"`.
+/// # Arguments
+/// * `config` - The config to boot.
+/// * `transaction` - The transaction to simulate.
+/// * `verbose` - Mirror the validator output to the terminal.
+pub fn simulate_with_config(
+  config: ConfigRoot,
+  transaction: &VersionedTransaction,
+  verbose: bool,
+) -> anyhow::Result<SimulationResult> {
+  match config.general.backend.unwrap_or_default() {
+    Backend::Validator => {
+      let validator = LumosValidator::builder(config).verbose(verbose).spawn()?;
+      let client = validator.rpc_client();
+      simulate_transaction(
+        &client,
+        transaction,
+        &transaction_addresses(&client, transaction)?,
+      )
+    },
+    Backend::Inprocess => simulate_inprocess(config, transaction),
+  }
+}
+
+/// Simulate a transaction against an in-process SVM loaded with the config.
+#[cfg(feature = "inprocess")]
+fn simulate_inprocess(
+  config: ConfigRoot,
+  transaction: &VersionedTransaction,
+) -> anyhow::Result<SimulationResult> {
+  let svm = crate::inprocess::InProcessSvm::load(config)?;
+  let server = crate::inprocess_rpc::serve_inprocess(svm, 0)?;
+  let client = RpcClient::new(server.url());
+  simulate_transaction(
+    &client,
+    transaction,
+    &transaction_addresses(&client, transaction)?,
+  )
+}
+
+/// Fallback when the in-process backend is not compiled in.
+#[cfg(not(feature = "inprocess"))]
+fn simulate_inprocess(
+  _config: ConfigRoot,
+  _transaction: &VersionedTransaction,
+) -> anyhow::Result<SimulationResult> {
+  anyhow::bail!("The in-process backend requires building with the `inprocess` feature")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mock_rpc::{
+    MockRpcFixtures,
+    serve_mock_rpc,
+  };
+  use crate::test_support::account;
+  use assert_fs::TempDir;
+  use solana_sdk::address_lookup_table;
+  use solana_sdk::address_lookup_table::state::LookupTableMeta;
+  use solana_sdk::hash::Hash;
+  use solana_sdk::instruction::Instruction;
+  use solana_sdk::message::{
+    Message,
+    VersionedMessage,
+    v0,
+  };
+  use solana_sdk::transaction::Transaction;
+
+  #[test]
+  fn it_should_decode_base64_and_file_transactions() -> anyhow::Result<()> {
+    let payer = Pubkey::new_unique();
+    let instruction = Instruction::new_with_bytes(Pubkey::new_unique(), &[1, 2, 3], vec![]);
+    let message = Message::new_with_blockhash(&[instruction], Some(&payer), &Hash::new_unique());
+    let transaction = VersionedTransaction::from(Transaction::new_unsigned(message));
+    let bytes = bincode::serialize(&transaction)?;
+
+    assert_eq!(decode_transaction(&BASE64_STANDARD.encode(&bytes))?, transaction);
+
+    let temp_dir = TempDir::new()?;
+    let raw_file = temp_dir.path().join("tx.bin");
+    fs::write(&raw_file, &bytes)?;
+    assert_eq!(decode_transaction(raw_file.to_str().unwrap())?, transaction);
+
+    let base64_file = temp_dir.path().join("tx.b64");
+    fs::write(&base64_file, format!("{}\n", BASE64_STANDARD.encode(&bytes)))?;
+    assert_eq!(decode_transaction(base64_file.to_str().unwrap())?, transaction);
+
+    assert!(decode_transaction("not a transaction").is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_list_account_changes() -> anyhow::Result<()> {
+    let account = |lamports: u64, data: Vec<u8>| Account {
+      lamports,
+      ..account(Pubkey::new_unique(), data)
+    };
+    let unchanged = account(10, vec![]);
+    let payer = account(1_000, vec![]);
+    let mut paid = payer.clone();
+    paid.lamports = 900;
+    let created = account(100, vec![0; 8]);
+
+    let addresses = vec![Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let result = SimulationResult {
+      addresses: addresses.clone(),
+      err: None,
+      logs: vec![],
+      units_consumed: None,
+      pre_accounts: vec![Some(unchanged.clone()), Some(payer), None],
+      post_accounts: vec![Some(unchanged), Some(paid), Some(created)],
+    };
+
+    let changes = account_changes(&result);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].address, addresses[1]);
+    assert_eq!(changes[0].lamports, -100);
+    assert!(!changes[0].data_changed);
+    assert!(changes[1].created());
+    assert_eq!(changes[1].data_len, (0, 8));
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_resolve_lookup_table_addresses() -> anyhow::Result<()> {
+    let (payer, table_address) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let loaded = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let table = AddressLookupTable {
+      meta: LookupTableMeta::default(),
+      addresses: loaded.to_vec().into(),
+    };
+
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_account(
+      table_address,
+      account(address_lookup_table::program::ID, table.serialize_for_tests()?),
+    );
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let client = RpcClient::new(server.url());
+
+    let lookup = |writable_indexes: Vec<u8>| v0::MessageAddressTableLookup {
+      account_key: table_address,
+      writable_indexes,
+      readonly_indexes: vec![0],
+    };
+    let transaction = |lookups| VersionedTransaction {
+      signatures: vec![],
+      message: VersionedMessage::V0(v0::Message {
+        account_keys: vec![payer],
+        address_table_lookups: lookups,
+        ..v0::Message::default()
+      }),
+    };
+
+    assert_eq!(
+      transaction_addresses(&client, &transaction(vec![lookup(vec![2])]))?,
+      vec![payer, loaded[2], loaded[0]]
+    );
+    assert!(transaction_addresses(&client, &transaction(vec![lookup(vec![3])])).is_err());
+    Ok(())
+  }
+}
@@ -0,0 +1,314 @@
+use anyhow::Context;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{
+  Signer as _,
+  read_keypair_file,
+};
+use solana_sdk::system_program;
+use std::fs;
+use std::path::{
+  Path,
+  PathBuf,
+};
+use std::str::FromStr as _;
+
+use crate::archive::{
+  pack_tar_gz,
+  unpack_tar_gz,
+};
+use crate::dump::fetch_accounts;
+use crate::lumos_context::LumosContext;
+use crate::utils::{
+  copy_dir_all,
+  is_ledger_dir,
+  is_ledger_locked,
+  is_validator_running,
+  write_account_json,
+};
+
+/// The file inside a snapshot that holds the archived ledger.
+const LEDGER_ARCHIVE: &str = "ledger.tar.gz";
+
+/// The keypair of the faucet, written into the ledger by the test validator.
+const FAUCET_KEYPAIR_FILE: &str = "faucet-keypair.json";
+
+/// The directory inside a snapshot that holds the dumped accounts.
+const ACCOUNTS_SNAPSHOT_DIR: &str = "accounts";
+
+/// The kind of state kept in a snapshot.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SnapshotKind {
+  /// An archive of the ledger directory of a stopped validator.
+  Ledger,
+  /// The accounts dumped from a running validator.
+  Accounts(usize),
+}
+
+/// Save a named snapshot of the local state.
+/// If the local validator is running, all non-default accounts are dumped
+/// into an account dir, otherwise the ledger directory is archived.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `name` - The name of the snapshot.
+/// * `local_rpc_endpoint` - The RPC endpoint of the local validator.
+/// * `force` - Overwrite the snapshot if it already exists.
+pub fn save_snapshot(
+  context: &LumosContext,
+  name: &str,
+  local_rpc_endpoint: &str,
+  force: bool,
+) -> anyhow::Result<SnapshotKind> {
+  let snapshot_dir = snapshot_path(context, name)?;
+  if snapshot_dir.exists() {
+    if !force {
+      anyhow::bail!("Snapshot already exists: {name}");
+    }
+
+    fs::remove_dir_all(&snapshot_dir)?;
+  }
+
+  if is_validator_running(local_rpc_endpoint) {
+    let client = RpcClient::new(local_rpc_endpoint);
+    let out_dir = snapshot_dir.join(ACCOUNTS_SNAPSHOT_DIR);
+    let count = dump_local_accounts(context, &client, &out_dir)?;
+    return Ok(SnapshotKind::Accounts(count));
+  }
+
+  let ledger_dir: &str = &context.ledger_dir();
+  let ledger_dir = Path::new(ledger_dir);
+  if !ledger_dir.exists() {
+    anyhow::bail!("Ledger directory not found: {ledger_dir:?}");
+  }
+
+  // A validator on another endpoint is still writing the ledger.
+  if is_ledger_locked(ledger_dir)? {
+    anyhow::bail!("Stop the validator before saving the ledger {ledger_dir:?}, or pass its RPC endpoint");
+  }
+
+  pack_tar_gz(ledger_dir, &snapshot_dir.join(LEDGER_ARCHIVE))
+    .with_context(|| format!("Failed to archive ledger into snapshot: {name}"))?;
+
+  Ok(SnapshotKind::Ledger)
+}
+
+/// Restore a named snapshot of the local state.
+/// A ledger snapshot replaces the ledger directory and requires the validator
+/// to be stopped. An account snapshot replaces the restored accounts, which are
+/// loaded on top of the account cache on the next run, until discarded.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `name` - The name of the snapshot.
+/// * `local_rpc_endpoint` - The RPC endpoint of the local validator.
+pub fn restore_snapshot(
+  context: &LumosContext,
+  name: &str,
+  local_rpc_endpoint: &str,
+) -> anyhow::Result<SnapshotKind> {
+  let snapshot_dir = snapshot_path(context, name)?;
+  if !snapshot_dir.exists() {
+    anyhow::bail!("Snapshot not found: {name}");
+  }
+
+  let ledger_snapshot = snapshot_dir.join(LEDGER_ARCHIVE);
+  if ledger_snapshot.exists() {
+    if is_validator_running(local_rpc_endpoint) {
+      anyhow::bail!("Stop the validator before restoring the ledger snapshot: {name}");
+    }
+
+    let ledger_dir: &str = &context.ledger_dir();
+    let ledger_dir = Path::new(ledger_dir);
+    if ledger_dir.exists() {
+      if is_ledger_locked(ledger_dir)? {
+        anyhow::bail!("Stop the validator before restoring the ledger snapshot: {name}");
+      }
+
+      if !is_ledger_dir(ledger_dir)? {
+        anyhow::bail!("Refusing to replace {ledger_dir:?}, it is not empty and doesn't look like a ledger");
+      }
+
+      fs::remove_dir_all(ledger_dir)?;
+    }
+
+    unpack_tar_gz(&ledger_snapshot, ledger_dir)
+      .with_context(|| format!("Failed to restore ledger from snapshot: {name}"))?;
+
+    return Ok(SnapshotKind::Ledger);
+  }
+
+  let accounts_snapshot = snapshot_dir.join(ACCOUNTS_SNAPSHOT_DIR);
+  if !accounts_snapshot.exists() {
+    anyhow::bail!("Snapshot is empty: {name}");
+  }
+
+  discard_restored_accounts(context)?;
+  let restored_dir: &str = &context.restored_account_dir()?;
+  copy_dir_all(&accounts_snapshot, Path::new(restored_dir))
+    .with_context(|| format!("Failed to restore accounts from snapshot: {name}"))?;
+
+  let count = fs::read_dir(&accounts_snapshot)?.count();
+  Ok(SnapshotKind::Accounts(count))
+}
+
+/// Discard the accounts restored from a snapshot, so the next run only loads
+/// the account cache again.
+/// Returns the number of accounts discarded.
+/// # Arguments
+/// * `context` - The lumos context.
+pub fn discard_restored_accounts(context: &LumosContext) -> anyhow::Result<usize> {
+  let restored_dir: &str = &context.restored_account_dir()?;
+  let restored_dir = Path::new(restored_dir);
+  if !restored_dir.exists() {
+    return Ok(0);
+  }
+
+  let count = fs::read_dir(restored_dir)?.count();
+  fs::remove_dir_all(restored_dir)
+    .with_context(|| format!("Failed to discard restored accounts: {restored_dir:?}"))?;
+  Ok(count)
+}
+
+/// Get the path of a named snapshot.
+/// The name is restricted so it can't escape the snapshot directory.
+fn snapshot_path(context: &LumosContext, name: &str) -> anyhow::Result<PathBuf> {
+  let is_valid = !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    && !name.starts_with('.');
+
+  if !is_valid {
+    anyhow::bail!("Invalid snapshot name: {name}");
+  }
+
+  Ok(Path::new(&context.snapshot_dir()).join(name))
+}
+
+/// Dump the non-default accounts of the local validator into a directory.
+/// These are the accounts owned by the system and token programs, the accounts
+/// owned by the configured programs, and the configured accounts. The validator
+/// identity and the faucet are created by the validator on startup, so they
+/// are left out.
+/// Returns the number of accounts written.
+fn dump_local_accounts(context: &LumosContext, client: &RpcClient, out_dir: &Path) -> anyhow::Result<usize> {
+  let mut owners = vec![system_program::ID, spl_token::id(), spl_token_2022::id()];
+  for program in context.config.program.values() {
+    let owner = Pubkey::from_str(&program.address)
+      .with_context(|| format!("Invalid program address: {}", program.address))?;
+    owners.push(owner);
+  }
+
+  let addresses = context
+    .config
+    .account
+    .values()
+    .map(|account| Pubkey::from_str(&account.address))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let mut accounts = fetch_accounts(client, &owners, &addresses)?;
+  for address in validator_accounts(context, client) {
+    accounts.remove(&address);
+  }
+
+  fs::create_dir_all(out_dir)?;
+  for (address, account) in accounts.iter() {
+    write_account_json(out_dir, address, account)?;
+  }
+
+  Ok(accounts.len())
+}
+
+/// Get the accounts the validator creates in its genesis: its identity and
+/// the faucet.
+fn validator_accounts(context: &LumosContext, client: &RpcClient) -> Vec<Pubkey> {
+  let mut addresses = vec![];
+  if let Ok(identity) = client.get_identity() {
+    addresses.push(identity);
+  }
+
+  let ledger_dir: &str = &context.ledger_dir();
+  if let Ok(faucet) = read_keypair_file(Path::new(ledger_dir).join(FAUCET_KEYPAIR_FILE)) {
+    addresses.push(faucet.pubkey());
+  }
+
+  addresses
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::schema::ConfigRoot;
+  use crate::test_support::mint_account;
+  use crate::utils::{
+    LEDGER_GENESIS_FILE,
+    LEDGER_LOCK_FILE,
+    read_account_json,
+  };
+  use assert_fs::TempDir;
+  use std::os::fd::AsRawFd as _;
+  use std::sync::Arc;
+
+  #[test]
+  fn it_should_restore_accounts_without_touching_the_cache() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut config = ConfigRoot::default();
+    config.general.snapshot_dir = Some(temp_dir.path().join("snapshots").to_str().unwrap().into());
+    let cache_dir = temp_dir.path().join("cache").to_str().unwrap().to_string();
+    let context = LumosContext::new(Arc::new(config), "http://127.0.0.1:1", Some(cache_dir), false);
+
+    let (cached, restored) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let account_cache_dir = PathBuf::from(context.account_cache_dir()?);
+    write_account_json(&account_cache_dir, &cached, &mint_account(0, 6))?;
+    write_account_json(&account_cache_dir, &restored, &mint_account(0, 6))?;
+
+    let snapshot_accounts = snapshot_path(&context, "seeded")?.join(ACCOUNTS_SNAPSHOT_DIR);
+    write_account_json(&snapshot_accounts, &restored, &mint_account(1_000, 6))?;
+
+    let kind = restore_snapshot(&context, "seeded", "http://127.0.0.1:1")?;
+    assert_eq!(kind, SnapshotKind::Accounts(1));
+
+    let restored_dir = PathBuf::from(context.restored_account_dir()?);
+    assert!(restored_dir.join(format!("{restored}.json")).exists());
+    assert_eq!(
+      read_account_json(&account_cache_dir.join(format!("{restored}.json")))?.1,
+      mint_account(0, 6)
+    );
+
+    assert_eq!(discard_restored_accounts(&context)?, 1);
+    assert!(!restored_dir.exists());
+    assert!(account_cache_dir.join(format!("{cached}.json")).exists());
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_not_touch_a_locked_ledger() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let ledger_dir = temp_dir.path().join("ledger");
+    let mut config = ConfigRoot::default();
+    config.general.snapshot_dir = Some(temp_dir.path().join("snapshots").to_str().unwrap().into());
+    config.general.ledger_dir = Some(ledger_dir.to_str().unwrap().into());
+    let context = LumosContext::new(Arc::new(config), "http://127.0.0.1:1", None, false);
+
+    fs::create_dir_all(&ledger_dir)?;
+    fs::write(ledger_dir.join(LEDGER_GENESIS_FILE), "genesis")?;
+    assert_eq!(
+      save_snapshot(&context, "stopped", "http://127.0.0.1:1", false)?,
+      SnapshotKind::Ledger
+    );
+
+    // Hold the ledger lock like a validator on another port.
+    let lock = fs::File::create(ledger_dir.join(LEDGER_LOCK_FILE))?;
+    assert_eq!(unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX) }, 0);
+    assert!(save_snapshot(&context, "running", "http://127.0.0.1:1", false).is_err());
+    assert!(restore_snapshot(&context, "stopped", "http://127.0.0.1:1").is_err());
+    assert!(ledger_dir.join(LEDGER_LOCK_FILE).exists());
+
+    drop(lock);
+    fs::remove_file(ledger_dir.join(LEDGER_GENESIS_FILE))?;
+    fs::write(ledger_dir.join("notes.txt"), "keep me")?;
+    fs::remove_file(ledger_dir.join(LEDGER_LOCK_FILE))?;
+    assert!(restore_snapshot(&context, "stopped", "http://127.0.0.1:1").is_err());
+    assert!(ledger_dir.join("notes.txt").exists());
+    Ok(())
+  }
+}
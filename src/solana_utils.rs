@@ -1,5 +1,8 @@
+use serde::Serialize;
+use solana_account_decoder::parse_account_data::SplTokenAdditionalDataV2;
 use solana_account_decoder::parse_token::{
   TokenAccountType,
+  UiAccountState,
   is_known_spl_token_id,
   parse_token_v3,
 };
@@ -9,23 +12,41 @@ use solana_account_decoder::parse_token_extension::{
   UiTokenMetadata,
 };
 use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_filter::{
+  Memcmp,
+  RpcFilterType,
+};
+use solana_loader_v3_interface::state::UpgradeableLoaderState;
+use solana_sdk::account::Account;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{
+  bpf_loader,
+  bpf_loader_deprecated,
+  bpf_loader_upgradeable,
+  loader_v4,
+};
+use spl_token_2022::generic_token_account::GenericTokenAccount as _;
 use std::str::FromStr as _;
 
+use crate::traits::AccountSource;
+
 /// Get the owners of the given addresses.
+/// Invalid addresses and missing accounts are skipped.
 pub async fn get_owners<T: AsRef<str>>(rpc_endpoint: &str, addresses: &[T]) -> anyhow::Result<Vec<String>> {
-  let mut owners = Vec::with_capacity(addresses.len());
-
-  for address in addresses {
-    match get_owner(rpc_endpoint, address.as_ref()).await {
-      Ok(owner) => {
-        owners.push(owner);
-      },
-      Err(_) => continue,
-    }
-  }
+  let client = RpcClient::new(rpc_endpoint);
+  let pubkeys: Vec<Pubkey> = addresses
+    .iter()
+    .filter_map(|address| Pubkey::from_str(address.as_ref()).ok())
+    .collect();
 
-  Ok(owners)
+  let accounts = AccountSource::get_multiple_accounts(&client, &pubkeys)?;
+  Ok(
+    accounts
+      .into_iter()
+      .flatten()
+      .map(|account| account.owner.to_string())
+      .collect(),
+  )
 }
 
 /// Get the owner of the given address.
@@ -38,38 +59,135 @@ pub async fn get_owner(rpc_endpoint: &str, address: &str) -> anyhow::Result<Stri
 }
 
 /// Get the token details of the given address.
-#[derive(Debug)]
+/// Serialized in camelCase, missing values are `null`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TokenDetails {
+  /// The token program owning the mint.
   pub owner: String,
+  /// The authority allowed to mint, `null` if the supply is fixed.
   pub mint_authority: Option<String>,
+  /// The authority allowed to freeze token accounts.
   pub freeze_authority: Option<String>,
+  /// The metadata update authority, from the Token-2022 metadata or the
+  /// Metaplex metadata of classic mints.
   pub update_authority: Option<String>,
+  /// The number of decimals of the token.
   pub decimals: u8,
+  /// The raw supply, as a string since it may not fit a JSON number.
   pub supply: String,
+  /// Whether the mint is initialized.
   pub is_initialized: bool,
-  pub extensions: bool,
+  /// The Token-2022 extensions of the mint.
+  pub extension_details: Vec<UiExtension>,
+  /// The Token-2022 metadata of the mint.
   pub metadata: Option<TokenMetadata>,
 }
 
 /// Token metadata definition.
-#[derive(Debug)]
+/// Serialized in camelCase, missing values are `null`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TokenMetadata {
+  /// The authority of the metadata pointer.
   pub authority: Option<String>,
+  /// The account holding the metadata, usually the mint itself.
   pub metadata_address: Option<String>,
+  /// The authority allowed to update the metadata.
   pub update_authority: Option<String>,
+  /// The token name.
   pub name: Option<String>,
+  /// The token symbol.
   pub symbol: Option<String>,
+  /// The URI of the off-chain metadata.
   pub uri: Option<String>,
 }
 
+/// Get the token account details of the given address.
+/// Serialized in camelCase, missing values are `null`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenAccountDetails {
+  /// The token program owning the account.
+  pub program: String,
+  /// The mint of the tokens held.
+  pub mint: String,
+  /// The owner of the tokens.
+  pub owner: String,
+  /// The raw amount, as a string since it may not fit a JSON number.
+  pub amount: String,
+  /// The amount with the decimals of the mint applied.
+  pub ui_amount: String,
+  /// The number of decimals of the mint.
+  pub decimals: u8,
+  /// The delegate allowed to transfer tokens.
+  pub delegate: Option<String>,
+  /// The UI amount the delegate may transfer.
+  pub delegated_amount: Option<String>,
+  /// The authority allowed to close the account.
+  pub close_authority: Option<String>,
+  /// The account state: "initialized", "frozen" or "uninitialized".
+  pub state: String,
+  /// Whether the account holds wrapped SOL.
+  pub is_native: bool,
+  /// The Token-2022 extensions of the account.
+  pub extensions: Vec<UiExtension>,
+}
+
+/// A holder of a token, one of the largest token accounts of its mint.
+/// Serialized in camelCase, missing values are `null`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenHolder {
+  /// The token account.
+  pub address: String,
+  /// The owner of the token account, `null` if it couldn't be read.
+  pub owner: Option<String>,
+  /// The raw amount, as a string since it may not fit a JSON number.
+  pub amount: String,
+  /// The amount with the decimals of the mint applied.
+  pub ui_amount: String,
+  /// The share of the supply, in percent.
+  pub share: f64,
+}
+
+/// Get the program details of the given address.
+/// Serialized in camelCase, missing values are `null`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramDetails {
+  /// The loader owning the program.
+  pub loader: String,
+  /// The loader version, 1 to 4.
+  pub loader_version: u8,
+  /// The ProgramData account of upgradeable programs.
+  pub program_data_address: Option<String>,
+  /// The upgrade authority, `null` if the program is immutable.
+  pub upgrade_authority: Option<String>,
+  /// The slot of the last deployment.
+  pub last_deploy_slot: Option<u64>,
+  /// The size of the program binary in bytes.
+  pub elf_size: usize,
+  /// The address of the Anchor IDL account.
+  pub idl_address: String,
+  /// Whether the Anchor IDL account exists.
+  pub idl_exists: bool,
+}
+
 /// Get the token details of the given address.
 pub async fn get_token_details(rpc_endpoint: &str, address: &str) -> anyhow::Result<TokenDetails> {
   let client = RpcClient::new(rpc_endpoint);
   let pubkey =
     Pubkey::from_str(address).map_err(|e| anyhow::anyhow!("Invalid mint address format: {}", e))?;
 
+  let account = get_token_program_account(&client, &pubkey)?;
+  mint_details(&account)
+}
+
+/// Get an account owned by a token program.
+fn get_token_program_account(client: &RpcClient, pubkey: &Pubkey) -> anyhow::Result<Account> {
   let account = client
-    .get_account_with_commitment(&pubkey, client.commitment())?
+    .get_account_with_commitment(pubkey, client.commitment())?
     .value
     .ok_or_else(|| anyhow::anyhow!("Account not found"))?;
 
@@ -77,52 +195,54 @@ pub async fn get_token_details(rpc_endpoint: &str, address: &str) -> anyhow::Res
     return Err(anyhow::anyhow!("Not a token mint account"));
   }
 
+  Ok(account)
+}
+
+/// Get the token details of a mint account.
+pub fn mint_details(account: &Account) -> anyhow::Result<TokenDetails> {
   if let Ok(token_mint) = parse_token_v3(&account.data, None) {
     match token_mint {
       TokenAccountType::Mint(mint) => {
-        let extensions = !mint.extensions.is_empty();
         let mut metadata = None;
-        if extensions {
-          for ext in mint.extensions.iter() {
-            match ext {
-              UiExtension::MetadataPointer(UiMetadataPointer {
-                authority,
-                metadata_address,
-              }) => {
+        for ext in mint.extensions.iter() {
+          match ext {
+            UiExtension::MetadataPointer(UiMetadataPointer {
+              authority,
+              metadata_address,
+            }) => {
+              metadata = Some(TokenMetadata {
+                authority: authority.clone(),
+                metadata_address: metadata_address.clone(),
+                update_authority: None,
+                name: None,
+                symbol: None,
+                uri: None,
+              });
+            },
+            UiExtension::TokenMetadata(UiTokenMetadata {
+              update_authority,
+              name,
+              symbol,
+              uri,
+              ..
+            }) => {
+              if let Some(meta) = metadata.as_mut() {
+                meta.update_authority = update_authority.clone();
+                meta.name = Some(name.clone());
+                meta.symbol = Some(symbol.clone());
+                meta.uri = Some(uri.clone());
+              } else {
                 metadata = Some(TokenMetadata {
-                  authority: authority.clone(),
-                  metadata_address: metadata_address.clone(),
-                  update_authority: None,
-                  name: None,
-                  symbol: None,
-                  uri: None,
+                  authority: None,
+                  metadata_address: None,
+                  update_authority: update_authority.clone(),
+                  name: Some(name.clone()),
+                  symbol: Some(symbol.clone()),
+                  uri: Some(uri.clone()),
                 });
-              },
-              UiExtension::TokenMetadata(UiTokenMetadata {
-                update_authority,
-                name,
-                symbol,
-                uri,
-                ..
-              }) => {
-                if let Some(meta) = metadata.as_mut() {
-                  meta.update_authority = update_authority.clone();
-                  meta.name = Some(name.clone());
-                  meta.symbol = Some(symbol.clone());
-                  meta.uri = Some(uri.clone());
-                } else {
-                  metadata = Some(TokenMetadata {
-                    authority: None,
-                    metadata_address: None,
-                    update_authority: update_authority.clone(),
-                    name: Some(name.clone()),
-                    symbol: Some(symbol.clone()),
-                    uri: Some(uri.clone()),
-                  });
-                }
-              },
-              _ => continue,
-            }
+              }
+            },
+            _ => continue,
           }
         }
 
@@ -130,11 +250,13 @@ pub async fn get_token_details(rpc_endpoint: &str, address: &str) -> anyhow::Res
           owner: account.owner.to_string(),
           mint_authority: mint.mint_authority,
           freeze_authority: mint.freeze_authority,
-          update_authority: None,
+          update_authority: metadata
+            .as_ref()
+            .and_then(|metadata| metadata.update_authority.clone()),
           decimals: mint.decimals,
           supply: mint.supply,
           is_initialized: mint.is_initialized,
-          extensions,
+          extension_details: mint.extensions,
           metadata,
         })
       },
@@ -145,23 +267,358 @@ pub async fn get_token_details(rpc_endpoint: &str, address: &str) -> anyhow::Res
   }
 }
 
+/// Get the largest holders of a mint, with their share of the supply.
+/// The cluster returns at most 20 token accounts.
+/// # Arguments
+/// * `client` - The RPC client.
+/// * `mint` - The mint address.
+/// * `mint_account` - The mint account, for its supply.
+/// * `limit` - The number of holders to return.
+pub fn get_largest_holders(
+  client: &RpcClient,
+  mint: &Pubkey,
+  mint_account: &Account,
+  limit: usize,
+) -> anyhow::Result<Vec<TokenHolder>> {
+  let supply: u64 = mint_details(mint_account)?.supply.parse()?;
+
+  let mut balances = client.get_token_largest_accounts(mint)?;
+  balances.truncate(limit);
+
+  let addresses = balances
+    .iter()
+    .map(|balance| Pubkey::from_str(&balance.address))
+    .collect::<Result<Vec<_>, _>>()?;
+  let accounts = AccountSource::get_multiple_accounts(client, &addresses)?;
+
+  let holders = balances
+    .into_iter()
+    .zip(accounts)
+    .map(|(balance, account)| {
+      let owner = account
+        .as_ref()
+        .and_then(|account| spl_token_2022::state::Account::unpack_account_owner(&account.data))
+        .map(|owner| owner.to_string());
+      let amount: u64 = balance.amount.amount.parse().unwrap_or_default();
+      let share = match supply {
+        0 => 0.0,
+        supply => amount as f64 / supply as f64 * 100.0,
+      };
+
+      TokenHolder {
+        address: balance.address,
+        owner,
+        amount: balance.amount.amount,
+        ui_amount: balance.amount.ui_amount_string,
+        share,
+      }
+    })
+    .collect();
+
+  Ok(holders)
+}
+
+/// The program loaders, by version.
+const LOADERS: [(Pubkey, u8); 4] = [
+  (bpf_loader_deprecated::ID, 1),
+  (bpf_loader::ID, 2),
+  (bpf_loader_upgradeable::ID, 3),
+  (loader_v4::ID, 4),
+];
+
+/// The size of the loader v4 program header: slot, authority and status.
+const LOADER_V4_HEADER_SIZE: usize = 48;
+
+/// The loader v4 status of a finalized program, which has no authority.
+const LOADER_V4_FINALIZED: u64 = 2;
+
+/// Get the version of a program loader, `None` if it's not a loader.
+pub fn loader_version(owner: &Pubkey) -> Option<u8> {
+  LOADERS
+    .iter()
+    .find(|(loader, _)| loader == owner)
+    .map(|(_, version)| *version)
+}
+
+/// Get the address of the Anchor IDL account of a program.
+pub fn anchor_idl_address(program_id: &Pubkey) -> anyhow::Result<Pubkey> {
+  let (base, _) = Pubkey::find_program_address(&[], program_id);
+  Pubkey::create_with_seed(&base, "anchor:idl", program_id)
+    .map_err(|e| anyhow::anyhow!("Failed to derive IDL address: {}", e))
+}
+
+/// Get the program details of a loader owned account.
+/// Both the program and the ProgramData account of an upgradeable program
+/// can be analyzed.
+/// # Arguments
+/// * `source` - The source to look up the ProgramData and IDL accounts in.
+/// * `address` - The account address.
+/// * `account` - The loader owned account.
+pub fn program_details(
+  source: &dyn AccountSource,
+  address: &Pubkey,
+  account: &Account,
+) -> anyhow::Result<ProgramDetails> {
+  let loader_version =
+    loader_version(&account.owner).ok_or_else(|| anyhow::anyhow!("Not a program account"))?;
+
+  let mut details = ProgramDetails {
+    loader: account.owner.to_string(),
+    loader_version,
+    program_data_address: None,
+    upgrade_authority: None,
+    last_deploy_slot: None,
+    elf_size: account.data.len(),
+    idl_address: String::new(),
+    idl_exists: false,
+  };
+
+  let program_id = match loader_version {
+    3 => upgradeable_program_details(source, address, account, &mut details)?,
+    4 => {
+      if account.data.len() < LOADER_V4_HEADER_SIZE {
+        anyhow::bail!("Invalid loader v4 program account");
+      }
+
+      let slot = u64::from_le_bytes(account.data[0..8].try_into()?);
+      let status = u64::from_le_bytes(account.data[40..48].try_into()?);
+      details.last_deploy_slot = Some(slot);
+      details.elf_size = account.data.len() - LOADER_V4_HEADER_SIZE;
+      if status != LOADER_V4_FINALIZED {
+        details.upgrade_authority = Some(Pubkey::try_from(&account.data[8..40])?.to_string());
+      }
+
+      *address
+    },
+    _ => *address,
+  };
+
+  let idl_address = anchor_idl_address(&program_id)?;
+  details.idl_address = idl_address.to_string();
+  details.idl_exists = source.get_account(&idl_address)?.is_some();
+
+  Ok(details)
+}
+
+/// Fill the details of an upgradeable program from its ProgramData account.
+/// Returns the program id.
+fn upgradeable_program_details(
+  source: &dyn AccountSource,
+  address: &Pubkey,
+  account: &Account,
+  details: &mut ProgramDetails,
+) -> anyhow::Result<Pubkey> {
+  let metadata_size = UpgradeableLoaderState::size_of_programdata_metadata();
+  let state: UpgradeableLoaderState = bincode::deserialize(&account.data)
+    .map_err(|e| anyhow::anyhow!("Invalid upgradeable loader account: {}", e))?;
+
+  let (program_id, program_data_address, program_data) = match state {
+    UpgradeableLoaderState::Program { programdata_address } => {
+      let program_data = source.get_account(&programdata_address)?;
+      (*address, programdata_address, program_data)
+    },
+    UpgradeableLoaderState::ProgramData { .. } => {
+      // The program id isn't stored, so find the program pointing at this account.
+      let filters = vec![
+        RpcFilterType::DataSize(UpgradeableLoaderState::size_of_program() as u64),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(4, address.to_bytes().to_vec())),
+      ];
+      let program_id = source
+        .get_program_accounts(&account.owner, filters)
+        .ok()
+        .and_then(|programs| programs.first().map(|(program_id, _)| *program_id))
+        .unwrap_or(*address);
+      (program_id, *address, Some(account.clone()))
+    },
+    UpgradeableLoaderState::Buffer { .. } => anyhow::bail!("Buffer account, not a program"),
+    UpgradeableLoaderState::Uninitialized => anyhow::bail!("Uninitialized upgradeable loader account"),
+  };
+
+  details.program_data_address = Some(program_data_address.to_string());
+  details.elf_size = 0;
+
+  // A closed program has no ProgramData account anymore.
+  if let Some(program_data) = program_data {
+    if let Ok(UpgradeableLoaderState::ProgramData {
+      slot,
+      upgrade_authority_address,
+    }) = bincode::deserialize(&program_data.data)
+    {
+      details.last_deploy_slot = Some(slot);
+      details.upgrade_authority = upgrade_authority_address.map(|authority| authority.to_string());
+      details.elf_size = program_data.data.len().saturating_sub(metadata_size);
+    }
+  }
+
+  Ok(program_id)
+}
+
+/// Get the token account details of a token account.
+/// # Arguments
+/// * `account` - The token account.
+/// * `decimals` - The decimals of its mint.
+pub fn token_account_details(account: &Account, decimals: u8) -> anyhow::Result<TokenAccountDetails> {
+  let additional_data = SplTokenAdditionalDataV2::with_decimals(decimals);
+  match parse_token_v3(&account.data, Some(&additional_data)) {
+    Ok(TokenAccountType::Account(token_account)) => {
+      let state = match token_account.state {
+        UiAccountState::Uninitialized => "uninitialized",
+        UiAccountState::Initialized => "initialized",
+        UiAccountState::Frozen => "frozen",
+      };
+
+      Ok(TokenAccountDetails {
+        program: account.owner.to_string(),
+        mint: token_account.mint,
+        owner: token_account.owner,
+        amount: token_account.token_amount.amount,
+        ui_amount: token_account.token_amount.ui_amount_string,
+        decimals,
+        delegate: token_account.delegate,
+        delegated_amount: token_account
+          .delegated_amount
+          .map(|amount| amount.ui_amount_string),
+        close_authority: token_account.close_authority,
+        state: state.to_string(),
+        is_native: token_account.is_native,
+        extensions: token_account.extensions,
+      })
+    },
+    Ok(_) => Err(anyhow::anyhow!("Not a token account")),
+    Err(_) => Err(anyhow::anyhow!("Failed to parse token account")),
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::mock_rpc::{
+    MockRpcFixtures,
+    serve_mock_rpc,
+  };
+  use crate::test_support::{
+    account,
+    mint_account,
+    token_account,
+  };
   use tokio::runtime::Runtime;
 
   #[test]
   fn test_get_owners() {
+    let accounts = [
+      "GU7NS9xCwgNPiAdJ69iusFrRfawjDDPjeMBovhV1d4kn",
+      "AKEWE7Bgh87GPp171b4cJPSSZfmZwQ3KaqYqXoKLNAEE",
+    ];
+
+    let mut fixtures = MockRpcFixtures::new();
+    for address in accounts {
+      fixtures.add_account(
+        Pubkey::from_str(address).unwrap(),
+        account(spl_token::ID, vec![0; 82]),
+      );
+    }
+
+    let server = serve_mock_rpc(fixtures, 0).unwrap();
+    let rpc_endpoint: &str = &server.url();
+
     let rt = Runtime::new().unwrap();
     rt.block_on(async {
-      let rpc_endpoint = "https://eclipse.lgns.net/";
-      let accounts = [
-        "GU7NS9xCwgNPiAdJ69iusFrRfawjDDPjeMBovhV1d4kn",
-        "AKEWE7Bgh87GPp171b4cJPSSZfmZwQ3KaqYqXoKLNAEE",
-      ];
-
       let owners = get_owners(rpc_endpoint, &accounts).await.unwrap();
-      assert_eq!(owners.len(), accounts.len());
+      assert_eq!(owners, vec![spl_token::ID.to_string(); accounts.len()]);
     });
   }
+
+  #[test]
+  fn it_should_get_token_account_details() -> anyhow::Result<()> {
+    use solana_sdk::program_option::COption;
+    use solana_sdk::program_pack::Pack as _;
+
+    let (mint, owner, delegate) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut token = token_account(mint, owner, 1_500_000);
+    let mut state = spl_token::state::Account::unpack(&token.data)?;
+    state.delegate = COption::Some(delegate);
+    state.delegated_amount = 500_000;
+    state.state = spl_token::state::AccountState::Frozen;
+    state.pack_into_slice(&mut token.data);
+
+    let details = token_account_details(&token, 6)?;
+    assert_eq!(details.mint, mint.to_string());
+    assert_eq!(details.owner, owner.to_string());
+    assert_eq!(details.amount, "1500000");
+    assert_eq!(details.ui_amount, "1.5");
+    assert_eq!(details.delegate, Some(delegate.to_string()));
+    assert_eq!(details.delegated_amount.as_deref(), Some("0.5"));
+    assert_eq!(details.state, "frozen");
+    assert!(details.extensions.is_empty());
+
+    assert_eq!(mint_details(&mint_account(0, 6))?.decimals, 6);
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_get_largest_holders() -> anyhow::Result<()> {
+    let mint = Pubkey::new_unique();
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_account(mint, mint_account(1_000_000, 6));
+    let mut holders = vec![];
+    for amount in [250_000, 600_000, 150_000] {
+      let (address, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+      fixtures.add_account(address, token_account(mint, owner, amount));
+      holders.push((address, owner));
+    }
+
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let client = RpcClient::new(server.url());
+    let largest = get_largest_holders(&client, &mint, &mint_account(1_000_000, 6), 2)?;
+    assert_eq!(largest.len(), 2);
+    assert_eq!(largest[0].address, holders[1].0.to_string());
+    assert_eq!(largest[0].owner, Some(holders[1].1.to_string()));
+    assert_eq!(largest[0].ui_amount, "0.6");
+    assert_eq!(largest[0].share, 60.0);
+    assert_eq!(largest[1].amount, "250000");
+    assert_eq!(largest[1].share, 25.0);
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_get_upgradeable_program_details() -> anyhow::Result<()> {
+    let loader = bpf_loader_upgradeable::ID;
+    let program_id = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let program_data_address = solana_loader_v3_interface::get_program_data_address(&program_id);
+
+    let program = bincode::serialize(&UpgradeableLoaderState::Program {
+      programdata_address: program_data_address,
+    })?;
+    let mut program_data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+      slot: 42,
+      upgrade_authority_address: Some(authority),
+    })?;
+    program_data.resize(UpgradeableLoaderState::size_of_programdata_metadata() + 1024, 0);
+
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures
+      .add_account(program_id, account(loader, program))
+      .add_account(program_data_address, account(loader, program_data))
+      .add_account(anchor_idl_address(&program_id)?, account(program_id, vec![0; 64]));
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let client = RpcClient::new(server.url());
+
+    for address in [program_id, program_data_address] {
+      let account = client.get_account(&address)?;
+      let details = program_details(&client, &address, &account)?;
+
+      assert_eq!(details.loader_version, 3);
+      assert_eq!(
+        details.program_data_address,
+        Some(program_data_address.to_string())
+      );
+      assert_eq!(details.upgrade_authority, Some(authority.to_string()));
+      assert_eq!(details.last_deploy_slot, Some(42));
+      assert_eq!(details.elf_size, 1024);
+      assert!(details.idl_exists);
+    }
+
+    Ok(())
+  }
 }
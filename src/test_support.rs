@@ -0,0 +1,120 @@
+//! Account fixtures shared by the tests.
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use solana_sdk::account::Account;
+use solana_sdk::program_pack::Pack as _;
+use solana_sdk::pubkey::Pubkey;
+use std::io::Write as _;
+
+use crate::decoders::MetaplexMetadata;
+
+/// Create an account owned by a program, for fixtures in tests.
+/// # Arguments
+/// * `owner` - The program owning the account.
+/// * `data` - The account data.
+pub(crate) fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+  Account {
+    lamports: 1_000_000,
+    data,
+    owner,
+    executable: false,
+    rent_epoch: 0,
+  }
+}
+
+/// Create an initialized SPL Token mint account, for fixtures in tests.
+/// # Arguments
+/// * `supply` - The token supply, in base units.
+/// * `decimals` - The number of decimals.
+pub(crate) fn mint_account(supply: u64, decimals: u8) -> Account {
+  let mut data = vec![0; spl_token::state::Mint::LEN];
+  spl_token::state::Mint {
+    supply,
+    decimals,
+    is_initialized: true,
+    ..Default::default()
+  }
+  .pack_into_slice(&mut data);
+
+  Account {
+    lamports: 1_461_600,
+    data,
+    owner: spl_token::ID,
+    executable: false,
+    rent_epoch: 0,
+  }
+}
+
+/// Create an initialized SPL Token account, for fixtures in tests.
+/// # Arguments
+/// * `mint` - The token mint.
+/// * `owner` - The owner of the tokens.
+/// * `amount` - The token amount, in base units.
+pub(crate) fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+  let mut data = vec![0; spl_token::state::Account::LEN];
+  spl_token::state::Account {
+    mint,
+    owner,
+    amount,
+    state: spl_token::state::AccountState::Initialized,
+    ..Default::default()
+  }
+  .pack_into_slice(&mut data);
+
+  Account {
+    lamports: 2_039_280,
+    data,
+    owner: spl_token::ID,
+    executable: false,
+    rent_epoch: 0,
+  }
+}
+
+/// Encode Metaplex metadata like the Token Metadata program, with padded
+/// strings, for fixtures in tests.
+/// # Arguments
+/// * `metadata` - The metadata, with a collection.
+pub(crate) fn encode_metadata(metadata: &MetaplexMetadata) -> Vec<u8> {
+  let string = |data: &mut Vec<u8>, value: &str, len: usize| {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.resize(len, 0);
+    data.extend((len as u32).to_le_bytes());
+    data.extend(bytes);
+  };
+
+  // The key of a Metadata V1 account.
+  let mut data = vec![4];
+  data.extend(metadata.update_authority.to_bytes());
+  data.extend(metadata.mint.to_bytes());
+  string(&mut data, &metadata.name, 32);
+  string(&mut data, &metadata.symbol, 10);
+  string(&mut data, &metadata.uri, 200);
+  data.extend(metadata.seller_fee_basis_points.to_le_bytes());
+  data.push(1);
+  data.extend((metadata.creators.len() as u32).to_le_bytes());
+  for creator in metadata.creators.iter() {
+    data.extend(creator.address.to_bytes());
+    data.extend([creator.verified as u8, creator.share]);
+  }
+  data.extend([metadata.primary_sale_happened as u8, metadata.is_mutable as u8]);
+  // Edition nonce, token standard and collection.
+  data.extend([1, 255, 1, 4, 1, 1]);
+  data.extend(metadata.collection.as_ref().unwrap().key.to_bytes());
+  data.resize(679, 0);
+  data
+}
+
+/// Encode an IDL like the data of an Anchor IDL account, for fixtures in tests.
+/// # Arguments
+/// * `json` - The IDL JSON.
+pub(crate) fn encode_idl(json: &str) -> anyhow::Result<Vec<u8>> {
+  let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+  encoder.write_all(json.as_bytes())?;
+  let compressed = encoder.finish()?;
+
+  // The discriminator, authority and length of the compressed IDL.
+  let mut data = vec![0; 40];
+  data.extend((compressed.len() as u32).to_le_bytes());
+  data.extend(compressed);
+  Ok(data)
+}
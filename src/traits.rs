@@ -1,3 +1,14 @@
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{
+  RpcAccountInfoConfig,
+  RpcProgramAccountsConfig,
+};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::decoders::DecodedAccount;
+use crate::dump::fetch_accounts;
 use crate::lumos_context::LumosContext;
 
 /// Pull is a trait that defines the behavior of pulling data from a source.
@@ -5,3 +16,86 @@ pub trait Pull {
   fn pull(&self, context: &LumosContext) -> anyhow::Result<()>;
   fn address(&self) -> &str;
 }
+
+/// AccountSource is a trait that defines where decoders look up related
+/// accounts, like the mint of a token account or the ProgramData of a program.
+pub trait AccountSource {
+  /// Get an account, `None` if it doesn't exist.
+  fn get_account(&self, address: &Pubkey) -> anyhow::Result<Option<Account>>;
+
+  /// Get several accounts, in the order of the addresses.
+  fn get_multiple_accounts(&self, addresses: &[Pubkey]) -> anyhow::Result<Vec<Option<Account>>> {
+    addresses
+      .iter()
+      .map(|address| self.get_account(address))
+      .collect()
+  }
+
+  /// Get the accounts owned by a program matching all filters.
+  /// Sources that can't scan accounts return none.
+  fn get_program_accounts(
+    &self,
+    _program_id: &Pubkey,
+    _filters: Vec<RpcFilterType>,
+  ) -> anyhow::Result<Vec<(Pubkey, Account)>> {
+    Ok(vec![])
+  }
+}
+
+/// Implementation of AccountSource for the RPC client.
+impl AccountSource for RpcClient {
+  fn get_account(&self, address: &Pubkey) -> anyhow::Result<Option<Account>> {
+    Ok(
+      self
+        .get_account_with_commitment(address, self.commitment())?
+        .value,
+    )
+  }
+
+  fn get_multiple_accounts(&self, addresses: &[Pubkey]) -> anyhow::Result<Vec<Option<Account>>> {
+    // Duplicate addresses get the same account.
+    let accounts = fetch_accounts(self, &[], addresses)?;
+    Ok(
+      addresses
+        .iter()
+        .map(|address| accounts.get(address).cloned())
+        .collect(),
+    )
+  }
+
+  fn get_program_accounts(
+    &self,
+    program_id: &Pubkey,
+    filters: Vec<RpcFilterType>,
+  ) -> anyhow::Result<Vec<(Pubkey, Account)>> {
+    let config = RpcProgramAccountsConfig {
+      filters: Some(filters),
+      account_config: RpcAccountInfoConfig {
+        commitment: Some(self.commitment()),
+        ..RpcAccountInfoConfig::default()
+      },
+      ..RpcProgramAccountsConfig::default()
+    };
+
+    Ok(self.get_program_accounts_with_config(program_id, config)?)
+  }
+}
+
+/// AccountDecoder is a trait that defines how `analyze` decodes an account.
+/// Decoders are registered in a `DecoderRegistry`, which picks the first one
+/// that can decode an account.
+pub trait AccountDecoder {
+  /// The name of the decoder.
+  fn name(&self) -> &str;
+
+  /// Check if the decoder handles the account, usually by its owner.
+  fn can_decode(&self, address: &Pubkey, account: &Account) -> bool;
+
+  /// Decode the account, looking up related accounts from the source.
+  fn decode(
+    &self,
+    address: &Pubkey,
+    account: &Account,
+    source: &dyn AccountSource,
+  ) -> anyhow::Result<DecodedAccount>;
+}
@@ -4,21 +4,54 @@ use serde::{
   Deserialize,
   Serialize,
 };
-use std::fs;
+use signal_hook::consts::SIGTERM;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
 use std::io::{
   BufRead as _,
   BufReader,
 };
 use std::net::TcpListener;
+use std::os::fd::AsRawFd as _;
+use std::os::unix::process::CommandExt as _;
 use std::path::Path;
 use std::process::{
+  Child,
   Command,
   Stdio,
 };
+use std::str::FromStr as _;
+use std::sync::{
+  Arc,
+  Mutex,
+};
+use std::time::Duration;
+use std::{
+  fs,
+  thread,
+};
 use which::which;
 
+use crate::connection::{
+  ConnectionInfo,
+  connection_dir,
+};
 use crate::file::ToUtf8;
-use crate::lumos_context::LumosContext;
+use crate::hooks::run_post_start_hooks;
+use crate::log_file::RotatingLog;
+use crate::lumos_context::{
+  LumosContext,
+  ValidatorPorts,
+};
+use crate::program_logs::subscribe_program_logs;
+use crate::schema::Backend;
+use crate::signals::{
+  ShutdownSignals,
+  ValidatorExit,
+  stop_child,
+  wait_forwarding_signals,
+};
 use crate::traits::Pull as _;
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -30,12 +63,122 @@ struct AccountDataRoot {
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct AccountData {
-  pub lamports: i64,
+  pub lamports: u64,
   pub data: Vec<String>,
   pub owner: String,
   pub executable: bool,
-  pub rent_epoch: i64,
-  pub space: i64,
+  pub rent_epoch: u64,
+  pub space: u64,
+}
+
+/// Write an account to a JSON file in the same format as `solana account --output json`.
+/// The file is named `{address}.json` so the directory can be passed to `--account-dir`.
+/// # Arguments
+/// * `out_dir` - The directory to write the account file to.
+/// * `address` - The public key address of the account.
+/// * `account` - The account to write.
+pub fn write_account_json(out_dir: &Path, address: &Pubkey, account: &Account) -> anyhow::Result<()> {
+  let root = AccountDataRoot {
+    pubkey: address.to_string(),
+    account: AccountData {
+      lamports: account.lamports,
+      data: vec![BASE64_STANDARD.encode(&account.data), "base64".into()],
+      owner: account.owner.to_string(),
+      executable: account.executable,
+      rent_epoch: account.rent_epoch,
+      space: account.data.len() as u64,
+    },
+  };
+
+  if !out_dir.exists() {
+    fs::create_dir_all(out_dir)?;
+  }
+
+  let out_file = out_dir.join(format!("{address}.json"));
+  fs::write(out_file, serde_json::to_string_pretty(&root)?)?;
+  Ok(())
+}
+
+/// Read an account from a JSON file in the format of `solana account --output json`.
+/// Returns the address and the account.
+/// # Arguments
+/// * `path` - The path of the account file.
+pub fn read_account_json(path: &Path) -> anyhow::Result<(Pubkey, Account)> {
+  let contents = fs::read_to_string(path)?;
+  let root: AccountDataRoot =
+    serde_json::from_str(&contents).with_context(|| format!("Failed to parse account file: {path:?}"))?;
+
+  let address = Pubkey::from_str(&root.pubkey)?;
+  let data = match root.account.data.as_slice() {
+    [data, encoding] if encoding == "base64" => BASE64_STANDARD.decode(data)?,
+    _ => anyhow::bail!("Unsupported account data encoding in {path:?}"),
+  };
+
+  let account = Account {
+    lamports: root.account.lamports,
+    data,
+    owner: Pubkey::from_str(&root.account.owner)?,
+    executable: root.account.executable,
+    rent_epoch: root.account.rent_epoch,
+  };
+
+  Ok((address, account))
+}
+
+/// Recursively copy a directory.
+/// Entries that are neither files nor directories, like sockets, are skipped.
+/// # Arguments
+/// * `src` - The directory to copy from.
+/// * `dst` - The directory to copy to.
+pub fn copy_dir_all(src: &Path, dst: &Path) -> anyhow::Result<()> {
+  fs::create_dir_all(dst)?;
+
+  for entry in fs::read_dir(src)? {
+    let entry = entry?;
+    let file_type = entry.file_type()?;
+    let target = dst.join(entry.file_name());
+    if file_type.is_dir() {
+      copy_dir_all(&entry.path(), &target)?;
+    } else if file_type.is_file() {
+      fs::copy(entry.path(), target)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// The lock file inside the cache directory.
+const CACHE_LOCK_FILE: &str = "lumos.lock";
+
+/// CacheLock holds an exclusive lock on a cache directory, so processes
+/// sharing it don't clone into it at the same time. It's released on drop.
+pub struct CacheLock {
+  _file: fs::File,
+}
+
+/// Implementation of CacheLock.
+impl CacheLock {
+  /// Wait until the exclusive lock on a cache directory is acquired.
+  pub fn acquire(cache_dir: &Path) -> anyhow::Result<Self> {
+    fs::create_dir_all(cache_dir)?;
+    let path = cache_dir.join(CACHE_LOCK_FILE);
+    let file = fs::OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .open(&path)
+      .with_context(|| format!("Failed to open cache lock: {path:?}"))?;
+
+    // SAFETY: the descriptor is owned by `file`, which is kept open as long as
+    // the lock is held.
+    let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) };
+    if result != 0 {
+      let err = std::io::Error::last_os_error();
+      anyhow::bail!("Failed to lock cache {cache_dir:?}: {err}");
+    }
+
+    Ok(Self { _file: file })
+  }
 }
 
 /// Clone an account.
@@ -212,24 +355,52 @@ pub fn clone_program(context: &LumosContext, address: &str, update: bool) -> any
   Ok(())
 }
 
-/// Start a validator.
-pub fn validator(context: &LumosContext, reset: bool) -> anyhow::Result<()> {
+/// Start a validator and wait for it to exit.
+/// SIGINT and SIGTERM are forwarded to the validator while waiting. They are
+/// caught before the validator is spawned, as it runs in its own process group
+/// and would be orphaned if lumos-svm was terminated.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `reset` - Whether to reset the ledger.
+/// * `program_logs` - Pretty-print program logs, optionally only for the given
+///   programs, see `on_validator_ready`.
+pub fn validator(
+  context: &LumosContext,
+  reset: bool,
+  program_logs: Option<&[String]>,
+) -> anyhow::Result<ValidatorExit> {
+  let signals = ShutdownSignals::register()?;
+  let mut child = spawn_validator(context, reset)?;
+  start_validator_tasks(context, &mut child, &signals, program_logs)?;
+
+  let exit = wait_forwarding_signals(&mut child, &signals)?;
+  on_validator_stopped(context)?;
+  if let ValidatorExit::Stopped { killed: true, .. } = exit {
+    invalidate_ledger(context)?;
+  }
+
+  Ok(exit)
+}
+
+/// Spawn a validator without waiting for it.
+/// The accounts and programs are pulled before the validator is spawned.
+/// The validator output is always written to the rotating validator log file,
+/// and its stdout is only mirrored to the terminal in verbose mode.
+pub fn spawn_validator(context: &LumosContext, reset: bool) -> anyhow::Result<Child> {
   let solana_test_validator_cmd =
     which("solana-test-validator").with_context(|| "Failed to find solana-test-validator command")?;
 
-  let stdout = get_tty_output!(context.verbose);
+  let stdout = Stdio::piped();
   let stderr = Stdio::piped();
 
   let rpc_endpoint: &str = &context.rpc_endpoint();
-  let ledger_dir: &str = &context
-    .config
-    .general
-    .ledger_dir
-    .clone()
-    .unwrap_or(".lumos-ledger".into());
+  let ledger_dir: &str = &context.ledger_dir();
 
+  // Run the validator in its own process group, so signals from the terminal
+  // only reach it when forwarded.
   let mut cmd = Command::new(solana_test_validator_cmd);
   cmd
+    .process_group(0)
     .stdout(stdout)
     .stderr(stderr)
     .arg("--url")
@@ -237,16 +408,49 @@ pub fn validator(context: &LumosContext, reset: bool) -> anyhow::Result<()> {
     .arg("--ledger")
     .arg(ledger_dir);
 
-  // Check if the rpc port is available, if not, then use a different port.
-  if !is_validator_port_available(8899) {
-    cmd.arg("--rpc-port").arg("8900");
+  // Use the requested ports, otherwise fall back to different ports if the defaults are taken.
+  let ports = match context.ports {
+    Some(ports) => ports,
+    None => {
+      let mut ports = ValidatorPorts::default();
+
+      // Check if the rpc port is available, if not, then use a different port.
+      if !is_validator_port_available(ports.rpc) {
+        ports.rpc = 8900;
+      }
+
+      // Check if faucet port is available, if not, then use a different port.
+      if !is_validator_port_available(ports.faucet) {
+        ports.faucet = 9901;
+      }
+
+      ports
+    },
+  };
+
+  cmd
+    .arg("--rpc-port")
+    .arg(ports.rpc.to_string())
+    .arg("--faucet-port")
+    .arg(ports.faucet.to_string());
+
+  if let Some(gossip) = ports.gossip {
+    cmd.arg("--gossip-port").arg(gossip.to_string());
   }
 
-  // Check if faucet port is available, if not, then use a different port.
-  if !is_validator_port_available(9900) {
-    cmd.arg("--faucet-port").arg("9901");
+  if let Some((start, end)) = ports.dynamic_port_range {
+    cmd.arg("--dynamic-port-range").arg(format!("{start}-{end}"));
   }
 
+  context.set_local_ports(ports)?;
+
+  // Prepare the ledger, this will reset it if the config changed since it was created.
+  prepare_ledger(context, reset)?;
+
+  // Validators started in parallel share the cache, so they clone one at a time.
+  let cache_dir: &str = &context.cache_dir();
+  let cache_lock = CacheLock::acquire(Path::new(cache_dir))?;
+
   // Process the accounts
   let account_cache_dir: &str = &context.account_cache_dir()?;
   let account_cache_dir = Path::new(account_cache_dir);
@@ -257,9 +461,18 @@ pub fn validator(context: &LumosContext, reset: bool) -> anyhow::Result<()> {
     account.pull(context)?;
   }
 
-  // Add the accounts to the validator.
+  // Add the accounts to the validator. Accounts restored from a snapshot are
+  // added last, so they take precedence over the cached ones.
   cmd.arg("--account-dir").arg(account_cache_dir);
 
+  let restored_dir: &str = &context.restored_account_dir()?;
+  if Path::new(restored_dir).exists() {
+    let _ = context
+      .pb
+      .println("Loading accounts restored from a snapshot, run `lumos-svm snapshot discard` to drop them");
+    cmd.arg("--account-dir").arg(restored_dir);
+  }
+
   // Process the programs and add them to the validator.
   for (_, program) in context.config.program.iter() {
     let address: &str = &program.address;
@@ -267,13 +480,14 @@ pub fn validator(context: &LumosContext, reset: bool) -> anyhow::Result<()> {
     // Pull the program, if any.
     program.pull(context)?;
 
-    let cache_dir: &str = &context.program_cache_dir()?;
-    let cache_dir = Path::new(cache_dir);
-    let out_filename: &str = &format!("{address}.so");
-    let out_file = cache_dir.join(out_filename);
+    let out_file = program.program_file(context)?;
 
-    // If it doesn't exist, then skip.
+    // If it doesn't exist, then skip. A missing local program is an error.
     if !out_file.exists() {
+      if program.path.is_some() {
+        anyhow::bail!("Program binary not found: {out_file:?}");
+      }
+
       continue;
     }
 
@@ -291,20 +505,238 @@ pub fn validator(context: &LumosContext, reset: bool) -> anyhow::Result<()> {
     }
   }
 
-  if reset {
-    cmd.arg("--reset");
+  drop(cache_lock);
+
+  // Open the log file after the ledger is prepared, as a reset wipes it.
+  let log_file: &str = &context.validator_log_file()?;
+  let log = RotatingLog::open(
+    Path::new(log_file),
+    context.log_max_size(),
+    context.log_retention(),
+  )
+  .with_context(|| format!("Failed to open validator log file: {log_file}"))?;
+  let log = Arc::new(Mutex::new(log));
+
+  let mut child = cmd.spawn()?;
+
+  handle_tty_output!(child.stdout, context, log, context.verbose);
+  handle_tty_output!(child.stderr, context, log, true);
+
+  Ok(child)
+}
+
+/// Check if a validator is answering on the given RPC endpoint.
+pub fn is_validator_running(rpc_endpoint: &str) -> bool {
+  let client = RpcClient::new_with_timeout(rpc_endpoint, Duration::from_secs(2));
+  client.get_health().is_ok()
+}
+
+/// Wait until the local validator answers health checks.
+/// Returns false if the validator exited or a shutdown signal was received first.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `child` - The validator process.
+/// * `signals` - The registered shutdown signals.
+pub fn wait_for_validator(
+  context: &LumosContext,
+  child: &mut Child,
+  signals: &ShutdownSignals,
+) -> anyhow::Result<bool> {
+  let rpc_url: &str = &context.local_rpc_url();
+  loop {
+    if child.try_wait()?.is_some() || signals.received().is_some() {
+      return Ok(false);
+    }
+
+    if is_validator_running(rpc_url) {
+      return Ok(true);
+    }
+
+    thread::sleep(Duration::from_millis(500));
   }
+}
 
-  let mut cmd = cmd.spawn()?;
+/// Wait for a spawned validator to be ready and run the tasks that need it.
+/// The validator is stopped if waiting or a task fails, so it isn't left
+/// running without lumos-svm.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `child` - The validator process.
+/// * `signals` - The registered shutdown signals.
+/// * `program_logs` - Pretty-print program logs, see `on_validator_ready`.
+pub fn start_validator_tasks(
+  context: &LumosContext,
+  child: &mut Child,
+  signals: &ShutdownSignals,
+  program_logs: Option<&[String]>,
+) -> anyhow::Result<()> {
+  let started = wait_for_validator(context, child, signals).and_then(|ready| match ready {
+    true => on_validator_ready(context, program_logs),
+    false => Ok(()),
+  });
+
+  if let Err(err) = started {
+    stop_child(child, SIGTERM)?;
+    on_validator_stopped(context)?;
+    return Err(err);
+  }
 
-  if context.verbose {
-    handle_tty_output!(cmd.stdout, context);
+  Ok(())
+}
+
+/// Clean up after the validator stopped, removing the connection details so
+/// tools don't connect to a validator that is gone.
+pub fn on_validator_stopped(context: &LumosContext) -> anyhow::Result<()> {
+  ConnectionInfo::remove(&connection_dir(&context.config))
+    .with_context(|| "Failed to remove connection details")
+}
+
+/// Run the tasks that need a ready validator, like the post start hooks.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `program_logs` - Pretty-print program logs, optionally only for the given
+///   programs (config names or addresses). Disabled if `None`.
+pub fn on_validator_ready(context: &LumosContext, program_logs: Option<&[String]>) -> anyhow::Result<()> {
+  let _ = context
+    .pb
+    .println(format!("Validator is ready at {}", context.local_rpc_url()));
+
+  // Export the connection details for test frameworks.
+  ConnectionInfo::from_context(context)
+    .write(&connection_dir(&context.config))
+    .with_context(|| "Failed to write connection details")?;
+
+  if let Some(filter) = program_logs {
+    subscribe_program_logs(context, filter)?;
   }
-  handle_tty_output!(cmd.stderr, context);
 
-  let status = cmd.wait()?;
-  if !status.success() {
-    anyhow::bail!("Failed to start validator");
+  run_ledger_hooks(context)?;
+
+  Ok(())
+}
+
+/// Run the post start hooks once per ledger. Hooks usually seed state, which a
+/// ledger kept with `--keep-ledger`, `reset = false` or across watch restarts
+/// already has. The in-process backend has no ledger and always runs them.
+pub(crate) fn run_ledger_hooks(context: &LumosContext) -> anyhow::Result<()> {
+  if context.backend() == Backend::Inprocess {
+    return run_post_start_hooks(context);
+  }
+
+  let ledger_dir: &str = &context.ledger_dir();
+  let hooks_file = Path::new(ledger_dir).join(LEDGER_HOOKS_FILE);
+  if hooks_file.exists() {
+    if !context.config.hooks.post_start.is_empty() {
+      let _ = context
+        .pb
+        .println("Skipping post start hooks, they already ran against the kept ledger");
+    }
+
+    return Ok(());
+  }
+
+  run_post_start_hooks(context)?;
+  fs::write(hooks_file, "")?;
+  Ok(())
+}
+
+/// The file inside the ledger directory that holds the config fingerprint.
+const LEDGER_STATE_FILE: &str = "lumos-state";
+
+/// The file inside the ledger directory marking that the post start hooks ran.
+const LEDGER_HOOKS_FILE: &str = "lumos-hooks";
+
+/// The file inside the ledger directory the validator holds locked while running.
+pub(crate) const LEDGER_LOCK_FILE: &str = "ledger.lock";
+
+/// The genesis file the validator writes when initializing a ledger directory.
+pub(crate) const LEDGER_GENESIS_FILE: &str = "genesis.bin";
+
+/// Prepare the ledger directory before starting the validator.
+/// The ledger is wiped if a reset is requested or if the configured accounts
+/// and programs changed since the ledger was created.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `reset` - Whether to reset the ledger.
+fn prepare_ledger(context: &LumosContext, reset: bool) -> anyhow::Result<()> {
+  let ledger_dir: &str = &context.ledger_dir();
+  let ledger_dir = Path::new(ledger_dir);
+  let state_file = ledger_dir.join(LEDGER_STATE_FILE);
+  let fingerprint = context.config.fingerprint();
+
+  let mut reset = reset;
+  if !reset && ledger_dir.exists() {
+    let previous = fs::read_to_string(&state_file).unwrap_or_default();
+    if previous != fingerprint {
+      let msg = "Ledger was not shut down cleanly or configured accounts or programs changed since it was created, forcing a reset";
+      log::warn!("{msg}");
+      let _ = context.pb.println(format!("Warning: {msg}."));
+      reset = true;
+    }
+  }
+
+  // The reset is done here instead of passing `--reset` to the validator,
+  // as the validator would also wipe the state file. Unlike the validator,
+  // this doesn't honor the ledger lock, so check it first.
+  if reset && ledger_dir.exists() {
+    if is_ledger_locked(ledger_dir)? || is_validator_running(&context.local_rpc_url()) {
+      anyhow::bail!("Refusing to reset ledger {ledger_dir:?}, a validator is still running on it");
+    }
+
+    if !is_ledger_dir(ledger_dir)? {
+      anyhow::bail!("Refusing to reset {ledger_dir:?}, it is not empty and doesn't look like a ledger");
+    }
+
+    fs::remove_dir_all(ledger_dir).with_context(|| format!("Failed to reset ledger: {ledger_dir:?}"))?;
+  }
+
+  fs::create_dir_all(ledger_dir)?;
+  fs::write(state_file, fingerprint)?;
+
+  Ok(())
+}
+
+/// Check if a directory is a ledger, or empty, and so safe to reset.
+/// A ledger has either been prepared by lumos-svm or initialized by the validator.
+pub(crate) fn is_ledger_dir(ledger_dir: &Path) -> anyhow::Result<bool> {
+  let is_empty = fs::read_dir(ledger_dir)?.next().is_none();
+  Ok(is_empty || ledger_dir.join(LEDGER_STATE_FILE).exists() || ledger_dir.join(LEDGER_GENESIS_FILE).exists())
+}
+
+/// Check if a validator holds the lock of a ledger directory.
+pub(crate) fn is_ledger_locked(ledger_dir: &Path) -> anyhow::Result<bool> {
+  let path = ledger_dir.join(LEDGER_LOCK_FILE);
+  if !path.exists() {
+    return Ok(false);
+  }
+
+  let file = fs::OpenOptions::new()
+    .read(true)
+    .write(true)
+    .open(&path)
+    .with_context(|| format!("Failed to open ledger lock: {path:?}"))?;
+
+  // SAFETY: the descriptor is owned by `file`, closing it releases the lock.
+  let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+  if result != 0 {
+    let err = std::io::Error::last_os_error();
+    if err.kind() == std::io::ErrorKind::WouldBlock {
+      return Ok(true);
+    }
+
+    anyhow::bail!("Failed to check ledger lock {path:?}: {err}");
+  }
+
+  Ok(false)
+}
+
+/// Invalidate the ledger state, so the next run without a reset resets the
+/// ledger anyway. This is used when the validator had to be killed.
+pub fn invalidate_ledger(context: &LumosContext) -> anyhow::Result<()> {
+  let ledger_dir: &str = &context.ledger_dir();
+  let state_file = Path::new(ledger_dir).join(LEDGER_STATE_FILE);
+  if state_file.exists() {
+    fs::remove_file(state_file)?;
   }
 
   Ok(())
@@ -314,7 +746,7 @@ pub fn validator(context: &LumosContext, reset: bool) -> anyhow::Result<()> {
 /// Returns true if the port is available, false otherwise.
 /// # Arguments
 /// * `port` - The port to check.
-fn is_validator_port_available(port: u16) -> bool {
+pub(crate) fn is_validator_port_available(port: u16) -> bool {
   TcpListener::bind(("0.0.0.0", port)).is_ok()
 }
 
@@ -336,6 +768,10 @@ fn is_valid_solana_address(address: &str) -> bool {
 mod tests {
   use std::sync::Arc;
 
+  use crate::mock_rpc::{
+    MockRpcFixtures,
+    serve_mock_rpc,
+  };
   use crate::schema::ConfigRoot;
 
   use super::*;
@@ -343,11 +779,26 @@ mod tests {
 
   #[test]
   fn it_should_clone_account_and_output_json_file() -> anyhow::Result<()> {
+    if !has_solana_cli() {
+      return Ok(());
+    }
+
     let temp_dir = TempDir::new()?;
     let address = "AKEWE7Bgh87GPp171b4cJPSSZfmZwQ3KaqYqXoKLNAEE";
     let cache_dir = temp_dir.path();
     let config = Arc::new(ConfigRoot::default());
-    let rpc_endpoint = "https://eclipse.lgns.net/";
+
+    let mut fixtures = MockRpcFixtures::new();
+    let account = Account {
+      lamports: 1_461_600,
+      data: vec![0; 82],
+      owner: spl_token::ID,
+      executable: false,
+      rent_epoch: 0,
+    };
+    fixtures.add_account(Pubkey::from_str(address)?, account);
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let rpc_endpoint: &str = &server.url();
 
     let context = LumosContext::new(
       config,
@@ -364,13 +815,118 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn it_should_read_written_account_json() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let address = Pubkey::new_unique();
+    let account = Account {
+      lamports: 1_461_600,
+      data: vec![1, 2, 3],
+      owner: Pubkey::new_unique(),
+      executable: false,
+      rent_epoch: u64::MAX,
+    };
+
+    write_account_json(temp_dir.path(), &address, &account)?;
+    let (read_address, read_account) = read_account_json(&temp_dir.path().join(format!("{address}.json")))?;
+    assert_eq!(read_address, address);
+    assert_eq!(read_account, account);
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_copy_dir_recursively() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let src = temp_dir.path().join("src");
+    let dst = temp_dir.path().join("dst");
+    fs::create_dir_all(src.join("nested"))?;
+    fs::write(src.join("genesis.bin"), "genesis")?;
+    fs::write(src.join("nested").join("data"), "data")?;
+
+    copy_dir_all(&src, &dst)?;
+
+    assert_eq!(fs::read_to_string(dst.join("genesis.bin"))?, "genesis");
+    assert_eq!(fs::read_to_string(dst.join("nested").join("data"))?, "data");
+    Ok(())
+  }
+
+  /// Check if the `solana` CLI the clone tests shell out to is installed,
+  /// so they are skipped on machines without it.
+  fn has_solana_cli() -> bool {
+    let found = which("solana").is_ok();
+    if !found {
+      eprintln!("Skipping, the solana command is not installed");
+    }
+    found
+  }
+
+  fn ledger_context(ledger_dir: &Path) -> anyhow::Result<LumosContext> {
+    let mut config = ConfigRoot::default();
+    config.general.ledger_dir = Some(ledger_dir.to_utf8()?.into());
+    let context = LumosContext::new(Arc::new(config), "http://localhost:8899", None, false);
+
+    // Nothing listens on port 1, so no validator is found running.
+    context.set_local_ports(ValidatorPorts {
+      rpc: 1,
+      ..ValidatorPorts::default()
+    })?;
+    Ok(context)
+  }
+
+  #[test]
+  fn it_should_only_reset_ledger_dirs() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let ledger_dir = temp_dir.path().join("ledger");
+    fs::create_dir_all(&ledger_dir)?;
+    fs::write(ledger_dir.join("notes.txt"), "keep me")?;
+    let context = ledger_context(&ledger_dir)?;
+
+    assert!(prepare_ledger(&context, true).is_err());
+    assert!(ledger_dir.join("notes.txt").exists());
+
+    fs::write(ledger_dir.join(LEDGER_GENESIS_FILE), "genesis")?;
+    prepare_ledger(&context, true)?;
+    assert!(!ledger_dir.join("notes.txt").exists());
+    assert!(ledger_dir.join(LEDGER_STATE_FILE).exists());
+    Ok(())
+  }
+
+  #[test]
+  fn it_should_refuse_to_reset_locked_ledger() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let ledger_dir = temp_dir.path().join("ledger");
+    fs::create_dir_all(&ledger_dir)?;
+    fs::write(ledger_dir.join(LEDGER_GENESIS_FILE), "genesis")?;
+    let context = ledger_context(&ledger_dir)?;
+
+    // Hold the ledger lock like a running validator.
+    let lock = fs::File::create(ledger_dir.join(LEDGER_LOCK_FILE))?;
+    assert_eq!(unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX) }, 0);
+
+    assert!(prepare_ledger(&context, true).is_err());
+    assert!(ledger_dir.join(LEDGER_GENESIS_FILE).exists());
+
+    drop(lock);
+    prepare_ledger(&context, true)?;
+    assert!(!ledger_dir.join(LEDGER_GENESIS_FILE).exists());
+    Ok(())
+  }
+
   #[test]
   fn it_should_clone_program_and_output_so_file() -> anyhow::Result<()> {
+    if !has_solana_cli() {
+      return Ok(());
+    }
+
     let temp_dir = TempDir::new()?;
     let address = "br1xwubggTiEZ6b7iNZUwfA3psygFfaXGfZ1heaN9AW";
     let cache_dir = temp_dir.path();
     let config = Arc::new(ConfigRoot::default());
-    let rpc_endpoint = "https://eclipse.lgns.net/";
+
+    let mut fixtures = MockRpcFixtures::new();
+    fixtures.add_program(Pubkey::from_str(address)?, b"\x7fELF program")?;
+    let server = serve_mock_rpc(fixtures, 0)?;
+    let rpc_endpoint: &str = &server.url();
 
     let context = LumosContext::new(
       config,
@@ -383,7 +939,7 @@ mod tests {
 
     let out_filename: &str = &format!("{address}.so");
     let out_file = cache_dir.join("programs").join(out_filename);
-    assert!(out_file.exists());
+    assert_eq!(fs::read(out_file)?, b"\x7fELF program");
     Ok(())
   }
 }
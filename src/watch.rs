@@ -0,0 +1,262 @@
+use hashbrown::HashMap;
+use signal_hook::consts::SIGTERM;
+use std::path::{
+  Path,
+  PathBuf,
+};
+use std::process::Child;
+use std::sync::Arc;
+use std::time::{
+  Duration,
+  SystemTime,
+};
+use std::{
+  fs,
+  thread,
+};
+
+use crate::lumos_context::LumosContext;
+use crate::schema::ConfigRoot;
+use crate::signals::{
+  ShutdownSignals,
+  ValidatorExit,
+  stop_child,
+};
+use crate::utils::{
+  clone_program,
+  invalidate_ledger,
+  on_validator_stopped,
+  spawn_validator,
+  start_validator_tasks,
+};
+
+/// The interval between checks for changed files.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The quiet period to wait for after a change, so bursts of changes
+/// only restart the validator once.
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// FileWatcher polls a set of files for changes of their modification time.
+pub struct FileWatcher {
+  files: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+/// Implementation of FileWatcher.
+impl FileWatcher {
+  /// Create a new FileWatcher for the given paths.
+  pub fn new(paths: Vec<PathBuf>) -> Self {
+    let files = paths
+      .into_iter()
+      .map(|path| {
+        let modified = modified_time(&path);
+        (path, modified)
+      })
+      .collect();
+
+    Self { files }
+  }
+
+  /// Check if any of the files changed since the last check.
+  /// A file that is created or removed also counts as a change.
+  pub fn changed(&mut self) -> bool {
+    let mut changed = false;
+    for (path, last_modified) in self.files.iter_mut() {
+      let modified = modified_time(path);
+      if modified != *last_modified {
+        *last_modified = modified;
+        changed = true;
+      }
+    }
+
+    changed
+  }
+}
+
+/// Get the modification time of a file, if it exists.
+fn modified_time(path: &Path) -> Option<SystemTime> {
+  fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Get the paths to watch, the config file and the local program binaries.
+fn watched_paths(config_file: &str, config: &ConfigRoot) -> Vec<PathBuf> {
+  let programs = config
+    .program
+    .values()
+    .filter_map(|program| program.path.as_ref().map(PathBuf::from));
+
+  std::iter::once(PathBuf::from(config_file))
+    .chain(programs)
+    .collect()
+}
+
+/// Run the validator and restart it whenever the config file or a local
+/// program binary changes. Entries that were added or changed in the config,
+/// or have `update = true`, are cloned again before the restart.
+/// The ledger is only kept across restarts if `keep_ledger` is set or
+/// `general.reset` is disabled in the current config.
+/// Watching stops when SIGINT or SIGTERM is received, which is forwarded to
+/// the validator.
+/// # Arguments
+/// * `context` - The lumos context.
+/// * `config_file` - The config file to watch and reload.
+/// * `keep_ledger` - Whether to keep the ledger regardless of `general.reset`.
+/// * `program_logs` - Pretty-print program logs, see `on_validator_ready`.
+pub fn watch_validator(
+  context: LumosContext,
+  config_file: &str,
+  keep_ledger: bool,
+  program_logs: Option<&[String]>,
+) -> anyhow::Result<ValidatorExit> {
+  let mut context = context;
+  let mut watcher = FileWatcher::new(watched_paths(config_file, &context.config));
+  let signals = ShutdownSignals::register()?;
+  let mut child = Some(start_validator(&context, keep_ledger, &signals, program_logs)?);
+
+  loop {
+    thread::sleep(POLL_INTERVAL);
+
+    if let Some(signal) = signals.received() {
+      let killed = match child.as_mut() {
+        Some(process) => stop_child(process, signal)?,
+        None => false,
+      };
+
+      if killed {
+        invalidate_ledger(&context)?;
+      }
+
+      on_validator_stopped(&context)?;
+      return Ok(ValidatorExit::Stopped { signal, killed });
+    }
+
+    // Report a validator that exited on its own, and wait for the next change.
+    if let Some(process) = child.as_mut() {
+      if let Some(status) = process.try_wait()? {
+        let _ = context
+          .pb
+          .println(format!("Validator exited with {status}, waiting for changes..."));
+        on_validator_stopped(&context)?;
+        child = None;
+      }
+    }
+
+    if !watcher.changed() {
+      continue;
+    }
+
+    // Wait for the burst of changes to settle.
+    thread::sleep(DEBOUNCE_INTERVAL);
+    while watcher.changed() {
+      thread::sleep(DEBOUNCE_INTERVAL);
+    }
+
+    let config = match ConfigRoot::from_file(config_file) {
+      Ok(config) => Arc::new(config),
+      Err(err) => {
+        let _ = context.pb.println(format!("Failed to reload config: {err:#}"));
+        continue;
+      },
+    };
+
+    let _ = context.pb.println("Change detected, restarting validator...");
+
+    if let Some(mut process) = child.take() {
+      if stop_child(&mut process, SIGTERM)? {
+        invalidate_ledger(&context)?;
+      }
+
+      on_validator_stopped(&context)?;
+    }
+
+    let previous = context.config.clone();
+    let rpc_endpoint: &str = &config.general.rpc_endpoint;
+    let cache_dir = config.general.cache_dir.clone();
+    let mut reloaded = LumosContext::new(config.clone(), rpc_endpoint, cache_dir, context.verbose);
+    reloaded.pb = context.pb.clone();
+    reloaded.ports = context.ports;
+    context = reloaded;
+    watcher = FileWatcher::new(watched_paths(config_file, &context.config));
+
+    let restarted = reclone_changed(&context, &previous)
+      .and_then(|_| start_validator(&context, keep_ledger, &signals, program_logs));
+    match restarted {
+      Ok(process) => child = Some(process),
+      Err(err) => {
+        let _ = context.pb.println(format!(
+          "Failed to restart validator: {err:#}, waiting for changes..."
+        ));
+      },
+    }
+  }
+}
+
+/// Spawn the validator and run the tasks that need it ready.
+/// The ledger is reset according to the `general.reset` of the current config.
+fn start_validator(
+  context: &LumosContext,
+  keep_ledger: bool,
+  signals: &ShutdownSignals,
+  program_logs: Option<&[String]>,
+) -> anyhow::Result<Child> {
+  let reset = !keep_ledger && context.config.general.reset.unwrap_or(true);
+  let mut child = spawn_validator(context, reset)?;
+  start_validator_tasks(context, &mut child, signals, program_logs)?;
+  Ok(child)
+}
+
+/// Clone the accounts and programs that were added or changed compared to the
+/// previous config, and the ones with `update = true`, which are refreshed on
+/// every restart like `clone` does. Local programs are not cloned.
+fn reclone_changed(context: &LumosContext, previous: &ConfigRoot) -> anyhow::Result<()> {
+  for (name, account) in context.config.account.iter() {
+    let changed = match previous.account.get(name) {
+      Some(prev) => {
+        prev.address != account.address
+          || prev.mint != account.mint
+          || prev.clone_metadata != account.clone_metadata
+      },
+      None => true,
+    };
+
+    if changed || account.update.unwrap_or(false) {
+      account.clone_accounts(context, true)?;
+    }
+  }
+
+  for (name, program) in context.config.program.iter() {
+    let changed = match previous.program.get(name) {
+      Some(prev) => prev.address != program.address || prev.path != program.path,
+      None => true,
+    };
+
+    if (changed || program.update.unwrap_or(false)) && program.path.is_none() {
+      clone_program(context, &program.address, true)?;
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use assert_fs::TempDir;
+
+  #[test]
+  fn it_should_detect_changed_files() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let program = temp_dir.path().join("program.so");
+
+    let mut watcher = FileWatcher::new(vec![program.clone()]);
+    assert!(!watcher.changed());
+
+    fs::write(&program, "elf")?;
+    assert!(watcher.changed());
+    assert!(!watcher.changed());
+
+    fs::remove_file(&program)?;
+    assert!(watcher.changed());
+    Ok(())
+  }
+}